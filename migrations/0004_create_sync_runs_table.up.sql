@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS sync_runs (
+  id SERIAL PRIMARY KEY,
+  dataset TEXT NOT NULL,
+  pool TEXT,
+  provider TEXT NOT NULL,
+  status TEXT NOT NULL,
+  started_at TIMESTAMPTZ NOT NULL,
+  finished_at TIMESTAMPTZ,
+  pages_fetched INTEGER NOT NULL DEFAULT 0,
+  rows_inserted BIGINT NOT NULL DEFAULT 0,
+  rows_skipped BIGINT NOT NULL DEFAULT 0,
+  error_message TEXT
+);
+
+CREATE INDEX IF NOT EXISTS sync_runs_dataset_started_at_idx ON sync_runs (dataset, started_at DESC);
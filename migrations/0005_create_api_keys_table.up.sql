@@ -0,0 +1,22 @@
+CREATE TABLE IF NOT EXISTS api_keys (
+  id BIGSERIAL PRIMARY KEY,
+  name TEXT NOT NULL,
+  prefix TEXT NOT NULL,
+  key_hash TEXT NOT NULL UNIQUE,
+  scopes TEXT[] NOT NULL,
+  rate_limit_per_minute INTEGER,
+  daily_quota BIGINT,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  last_used_at TIMESTAMPTZ,
+  revoked_at TIMESTAMPTZ
+);
+
+CREATE TABLE IF NOT EXISTS api_key_usage (
+  key_id BIGINT NOT NULL REFERENCES api_keys (id) ON DELETE CASCADE,
+  day DATE NOT NULL,
+  route TEXT NOT NULL,
+  requests BIGINT NOT NULL DEFAULT 0,
+  PRIMARY KEY (key_id, day, route)
+);
+
+CREATE INDEX IF NOT EXISTS api_key_usage_day_idx ON api_key_usage (day);
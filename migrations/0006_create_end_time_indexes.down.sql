@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS depth_price_history_end_time_idx;
+DROP INDEX IF EXISTS earnings_history_end_time_idx;
+DROP INDEX IF EXISTS swap_history_end_time_idx;
+DROP INDEX IF EXISTS rune_pool_history_end_time_idx;
@@ -0,0 +1,5 @@
+-- The ETag/Last-Modified of the history endpoints read MAX(end_time) on every request
+CREATE INDEX IF NOT EXISTS depth_price_history_end_time_idx ON depth_price_history (end_time);
+CREATE INDEX IF NOT EXISTS earnings_history_end_time_idx ON earnings_history (end_time);
+CREATE INDEX IF NOT EXISTS swap_history_end_time_idx ON swap_history (end_time);
+CREATE INDEX IF NOT EXISTS rune_pool_history_end_time_idx ON rune_pool_history (end_time);
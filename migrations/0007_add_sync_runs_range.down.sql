@@ -0,0 +1,2 @@
+ALTER TABLE sync_runs DROP COLUMN IF EXISTS range_from;
+ALTER TABLE sync_runs DROP COLUMN IF EXISTS range_to;
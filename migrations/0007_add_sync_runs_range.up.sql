@@ -0,0 +1,3 @@
+-- The window a run synced, NULL for runs recorded before it was tracked
+ALTER TABLE sync_runs ADD COLUMN IF NOT EXISTS range_from TIMESTAMPTZ;
+ALTER TABLE sync_runs ADD COLUMN IF NOT EXISTS range_to TIMESTAMPTZ;
@@ -1,277 +1,440 @@
 use crate::error::AppError;
 use axum::http::StatusCode;
-use chrono::Utc;
+use serde::de::DeserializeOwned;
+use std::time::Instant;
 use tokio::time::{sleep, Duration};
+use tracing::{debug, info, instrument, warn};
 
 use crate::{
+    cronjobs::dataset::Dataset,
     dtos::responses::{
         DepthPriceHistoryResponse, EarningHistoryResponse, EarningInterval, PriceDepthInterval,
         RunepoolHistoryResponse, RunepoolInterval, SwapHistoryResponse, SwapInterval,
     },
     model::{
-        earning_history::EarningHistory, price_history::PriceHistory, rune_pool::Runepool,
+        earning_history::EarningHistory,
+        price_history::PriceHistory,
+        rune_pool::Runepool,
         swap_history::SwapHistory,
+        sync_run::{SyncProgress, SyncRange},
     },
-    service::{
-        earning_history_service::EarningHistoryService, price_history_service::PriceHistoryService,
-        run_pool_service::RunePoolService, swap_history_service::SwapHistoryService,
-    },
+    state::AppState,
+    utils::upstream::number,
 };
 
-pub async fn get_prev_2_months_price_history() -> Result<(), AppError> {
-    let price_history_service = PriceHistoryService::new()?;
-    let now = Utc::now();
-    let timestamp = now.timestamp();
-    let mut from = price_history_service
-        .get_last_update_timestamp()
-        .await
-        .unwrap_or(1730419200);
+pub const DEPTH_POOL: &str = "BTC.BTC";
+
+// Where a sync starts when the table is still empty
+pub const DEFAULT_SYNC_START: i64 = 1730419200;
+
+/// Midgard pages overlap at their edges and a backfill may cover data that is already
+/// stored, so drop intervals outside the range or whose `start_time` is already saved.
+async fn skip_synced<T>(
+    state: &AppState,
+    dataset: Dataset,
+    intervals: Vec<T>,
+    range: &SyncRange,
+    start_time: impl Fn(&T) -> &str,
+    progress: &SyncProgress,
+) -> Result<Vec<T>, AppError> {
+    let synced = state
+        .sync_runs
+        .get_synced_start_times(dataset, range)
+        .await?;
+    let fetched = intervals.len();
+    let fresh: Vec<T> = intervals
+        .into_iter()
+        .filter(|interval| {
+            let start = start_time(interval).parse::<i64>().unwrap_or(0);
+            start >= range.from && start < range.to && !synced.contains(&start)
+        })
+        .collect();
+    progress.rows_skipped(fetched - fresh.len());
+    Ok(fresh)
+}
 
-    println!("Last updated timestamp:: {}", from);
+fn check_cancelled(progress: &SyncProgress) -> Result<(), AppError> {
+    if progress.is_cancelled() {
+        return Err(AppError::conflict("Sync cancelled"));
+    }
+    Ok(())
+}
 
-    //todo: Get the last updated timestamp and use it as 'from'
+/// On shutdown the sync ends after the page in flight and saves what it has, the next
+/// run picks up from there.
+fn stop_for_shutdown(state: &AppState) -> bool {
+    let stop = state.shutdown.is_requested();
+    if stop {
+        info!("Shutting down, saving the pages fetched so far");
+    }
+    stop
+}
+
+/// GETs one Midgard page, retrying network errors, 429s and 5xxs with an exponential
+/// backoff. Any other failure, or a body that doesn't parse, fails the sync.
+#[instrument(name = "midgard_page", skip(state))]
+async fn fetch_page<T: DeserializeOwned>(
+    state: &AppState,
+    dataset: Dataset,
+    url: &str,
+) -> Result<T, AppError> {
+    let midgard = &state.config.midgard;
+    let mut attempt = 0;
+    loop {
+        let started = Instant::now();
+        let error = match state.http.get(url).send().await {
+            Ok(response) if response.status().is_success() => {
+                let page = response.json::<T>().await.map_err(|e| {
+                    AppError::upstream(format!("Failed to parse {} response: {}", dataset, e))
+                });
+                let outcome = if page.is_ok() { "ok" } else { "invalid_body" };
+                state.metrics.midgard_request(dataset, outcome, started);
+                return page;
+            }
+            Ok(response) => {
+                let status = response.status();
+                state
+                    .metrics
+                    .midgard_request(dataset, status.as_str(), started);
+                let body = response.text().await.unwrap_or_default();
+                let error = AppError::upstream(format!("HTTP {} error: {}", status, body));
+                if status != StatusCode::TOO_MANY_REQUESTS && !status.is_server_error() {
+                    return Err(error);
+                }
+                error
+            }
+            Err(e) => {
+                state
+                    .metrics
+                    .midgard_request(dataset, "network_error", started);
+                AppError::upstream(format!("Failed to fetch {}: {}", dataset, e))
+            }
+        };
+
+        // Retrying could outlast the shutdown deadline
+        if attempt >= midgard.max_retries || state.shutdown.is_requested() {
+            return Err(error);
+        }
+        let backoff = midgard
+            .retry_backoff_ms
+            .saturating_mul(1 << attempt.min(16));
+        warn!(
+            error = %error.message,
+            attempt = attempt + 1,
+            backoff_ms = backoff,
+            "Midgard request failed, retrying"
+        );
+        sleep(Duration::from_millis(backoff)).await;
+        attempt += 1;
+    }
+}
+
+/// Where the page after one ending at `end_time` starts. It has to move past `from`,
+/// otherwise the same page would be requested forever.
+fn next_from(end_time: &str, from: i64) -> Result<i64, AppError> {
+    let next: i64 = number("endTime", end_time)?;
+    if next <= from {
+        return Err(AppError::upstream(format!(
+            "Midgard page ending at {} does not advance past {}",
+            next, from
+        )));
+    }
+    Ok(next)
+}
+
+/// Pages through Midgard's price history over the range without saving anything.
+pub async fn fetch_price_history(
+    state: &AppState,
+    range: &SyncRange,
+    progress: &SyncProgress,
+) -> Result<Vec<PriceDepthInterval>, AppError> {
+    let mut from = range.from;
+
+    debug!(from = range.from, to = range.to, "Fetching price history");
 
     let mut final_data: Vec<PriceDepthInterval> = Vec::new();
 
-    while from < timestamp {
+    while from < range.to {
+        check_cancelled(progress)?;
+        if stop_for_shutdown(state) {
+            break;
+        }
         let url = format!(
-        "https://midgard.ninerealms.com/v2/history/depths/BTC.BTC?interval=5min&count=400&from={}",
-        from
-    );
-        println!("GET:: {}", url);
-        println!(
-            "Current Timestamp:: {} | Limit Timestamp:: {} | Is it less:: {}",
-            from,
-            timestamp,
-            from < timestamp
+            "{}/v2/history/depths/{}?interval=5min&count=400&from={}",
+            state.config.midgard.url, DEPTH_POOL, from
         );
-        let res = reqwest::get(&url)
-            .await
-            .map_err(|e| AppError::new(format!("Failed to fetch price history: {}", e)))?
-            .json::<DepthPriceHistoryResponse>()
-            .await
-            .map_err(|e| AppError::new(format!("Failed to parse price history response: {}", e)))?;
+        let res: DepthPriceHistoryResponse = fetch_page(state, Dataset::Depths, &url).await?;
 
+        let Some(last) = res.intervals.last() else {
+            break;
+        };
+        from = next_from(&last.end_time, from)?;
+        progress.page_fetched(from);
         final_data.extend(res.intervals);
-        let idx = final_data.len() - 1;
-        from = final_data[idx]
-            .end_time
-            .parse()
-            .expect("Not a valid string")
     }
-    println!(
-        "DONE last timestamp @{} and total entries are {}",
-        final_data[final_data.len() - 1].end_time,
-        final_data.len()
-    );
+
+    Ok(final_data)
+}
+
+pub async fn get_prev_2_months_price_history(
+    state: &AppState,
+    range: &SyncRange,
+    progress: &SyncProgress,
+) -> Result<(), AppError> {
+    let final_data = fetch_price_history(state, range, progress).await?;
+
+    check_cancelled(progress)?;
+    let final_data = skip_synced(
+        state,
+        Dataset::Depths,
+        final_data,
+        range,
+        |i| &i.start_time,
+        progress,
+    )
+    .await?;
+    if final_data.is_empty() {
+        info!("Price history is already up to date");
+        return Ok(());
+    }
 
     //Convert the DTO to Db Model
-    let price_history: Vec<PriceHistory> =
-        final_data.iter().cloned().map(PriceHistory::from).collect();
+    let price_history: Vec<PriceHistory> = final_data
+        .into_iter()
+        .map(PriceHistory::try_from)
+        .collect::<Result<_, _>>()?;
 
     //Use the corresponding service to push the data to database
-    let ids = price_history_service
+    let ids = state
+        .price_history
         .save_batch(&price_history)
         .await
-        .map_err(|e| AppError::new(format!("Failed to save price history: {}", e)))?;
+        .map_err(|e| e.context("Failed to save price history"))?;
+    progress.rows_inserted(ids.len());
 
-    println!("{} rows inserted ✅", ids.len());
+    info!(rows = ids.len(), "Saved price history");
     Ok(())
 }
 
-pub async fn get_prev_2_months_earning_history() -> Result<(), AppError> {
-    let earning_history_service = EarningHistoryService::new()?;
-    let now = Utc::now();
-    let timestamp = now.timestamp();
-    let mut from = earning_history_service
-        .get_last_update_timestamp()
-        .await
-        .unwrap_or(1730419200);
+/// Pages through Midgard's earning history over the range without saving anything.
+pub async fn fetch_earning_history(
+    state: &AppState,
+    range: &SyncRange,
+    progress: &SyncProgress,
+) -> Result<Vec<EarningInterval>, AppError> {
+    let mut from = range.from;
 
-    println!("Getting earning history from :: {}", from);
+    debug!(from = range.from, to = range.to, "Fetching earning history");
 
     let mut final_data: Vec<EarningInterval> = Vec::new();
 
-    while from < timestamp {
+    while from < range.to {
+        check_cancelled(progress)?;
+        if stop_for_shutdown(state) {
+            break;
+        }
         let url = format!(
-            "https://midgard.ninerealms.com/v2/history/earnings?interval=5min&from={}&count=400",
-            from
+            "{}/v2/history/earnings?interval=5min&from={}&count=400",
+            state.config.midgard.url, from
         );
+        let res: EarningHistoryResponse = fetch_page(state, Dataset::Earnings, &url).await?;
 
-        println!("GET:: {}", url);
-        println!(
-            "Current Timestamp:: {} | Limit Timestamp:: {} | Is it less:: {}",
-            from,
-            timestamp,
-            from < timestamp
-        );
-        let res = reqwest::get(&url)
-            .await
-            .map_err(|e| AppError::new(format!("Failed to fetch earning history: {}", e)))?
-            .json::<EarningHistoryResponse>()
-            .await
-            .map_err(|e| {
-                AppError::new(format!("Failed to parse earning history response: {}", e))
-            })?;
+        let Some(last) = res.intervals.last() else {
+            break;
+        };
+        from = next_from(&last.end_time, from)?;
+        progress.page_fetched(from);
         final_data.extend(res.intervals);
-        let idx = final_data.len() - 1;
-        from = final_data[idx]
-            .end_time
-            .parse()
-            .expect("Not a valid string")
     }
 
-    println!(
-        "DONE last timestamp @{} and total entries are {}",
-        final_data[final_data.len() - 1].end_time,
-        final_data.len()
-    );
+    Ok(final_data)
+}
+
+pub async fn get_prev_2_months_earning_history(
+    state: &AppState,
+    range: &SyncRange,
+    progress: &SyncProgress,
+) -> Result<(), AppError> {
+    let final_data = fetch_earning_history(state, range, progress).await?;
+
+    check_cancelled(progress)?;
+    let final_data = skip_synced(
+        state,
+        Dataset::Earnings,
+        final_data,
+        range,
+        |i| &i.start_time,
+        progress,
+    )
+    .await?;
+    if final_data.is_empty() {
+        info!("Earning history is already up to date");
+        return Ok(());
+    }
 
     let earning_histories: Vec<EarningHistory> = final_data
-        .iter()
-        .cloned()
-        .map(EarningHistory::from)
-        .collect();
+        .into_iter()
+        .map(EarningHistory::try_from)
+        .collect::<Result<_, _>>()?;
 
-    let res = earning_history_service
+    let res = state
+        .earning_history
         .save_batch(&earning_histories)
         .await
-        .map_err(|e| AppError::new(format!("Failed to save earning history: {}", e)))?;
+        .map_err(|e| e.context("Failed to save earning history"))?;
+    progress.rows_inserted(res.len());
 
-    println!("{} rows inserted ✅", res.len());
+    info!(rows = res.len(), "Saved earning history");
     Ok(())
 }
 
-pub async fn get_prev_2_months_swap_history() -> Result<(), AppError> {
-    let swap_history_service = SwapHistoryService::new()?;
-    let now = Utc::now();
-    let timestamp = now.timestamp();
-    let mut from = swap_history_service
-        .get_last_update_timestamp()
-        .await
-        .unwrap_or(1730419200);
+/// Pages through Midgard's swap history over the range without saving anything.
+pub async fn fetch_swap_history(
+    state: &AppState,
+    range: &SyncRange,
+    progress: &SyncProgress,
+) -> Result<Vec<SwapInterval>, AppError> {
+    let mut from = range.from;
 
-    println!("Fetching swap history form :: {}", from);
+    debug!(from = range.from, to = range.to, "Fetching swap history");
     let mut final_data: Vec<SwapInterval> = Vec::new();
 
-    while from < timestamp {
+    while from < range.to {
+        check_cancelled(progress)?;
+        if stop_for_shutdown(state) {
+            break;
+        }
         let url = format!(
-            "https://midgard.ninerealms.com/v2/history/swaps?interval=5min&from={}&count=400",
-            from
+            "{}/v2/history/swaps?interval=5min&from={}&count=400",
+            state.config.midgard.url, from
         );
+        let res: SwapHistoryResponse = fetch_page(state, Dataset::Swaps, &url).await?;
 
-        println!("GET:: {}", url);
-        println!(
-            "Current Timestamp:: {} | Limit Timestamp:: {} | Is it less:: {}",
-            from,
-            timestamp,
-            from < timestamp
-        );
-        let res = reqwest::get(&url)
-            .await
-            .map_err(|e| AppError::new(format!("Failed to fetch swap history: {}", e)))?
-            .json::<SwapHistoryResponse>()
-            .await
-            .map_err(|e| AppError::new(format!("Failed to parse swap history response: {}", e)))?;
-
+        let Some(last) = res.intervals.last() else {
+            break;
+        };
+        from = next_from(&last.end_time, from)?;
+        progress.page_fetched(from);
         final_data.extend(res.intervals);
-        let idx = final_data.len() - 1;
-        from = final_data[idx]
-            .end_time
-            .parse()
-            .expect("Not a valid string")
     }
 
-    let swap_histories: Vec<SwapHistory> =
-        final_data.iter().cloned().map(SwapHistory::from).collect();
+    Ok(final_data)
+}
+
+pub async fn get_prev_2_months_swap_history(
+    state: &AppState,
+    range: &SyncRange,
+    progress: &SyncProgress,
+) -> Result<(), AppError> {
+    let final_data = fetch_swap_history(state, range, progress).await?;
+
+    check_cancelled(progress)?;
+    let final_data = skip_synced(
+        state,
+        Dataset::Swaps,
+        final_data,
+        range,
+        |i| &i.start_time,
+        progress,
+    )
+    .await?;
+    if final_data.is_empty() {
+        info!("Swap history is already up to date");
+        return Ok(());
+    }
 
-    let _res = swap_history_service
+    let swap_histories: Vec<SwapHistory> = final_data
+        .into_iter()
+        .map(SwapHistory::try_from)
+        .collect::<Result<_, _>>()?;
+
+    let res = state
+        .swap_history
         .save_batch(&swap_histories)
         .await
-        .map_err(|e| AppError::new(format!("Failed to save swap history: {}", e)))?;
+        .map_err(|e| e.context("Failed to save swap history"))?;
+    progress.rows_inserted(res.len());
 
-    println!("Swap History Synced ✅");
+    info!(rows = res.len(), "Saved swap history");
     Ok(())
 }
 
-pub async fn get_prev_2_months_runepool_history() -> Result<(), AppError> {
-    let runepool_service = RunePoolService::new()?;
-    let now = Utc::now();
-    let timestamp = now.timestamp();
-    let mut from = runepool_service
-        .get_last_update_timestamp()
-        .await
-        .unwrap_or(1730419200);
-
-    println!("Fetching runepool history from :: {}", from);
+/// Pages through Midgard's runepool history over the range without saving anything.
+pub async fn fetch_runepool_history(
+    state: &AppState,
+    range: &SyncRange,
+    progress: &SyncProgress,
+) -> Result<Vec<RunepoolInterval>, AppError> {
+    let mut from = range.from;
+
+    debug!(
+        from = range.from,
+        to = range.to,
+        "Fetching runepool history"
+    );
     let mut final_data: Vec<RunepoolInterval> = Vec::new();
 
-    while from < timestamp {
+    while from < range.to {
+        check_cancelled(progress)?;
+        if stop_for_shutdown(state) {
+            break;
+        }
         let url = format!(
-            "https://midgard.ninerealms.com/v2/history/runepool?interval=5min&from={}&count=400",
-            from
+            "{}/v2/history/runepool?interval=5min&from={}&count=400",
+            state.config.midgard.url, from
         );
 
-        println!("GET:: {}", url);
-
         // Add delay between requests (500ms)
         sleep(Duration::from_millis(500)).await;
 
-        // Get the response
-        let response = reqwest::get(&url).await.map_err(|e| {
-            AppError::new(format!("Network error: {}", e)).with_status(StatusCode::BAD_GATEWAY)
-        })?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_body = response
-                .text()
-                .await
-                .map_err(|e| AppError::new(format!("Failed to read error response: {}", e)))?;
-            return Err(
-                AppError::new(format!("HTTP {} error: {}", status, error_body))
-                    .with_status(StatusCode::BAD_GATEWAY),
-            );
-        }
-
-        // Parse JSON response
-        let parsed_data = response
-            .json::<RunepoolHistoryResponse>()
-            .await
-            .map_err(|e| AppError::new(format!("Failed to parse runepool response: {}", e)))?;
+        let parsed_data: RunepoolHistoryResponse =
+            fetch_page(state, Dataset::Runepool, &url).await?;
 
+        let Some(last) = parsed_data.intervals.last() else {
+            break;
+        };
+        from = next_from(&last.end_time, from)?;
+        progress.page_fetched(from);
         final_data.extend(parsed_data.intervals);
-        let idx = final_data.len() - 1;
-        from = final_data[idx]
-            .end_time
-            .parse()
-            .expect("Not a valid string");
     }
 
-    let runepools: Vec<Runepool> = final_data.iter().cloned().map(Runepool::from).collect();
-
-    runepool_service
-        .save_batch(&runepools)
-        .await
-        .map_err(|e| AppError::new(format!("Failed to save runepool data: {}", e)))?;
-
-    println!("Runepool History Synced ✅");
-    Ok(())
+    Ok(final_data)
 }
 
-pub async fn sync_all_data() -> Result<(), AppError> {
-    println!("\n\n=========Syncing Price History 🔄===========");
-    get_prev_2_months_price_history().await?;
-
-    println!("\n\n=========Syncing Earning History 🔄===========");
-    get_prev_2_months_earning_history().await?;
+pub async fn get_prev_2_months_runepool_history(
+    state: &AppState,
+    range: &SyncRange,
+    progress: &SyncProgress,
+) -> Result<(), AppError> {
+    let final_data = fetch_runepool_history(state, range, progress).await?;
+
+    check_cancelled(progress)?;
+    let final_data = skip_synced(
+        state,
+        Dataset::Runepool,
+        final_data,
+        range,
+        |i| &i.start_time,
+        progress,
+    )
+    .await?;
+    if final_data.is_empty() {
+        info!("Runepool history is already up to date");
+        return Ok(());
+    }
 
-    println!("\n\n=========Syncing Swap History 🔄===========");
-    get_prev_2_months_swap_history().await?;
+    let runepools: Vec<Runepool> = final_data
+        .into_iter()
+        .map(Runepool::try_from)
+        .collect::<Result<_, _>>()?;
 
-    println!("\n\n=========Syncing Runepool History 🔄===========");
-    get_prev_2_months_runepool_history().await?;
+    let ids = state
+        .rune_pool
+        .save_batch(&runepools)
+        .await
+        .map_err(|e| e.context("Failed to save runepool data"))?;
+    progress.rows_inserted(ids.len());
 
-    println!("\n\n=========All Endpoints Synced Successfully ✅===========");
+    info!(rows = ids.len(), "Saved runepool history");
     Ok(())
 }
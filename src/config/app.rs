@@ -0,0 +1,346 @@
+use crate::{
+    config::{cors, scheduler::SchedulerConfig},
+    error::AppError,
+};
+use serde::Deserialize;
+use std::{env, fs, path::Path, str::FromStr};
+use tracing_subscriber::EnvFilter;
+
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    pub host: String,
+    pub port: u16,
+    /// How long a shutdown waits for in-flight requests and syncs before exiting anyway.
+    pub shutdown_timeout_secs: u64,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            host: "0.0.0.0".to_string(),
+            port: 3000,
+            shutdown_timeout_secs: 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DatabaseConfig {
+    pub url: String,
+    pub max_connections: u32,
+    pub acquire_timeout_secs: u64,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            max_connections: 5,
+            acquire_timeout_secs: 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MidgardConfig {
+    pub url: String,
+    pub timeout_secs: u64,
+    /// Retries of a page that failed with a network error, a 429 or a 5xx.
+    pub max_retries: u32,
+    /// Wait before the first retry, doubled on every further one.
+    pub retry_backoff_ms: u64,
+}
+
+impl Default for MidgardConfig {
+    fn default() -> Self {
+        Self {
+            url: "https://midgard.ninerealms.com".to_string(),
+            timeout_secs: 30,
+            max_retries: 3,
+            retry_backoff_ms: 500,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct HealthConfig {
+    /// `/readyz` fails once the newest interval of a dataset ended longer ago than this.
+    pub max_data_age_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AuthConfig {
+    /// Reject requests to the data routes that don't carry an `x-api-key`.
+    pub require_api_key: bool,
+    /// Requests per minute of keys without a limit of their own.
+    pub rate_limit_per_minute: u32,
+    /// Requests per UTC day of keys without a quota of their own, unlimited when unset.
+    pub daily_quota: Option<i64>,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            require_api_key: false,
+            rate_limit_per_minute: 60,
+            daily_quota: None,
+        }
+    }
+}
+
+/// Starting point of the CORS policy: `strict` only lets the configured origins in,
+/// `permissive` any origin, method and header, for local development.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CorsPreset {
+    #[default]
+    Strict,
+    Permissive,
+}
+
+impl FromStr for CorsPreset {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "strict" => Ok(CorsPreset::Strict),
+            "permissive" => Ok(CorsPreset::Permissive),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CorsConfig {
+    pub preset: CorsPreset,
+    /// Origins browsers may call from, e.g. `https://dashboard.example.com`, or `*` for
+    /// any. None by default, so cross-origin requests are refused.
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    pub allowed_headers: Vec<String>,
+    /// How long browsers may cache a preflight response.
+    pub max_age_secs: u64,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            preset: CorsPreset::Strict,
+            allowed_origins: Vec::new(),
+            allowed_methods: ["GET", "POST", "DELETE"].map(String::from).to_vec(),
+            allowed_headers: ["authorization", "content-type", "x-api-key", "x-request-id"]
+                .map(String::from)
+                .to_vec(),
+            max_age_secs: 3600,
+        }
+    }
+}
+
+/// How log lines are written: `pretty` for a terminal, `json` for log collectors.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Pretty,
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "pretty" => Ok(LogFormat::Pretty),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LoggingConfig {
+    /// `tracing` filter directives, e.g. `info` or `midguard_api=debug,sqlx=warn`.
+    pub level: String,
+    pub format: LogFormat,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+            format: LogFormat::Pretty,
+        }
+    }
+}
+
+/// Everything the app needs to start, read from an optional TOML file (`CONFIG_FILE`,
+/// `config.toml` by default) with environment variables taking precedence.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    pub midgard: MidgardConfig,
+    pub logging: LoggingConfig,
+    pub health: HealthConfig,
+    pub auth: AuthConfig,
+    pub cors: CorsConfig,
+    /// Bearer token of the `/admin` routes, which are disabled without one.
+    pub admin_token: Option<String>,
+    /// Total bonded RUNE (1e8 units) used when a bond yield request doesn't pass one.
+    pub total_bond: Option<i64>,
+    pub scheduler: SchedulerConfig,
+}
+
+impl AppConfig {
+    pub fn load() -> Result<Self, AppError> {
+        let mut config = match env_value("CONFIG_FILE") {
+            Some(path) => Self::from_file(&path)?,
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => {
+                Self::from_file(DEFAULT_CONFIG_FILE)?
+            }
+            None => Self::default(),
+        };
+
+        override_from_env("HOST", &mut config.server.host)?;
+        override_from_env("PORT", &mut config.server.port)?;
+        override_from_env(
+            "SHUTDOWN_TIMEOUT_SECS",
+            &mut config.server.shutdown_timeout_secs,
+        )?;
+        override_from_env("DATABASE_URL", &mut config.database.url)?;
+        override_from_env(
+            "DATABASE_MAX_CONNECTIONS",
+            &mut config.database.max_connections,
+        )?;
+        override_from_env(
+            "DATABASE_ACQUIRE_TIMEOUT_SECS",
+            &mut config.database.acquire_timeout_secs,
+        )?;
+        override_from_env("MIDGARD_URL", &mut config.midgard.url)?;
+        override_from_env("MIDGARD_TIMEOUT_SECS", &mut config.midgard.timeout_secs)?;
+        override_from_env("MIDGARD_MAX_RETRIES", &mut config.midgard.max_retries)?;
+        override_from_env(
+            "MIDGARD_RETRY_BACKOFF_MS",
+            &mut config.midgard.retry_backoff_ms,
+        )?;
+        override_from_env("LOG_LEVEL", &mut config.logging.level)?;
+        override_from_env("LOG_FORMAT", &mut config.logging.format)?;
+        if let Some(age) = env_value("HEALTH_MAX_DATA_AGE_SECS") {
+            config.health.max_data_age_secs = Some(parse_env("HEALTH_MAX_DATA_AGE_SECS", &age)?);
+        }
+        override_from_env("REQUIRE_API_KEY", &mut config.auth.require_api_key)?;
+        override_from_env(
+            "API_RATE_LIMIT_PER_MINUTE",
+            &mut config.auth.rate_limit_per_minute,
+        )?;
+        if let Some(quota) = env_value("API_DAILY_QUOTA") {
+            config.auth.daily_quota = Some(parse_env("API_DAILY_QUOTA", &quota)?);
+        }
+        override_from_env("CORS_PRESET", &mut config.cors.preset)?;
+        list_from_env("CORS_ALLOWED_ORIGINS", &mut config.cors.allowed_origins);
+        list_from_env("CORS_ALLOWED_METHODS", &mut config.cors.allowed_methods);
+        list_from_env("CORS_ALLOWED_HEADERS", &mut config.cors.allowed_headers);
+        override_from_env("CORS_MAX_AGE_SECS", &mut config.cors.max_age_secs)?;
+        if let Some(token) = env_value("ADMIN_TOKEN") {
+            config.admin_token = Some(token);
+        }
+        if let Some(total_bond) = env_value("TOTAL_BOND") {
+            config.total_bond = Some(parse_env("TOTAL_BOND", &total_bond)?);
+        }
+        config.scheduler.override_from_env()?;
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn from_file(path: &str) -> Result<Self, AppError> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| invalid(format!("Failed to read config file {}: {}", path, e)))?;
+        toml::from_str(&contents)
+            .map_err(|e| invalid(format!("Invalid config file {}: {}", path, e)))
+    }
+
+    pub fn validate(&self) -> Result<(), AppError> {
+        if self.database.url.is_empty() {
+            return Err(invalid("DATABASE_URL environment variable not set"));
+        }
+        if self.server.port == 0 {
+            return Err(invalid("server port must not be 0"));
+        }
+        if self.database.max_connections == 0 {
+            return Err(invalid("database max_connections must be at least 1"));
+        }
+        if !self.midgard.url.starts_with("http://") && !self.midgard.url.starts_with("https://") {
+            return Err(invalid(format!(
+                "Midgard url must be an http(s) url, got {}",
+                self.midgard.url
+            )));
+        }
+        if EnvFilter::try_new(&self.logging.level).is_err() {
+            return Err(invalid(format!("Invalid log level {}", self.logging.level)));
+        }
+        if self.health.max_data_age_secs == Some(0) {
+            return Err(invalid("HEALTH_MAX_DATA_AGE_SECS must be at least 1"));
+        }
+        if self.auth.rate_limit_per_minute == 0 {
+            return Err(invalid("API_RATE_LIMIT_PER_MINUTE must be at least 1"));
+        }
+        if self.auth.daily_quota.is_some_and(|quota| quota <= 0) {
+            return Err(invalid("API_DAILY_QUOTA must be at least 1"));
+        }
+        cors::validate(&self.cors)?;
+        self.scheduler.validate()?;
+        if self.total_bond.is_some_and(|bond| bond <= 0) {
+            return Err(invalid("TOTAL_BOND must be positive"));
+        }
+        // An empty token would let anyone with an empty bearer in
+        if self.admin_token.as_deref() == Some("") {
+            return Err(invalid("ADMIN_TOKEN must not be empty"));
+        }
+        Ok(())
+    }
+}
+
+/// Blank variables, e.g. `TOTAL_BOND=` in a `.env`, count as unset.
+pub fn env_value(key: &str) -> Option<String> {
+    env::var(key).ok().filter(|value| !value.is_empty())
+}
+
+fn override_from_env<T: FromStr>(key: &str, target: &mut T) -> Result<(), AppError> {
+    if let Some(value) = env_value(key) {
+        *target = parse_env(key, &value)?;
+    }
+    Ok(())
+}
+
+/// Comma-separated values, e.g. `CORS_ALLOWED_ORIGINS=https://a.example,https://b.example`.
+fn list_from_env(key: &str, target: &mut Vec<String>) {
+    if let Some(value) = env_value(key) {
+        *target = value
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(String::from)
+            .collect();
+    }
+}
+
+fn parse_env<T: FromStr>(key: &str, value: &str) -> Result<T, AppError> {
+    value
+        .parse()
+        .map_err(|_| invalid(format!("Invalid value for {}: {}", key, value)))
+}
+
+fn invalid(message: impl Into<String>) -> AppError {
+    AppError::new(message)
+}
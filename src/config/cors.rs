@@ -0,0 +1,78 @@
+use crate::{
+    config::app::{CorsConfig, CorsPreset},
+    error::AppError,
+    routes::REQUEST_ID_HEADER,
+};
+use axum::http::{header, HeaderName, HeaderValue, Method};
+use std::time::Duration;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+/// Builds the CORS layer of the app. Entries that don't parse are left out, they are
+/// reported by [`validate`] when the config is loaded.
+pub fn layer(config: &CorsConfig) -> CorsLayer {
+    let layer = match config.preset {
+        CorsPreset::Permissive => CorsLayer::permissive(),
+        CorsPreset::Strict => {
+            let origins = if config.allowed_origins.iter().any(|o| o == "*") {
+                AllowOrigin::any()
+            } else {
+                AllowOrigin::list(
+                    config
+                        .allowed_origins
+                        .iter()
+                        .filter_map(|origin| HeaderValue::from_str(origin).ok()),
+                )
+            };
+            CorsLayer::new()
+                .allow_origin(origins)
+                .allow_methods(
+                    config
+                        .allowed_methods
+                        .iter()
+                        .filter_map(|method| Method::from_bytes(method.as_bytes()).ok())
+                        .collect::<Vec<_>>(),
+                )
+                .allow_headers(
+                    config
+                        .allowed_headers
+                        .iter()
+                        .filter_map(|name| HeaderName::from_bytes(name.as_bytes()).ok())
+                        .collect::<Vec<_>>(),
+                )
+                // Lets browser clients read the request id and when to retry a 429
+                .expose_headers([REQUEST_ID_HEADER, header::RETRY_AFTER])
+        }
+    };
+    layer.max_age(Duration::from_secs(config.max_age_secs))
+}
+
+pub fn validate(config: &CorsConfig) -> Result<(), AppError> {
+    let origins = &config.allowed_origins;
+    if origins.len() > 1 && origins.iter().any(|o| o == "*") {
+        return Err(AppError::new(
+            "CORS allowed origins can't combine * with other origins",
+        ));
+    }
+    for origin in origins.iter().filter(|o| *o != "*") {
+        let valid = (origin.starts_with("http://") || origin.starts_with("https://"))
+            && !origin.ends_with('/')
+            && HeaderValue::from_str(origin).is_ok();
+        if !valid {
+            return Err(AppError::new(format!(
+                "Invalid CORS origin {}, expected e.g. https://dashboard.example.com",
+                origin
+            )));
+        }
+    }
+    for method in &config.allowed_methods {
+        if Method::from_bytes(method.as_bytes()).is_err() {
+            return Err(AppError::new(format!("Invalid CORS method {}", method)));
+        }
+    }
+    for name in &config.allowed_headers {
+        if HeaderName::from_bytes(name.as_bytes()).is_err() {
+            return Err(AppError::new(format!("Invalid CORS header {}", name)));
+        }
+    }
+    Ok(())
+}
@@ -1,47 +1,39 @@
-use crate::error::AppError;
-use axum::http::StatusCode;
-use sqlx::{Pool, Postgres};
-use std::env;
+use crate::{config::app::DatabaseConfig, error::AppError};
+use sqlx::{migrate::Migrator, PgPool};
 use std::time::Duration;
 
-// src/config/database.rs
-use once_cell::sync::OnceCell;
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
 
-static DB_POOL: OnceCell<Pool<Postgres>> = OnceCell::new();
-
-pub async fn initialize_database() -> Result<Pool<Postgres>, AppError> {
-    let url = env::var("DATABASE_URL").map_err(|_| {
-        AppError::new("DATABASE_URL environment variable not set")
-            .with_status(StatusCode::INTERNAL_SERVER_ERROR)
-    })?;
-
-    let pool = sqlx::postgres::PgPoolOptions::new()
-        .max_connections(5)
-        .acquire_timeout(Duration::from_secs(3))
-        .connect(&url)
+pub async fn connect(config: &DatabaseConfig) -> Result<PgPool, AppError> {
+    sqlx::postgres::PgPoolOptions::new()
+        .max_connections(config.max_connections)
+        .acquire_timeout(Duration::from_secs(config.acquire_timeout_secs))
+        .connect(&config.url)
         .await
-        .map_err(|e| AppError::new(format!("Failed to connect to database: {}", e)))?;
-
-    DB_POOL.set(pool.clone()).map_err(|_| {
-        AppError::new("Failed to set database pool").with_status(StatusCode::INTERNAL_SERVER_ERROR)
-    })?;
-
-    Ok(pool)
+        .map_err(|e| AppError::from(e).context("Failed to connect to database"))
 }
 
-pub fn get_pool() -> Result<&'static Pool<Postgres>, AppError> {
-    DB_POOL.get().ok_or_else(|| {
-        AppError::new("Database pool not initialized")
-            .with_status(StatusCode::INTERNAL_SERVER_ERROR)
-    })
-}
-
-pub async fn run_migrations() -> Result<(), AppError> {
-    let pool = self::get_pool()?;
-    sqlx::migrate!("./migrations")
+pub async fn run_migrations(pool: &PgPool) -> Result<(), AppError> {
+    MIGRATOR
         .run(pool)
         .await
         .map_err(|e| AppError::new(format!("Migration failed: {}", e)))?;
 
     Ok(())
 }
+
+/// Versions of the migrations shipped with the binary that the database hasn't applied.
+pub async fn pending_migrations(pool: &PgPool) -> Result<Vec<i64>, AppError> {
+    let applied: Vec<i64> =
+        sqlx::query_scalar("SELECT version FROM _sqlx_migrations WHERE success")
+            .fetch_all(pool)
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to read applied migrations"))?;
+
+    Ok(MIGRATOR
+        .iter()
+        .filter(|migration| !migration.migration_type.is_down_migration())
+        .map(|migration| migration.version)
+        .filter(|version| !applied.contains(version))
+        .collect())
+}
@@ -0,0 +1,21 @@
+use crate::config::app::{LogFormat, LoggingConfig};
+use tracing_subscriber::{fmt, EnvFilter};
+
+/// Installs the global `tracing` subscriber. `RUST_LOG` takes precedence over the
+/// configured level, so a single module can be turned up without a config change.
+pub fn init(config: &LoggingConfig) {
+    let filter = EnvFilter::try_from_default_env()
+        .or_else(|_| EnvFilter::try_new(&config.level))
+        .unwrap_or_else(|_| EnvFilter::new("info"));
+    let subscriber = fmt().with_env_filter(filter);
+
+    match config.format {
+        LogFormat::Pretty => subscriber.init(),
+        LogFormat::Json => subscriber
+            .json()
+            .flatten_event(true)
+            .with_current_span(false)
+            .with_span_list(true)
+            .init(),
+    }
+}
@@ -1 +1,5 @@
+pub mod app;
+pub mod cors;
 pub mod database;
+pub mod logging;
+pub mod scheduler;
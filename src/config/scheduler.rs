@@ -0,0 +1,154 @@
+use crate::{config::app::env_value, cronjobs::dataset::Dataset, error::AppError};
+use cron::Schedule;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+const DEFAULT_SCHEDULE: &str = "0 0 */23 * * *";
+
+fn default_schedule() -> Schedule {
+    Schedule::from_str(DEFAULT_SCHEDULE).expect("valid default schedule")
+}
+
+/// Schedule of a dataset's sync, `[scheduler.jobs.<dataset>]` in the config file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct JobSchedule {
+    pub enabled: bool,
+    pub schedule: Schedule,
+}
+
+impl Default for JobSchedule {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            schedule: default_schedule(),
+        }
+    }
+}
+
+/// Periodic reconciliation of the trailing window against Midgard.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReconcileSchedule {
+    pub schedule: Schedule,
+    #[serde(default = "default_window_hours")]
+    pub window_hours: i64,
+    #[serde(default)]
+    pub repair: bool,
+}
+
+fn default_window_hours() -> i64 {
+    24
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SchedulerConfig {
+    /// Serve the API without running any scheduled sync, e.g. on read replicas.
+    pub api_only: bool,
+    pub run_on_startup: bool,
+    /// Re-fetch the gaps of a dataset right after each scheduled sync of it.
+    pub repair_gaps: bool,
+    /// Only set when `[scheduler.reconcile]` or `RECONCILE_CRON` is configured.
+    pub reconcile: Option<ReconcileSchedule>,
+    /// Datasets left out run on the default schedule.
+    pub jobs: HashMap<Dataset, JobSchedule>,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            api_only: false,
+            run_on_startup: true,
+            repair_gaps: false,
+            reconcile: None,
+            jobs: HashMap::new(),
+        }
+    }
+}
+
+impl SchedulerConfig {
+    /// Schedule of the dataset's sync.
+    pub fn job(&self, dataset: Dataset) -> JobSchedule {
+        self.jobs.get(&dataset).cloned().unwrap_or_default()
+    }
+
+    /// Applies `API_ONLY`, `SYNC_ON_STARTUP`, `SYNC_REPAIR_GAPS`, the `RECONCILE_*` settings plus
+    /// `SYNC_<DATASET>_CRON` and `SYNC_<DATASET>_ENABLED` for every dataset, e.g.
+    /// `SYNC_SWAPS_CRON="0 */30 * * * *"`.
+    pub fn override_from_env(&mut self) -> Result<(), AppError> {
+        override_bool("API_ONLY", &mut self.api_only)?;
+        override_bool("SYNC_ON_STARTUP", &mut self.run_on_startup)?;
+        override_bool("SYNC_REPAIR_GAPS", &mut self.repair_gaps)?;
+
+        for dataset in Dataset::ALL {
+            let prefix = format!("SYNC_{}", dataset.as_str().to_uppercase());
+            let expression = env_value(&format!("{}_CRON", prefix));
+            let enabled = env_value(&format!("{}_ENABLED", prefix));
+            if expression.is_none() && enabled.is_none() {
+                continue;
+            }
+
+            let job = self.jobs.entry(dataset).or_default();
+            if let Some(expression) = expression {
+                job.schedule = Schedule::from_str(&expression).map_err(|e| {
+                    AppError::new(format!("Invalid cron expression for {}: {}", dataset, e))
+                })?;
+            }
+            override_bool(&format!("{}_ENABLED", prefix), &mut job.enabled)?;
+        }
+
+        if let Some(expression) = env_value("RECONCILE_CRON") {
+            let schedule = Schedule::from_str(&expression).map_err(|e| {
+                AppError::new(format!("Invalid cron expression for reconcile: {}", e))
+            })?;
+            match &mut self.reconcile {
+                Some(reconcile) => reconcile.schedule = schedule,
+                None => {
+                    self.reconcile = Some(ReconcileSchedule {
+                        schedule,
+                        window_hours: default_window_hours(),
+                        repair: false,
+                    })
+                }
+            }
+        }
+        if let Some(reconcile) = &mut self.reconcile {
+            if let Some(hours) = env_value("RECONCILE_WINDOW_HOURS") {
+                reconcile.window_hours = hours.parse().map_err(|_| {
+                    AppError::new(format!(
+                        "RECONCILE_WINDOW_HOURS must be a number, got {}",
+                        hours
+                    ))
+                })?;
+            }
+            override_bool("RECONCILE_REPAIR", &mut reconcile.repair)?;
+        }
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), AppError> {
+        if let Some(reconcile) = &self.reconcile {
+            if reconcile.window_hours <= 0 {
+                return Err(AppError::new("RECONCILE_WINDOW_HOURS must be at least 1"));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn override_bool(key: &str, target: &mut bool) -> Result<(), AppError> {
+    if let Some(value) = env_value(key) {
+        *target = match value.to_lowercase().as_str() {
+            "1" | "true" | "yes" | "on" => true,
+            "0" | "false" | "no" | "off" => false,
+            _ => {
+                return Err(AppError::new(format!(
+                    "{} must be a boolean, got {}",
+                    key, value
+                )))
+            }
+        };
+    }
+    Ok(())
+}
@@ -0,0 +1,206 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{
+    client::proxy::{self, DEFAULT_SYNC_START, DEPTH_POOL},
+    cronjobs::sync_lock::SyncLock,
+    error::{AppError, ErrorKind},
+    model::{
+        data_version::DataVersion,
+        sync_run::{SyncProgress, SyncRange, SyncReport},
+    },
+    service::sync_run_service::{STATUS_CANCELLED, STATUS_FAILED, STATUS_SUCCEEDED},
+    state::AppState,
+};
+use chrono::Utc;
+use tracing::{info, info_span, warn, Instrument};
+
+/// A Midgard history feed that is synced into its own table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Dataset {
+    Depths,
+    Earnings,
+    Swaps,
+    Runepool,
+}
+
+impl Dataset {
+    pub const ALL: [Dataset; 4] = [
+        Dataset::Depths,
+        Dataset::Earnings,
+        Dataset::Swaps,
+        Dataset::Runepool,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Dataset::Depths => "depths",
+            Dataset::Earnings => "earnings",
+            Dataset::Swaps => "swaps",
+            Dataset::Runepool => "runepool",
+        }
+    }
+
+    /// Table the dataset is stored in.
+    pub fn table(&self) -> &'static str {
+        match self {
+            Dataset::Depths => "depth_price_history",
+            Dataset::Earnings => "earnings_history",
+            Dataset::Swaps => "swap_history",
+            Dataset::Runepool => "rune_pool_history",
+        }
+    }
+
+    /// Pool the dataset is scoped to, `None` for network-wide feeds.
+    pub fn pool(&self) -> Option<&'static str> {
+        match self {
+            Dataset::Depths => Some(DEPTH_POOL),
+            _ => None,
+        }
+    }
+
+    /// Unix `end_time` of the newest stored interval, what the dataset is synced up to.
+    pub async fn last_synced(&self, state: &AppState) -> Option<i64> {
+        let version = self.data_version(state).await.ok()??;
+        Some(version.last_end_time.timestamp())
+    }
+
+    /// Version of the stored rows, `None` while the table is empty.
+    pub async fn data_version(&self, state: &AppState) -> Result<Option<DataVersion>, AppError> {
+        match self {
+            Dataset::Depths => state.price_history.get_data_version().await,
+            Dataset::Earnings => state.earning_history.get_data_version().await,
+            Dataset::Swaps => state.swap_history.get_data_version().await,
+            Dataset::Runepool => state.rune_pool.get_data_version().await,
+        }
+    }
+
+    /// Range of an incremental sync, from the newest stored interval up to now.
+    pub async fn incremental_range(&self, state: &AppState) -> SyncRange {
+        SyncRange {
+            from: self.last_synced(state).await.unwrap_or(DEFAULT_SYNC_START),
+            to: Utc::now().timestamp(),
+        }
+    }
+
+    /// Runs the sync over `range` and records it in `sync_runs`, including partial
+    /// counters on failure. Without a range it syncs everything since the last run.
+    /// Holds the dataset's [`SyncLock`] throughout, so only one instance syncs it at a time.
+    pub async fn sync(
+        &self,
+        state: &AppState,
+        range: Option<SyncRange>,
+        progress: &SyncProgress,
+    ) -> Result<SyncReport, AppError> {
+        let lock = SyncLock::try_acquire(&state.config.database.url, *self).await?;
+        let result = self.sync_locked(state, range, progress).await;
+        if let Err(e) = lock.release().await {
+            warn!(dataset = %self, error = %e.message, "Failed to release the sync lock");
+        }
+        result
+    }
+
+    /// Fails the runs a previous process left `running`, unless another instance is
+    /// syncing the dataset right now and they may still be its own.
+    pub async fn fail_interrupted_runs(&self, state: &AppState) -> Result<u64, AppError> {
+        let lock = match SyncLock::try_acquire(&state.config.database.url, *self).await {
+            Ok(lock) => lock,
+            Err(e) if e.kind == ErrorKind::Conflict => return Ok(0),
+            Err(e) => return Err(e),
+        };
+        let result = state.sync_runs.fail_interrupted(*self).await;
+        if let Err(e) = lock.release().await {
+            warn!(dataset = %self, error = %e.message, "Failed to release the sync lock");
+        }
+        result
+    }
+
+    async fn sync_locked(
+        &self,
+        state: &AppState,
+        range: Option<SyncRange>,
+        progress: &SyncProgress,
+    ) -> Result<SyncReport, AppError> {
+        let range = match range {
+            Some(range) => range,
+            None => self.incremental_range(state).await,
+        };
+        let sync_runs = &state.sync_runs;
+        let before = progress.report();
+        let run_id = sync_runs
+            .start(*self, &state.config.midgard.url, &range)
+            .await?;
+
+        // Everything logged during the run, down to the Midgard pages, carries its id
+        let span = info_span!(
+            "sync_run",
+            run_id,
+            dataset = %self,
+            from = range.from,
+            to = range.to
+        );
+        let result = match self {
+            Dataset::Depths => {
+                proxy::get_prev_2_months_price_history(state, &range, progress)
+                    .instrument(span.clone())
+                    .await
+            }
+            Dataset::Earnings => {
+                proxy::get_prev_2_months_earning_history(state, &range, progress)
+                    .instrument(span.clone())
+                    .await
+            }
+            Dataset::Swaps => {
+                proxy::get_prev_2_months_swap_history(state, &range, progress)
+                    .instrument(span.clone())
+                    .await
+            }
+            Dataset::Runepool => {
+                proxy::get_prev_2_months_runepool_history(state, &range, progress)
+                    .instrument(span.clone())
+                    .await
+            }
+        };
+
+        let status = match &result {
+            Ok(_) => STATUS_SUCCEEDED,
+            Err(_) if progress.is_cancelled() => STATUS_CANCELLED,
+            Err(_) => STATUS_FAILED,
+        };
+        // The progress may be shared by several runs of one job, only record this run
+        let report = progress.report().since(&before);
+        state.metrics.rows_ingested(*self, report.rows_inserted);
+        sync_runs
+            .finish(run_id, status, &report, result.as_ref().err())
+            .await?;
+        span.in_scope(|| match &result {
+            Ok(_) => info!(
+                pages = report.pages_fetched,
+                rows_inserted = report.rows_inserted,
+                rows_skipped = report.rows_skipped,
+                "Sync finished"
+            ),
+            Err(e) => warn!(status, error = %e.message, "Sync did not finish"),
+        });
+        result.map(|_| report)
+    }
+}
+
+impl fmt::Display for Dataset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Dataset {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Dataset::ALL
+            .into_iter()
+            .find(|dataset| dataset.as_str() == s.to_lowercase())
+            .ok_or_else(|| AppError::validation(format!("Unknown dataset: {}", s)))
+    }
+}
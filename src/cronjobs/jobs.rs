@@ -1,35 +1,175 @@
-use crate::{client::proxy, error::AppError};
-use axum::http::StatusCode;
-use chrono::Utc;
-use cron::Schedule;
-use std::str::FromStr;
+use crate::{
+    config::scheduler::{JobSchedule, ReconcileSchedule},
+    cronjobs::dataset::Dataset,
+    error::{AppError, ErrorKind},
+    model::sync_run::{SyncProgress, SyncRange},
+    state::AppState,
+};
+use chrono::{DateTime, Utc};
 use tokio::time::sleep;
+use tracing::{error, info, info_span, warn, Instrument};
 
-pub async fn run() -> Result<(), AppError> {
-    let expression = "0 0 */23 * * *";
-    let schedule = Schedule::from_str(expression).map_err(|e| {
-        AppError::new(format!("Invalid cron expression: {}", e))
-            .with_status(StatusCode::INTERNAL_SERVER_ERROR)
-    })?;
+pub async fn run(state: AppState) -> Result<(), AppError> {
+    // Before any sync of this process starts, so none of its own runs can be taken for one
+    for dataset in Dataset::ALL {
+        match dataset.fail_interrupted_runs(&state).await {
+            Ok(0) => {}
+            Ok(runs) => warn!(%dataset, runs, "Marked runs interrupted by a restart as failed"),
+            Err(e) => warn!(%dataset, error = %e.message, "Failed to close interrupted runs"),
+        }
+    }
+
+    let config = state.config.scheduler.clone();
+    if config.api_only {
+        info!("Running in API-only mode, scheduled syncs are disabled");
+        return Ok(());
+    }
+
+    // Every dataset gets its own task so a failing feed never holds up the others
+    let mut handles = Dataset::ALL
+        .into_iter()
+        .map(|dataset| (dataset, config.job(dataset)))
+        .filter(|(dataset, job)| {
+            if !job.enabled {
+                info!(dataset = %dataset, "Scheduled sync is disabled");
+            }
+            job.enabled
+        })
+        .map(|(dataset, job)| {
+            let span = info_span!("scheduled_sync", dataset = %dataset);
+            state.shutdown.spawn(
+                run_job(
+                    state.clone(),
+                    dataset,
+                    job,
+                    config.run_on_startup,
+                    config.repair_gaps,
+                )
+                .instrument(span),
+            )
+        })
+        .collect::<Vec<_>>();
+    if let Some(reconcile) = config.reconcile {
+        handles.push(state.shutdown.spawn(
+            run_reconcile(state.clone(), reconcile).instrument(info_span!("reconciliation")),
+        ));
+    }
+
+    for handle in handles {
+        if let Err(e) = handle.await {
+            error!(error = %e, "Sync task stopped unexpectedly");
+        }
+    }
+
+    Ok(())
+}
+
+async fn run_job(
+    state: AppState,
+    dataset: Dataset,
+    job: JobSchedule,
+    run_on_startup: bool,
+    repair_gaps: bool,
+) {
+    if run_on_startup {
+        run_once(&state, dataset, repair_gaps).await;
+    }
+
+    loop {
+        let Some(next) = job.schedule.upcoming(Utc).next() else {
+            warn!("No upcoming schedule, stopping the sync");
+            return;
+        };
+        info!(next = %next, "Next sync scheduled");
+        if !sleep_until(&state, next).await {
+            return;
+        }
+
+        run_once(&state, dataset, repair_gaps).await;
+    }
+}
 
+async fn run_once(state: &AppState, dataset: Dataset, repair_gaps: bool) {
+    match dataset.sync(state, None, &SyncProgress::default()).await {
+        Ok(_) => {}
+        Err(e) if e.kind == ErrorKind::Conflict => {
+            info!(reason = %e.message, "Skipping the sync")
+        }
+        Err(e) => error!(code = e.kind.code(), error = %e.message, "Sync failed"),
+    }
+
+    if repair_gaps {
+        if let Err(e) = repair(state, dataset).await {
+            error!(code = e.kind.code(), error = %e.message, "Gap repair failed");
+        }
+    }
+}
+
+async fn repair(state: &AppState, dataset: Dataset) -> Result<(), AppError> {
+    let ranges = state.gaps.get_repair_ranges(dataset, &None).await?;
+    if ranges.is_empty() {
+        return Ok(());
+    }
+
+    info!(gaps = ranges.len(), "Repairing gaps");
+    let progress = SyncProgress::default();
+    for range in ranges {
+        if state.shutdown.is_requested() {
+            break;
+        }
+        dataset.sync(state, Some(range), &progress).await?;
+    }
+    let report = progress.report();
+    info!(rows_inserted = report.rows_inserted, "Gaps repaired");
+    Ok(())
+}
+
+async fn run_reconcile(state: AppState, reconcile: ReconcileSchedule) {
     loop {
-        println!("Starting the job");
-        let now = Utc::now();
-        let next = schedule
-            .upcoming(Utc)
-            .next()
-            .ok_or_else(|| AppError::new("Failed to calculate next schedule time"))?;
-
-        let duration = (next - now)
-            .to_std()
-            .map_err(|e| AppError::new(format!("Duration calculation error: {}", e)))?;
-
-        println!("Running the scheduled data sync🔄...");
-        match proxy::sync_all_data().await {
-            Ok(_) => println!("Data synced successfully ✅"),
-            Err(e) => println!("Failed ❌: {:#?}", e),
-        }
-        println!("Next job at: {}", next);
-        sleep(duration).await;
+        let Some(next) = reconcile.schedule.upcoming(Utc).next() else {
+            warn!("No upcoming schedule for the reconciliation, stopping it");
+            return;
+        };
+        info!(next = %next, "Next reconciliation scheduled");
+        if !sleep_until(&state, next).await {
+            return;
+        }
+
+        let range = SyncRange::last_hours(reconcile.window_hours);
+        for dataset in Dataset::ALL {
+            if state.shutdown.is_requested() {
+                return;
+            }
+            match state
+                .reconciliation
+                .reconcile(&state, dataset, range, reconcile.repair)
+                .await
+            {
+                Ok(report) => info!(
+                    dataset = %dataset,
+                    matched = report.matched,
+                    missing = report.missing.len(),
+                    mismatched = report.mismatches.len(),
+                    unexpected = report.unexpected.len(),
+                    repaired = report.repaired,
+                    "Reconciled"
+                ),
+                Err(e) => error!(
+                    dataset = %dataset,
+                    code = e.kind.code(),
+                    error = %e.message,
+                    "Reconciliation failed"
+                ),
+            }
+        }
+    }
+}
+
+/// Sleeps until `next`, `false` when a shutdown is requested first.
+async fn sleep_until(state: &AppState, next: DateTime<Utc>) -> bool {
+    let duration = (next - Utc::now()).to_std().unwrap_or_default();
+    tokio::select! {
+        _ = sleep(duration) => true,
+        _ = state.shutdown.requested() => false,
     }
 }
@@ -1 +1,4 @@
+pub mod dataset;
 pub mod jobs;
+pub mod sync_jobs;
+pub mod sync_lock;
@@ -0,0 +1,264 @@
+use chrono::Utc;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard},
+};
+use tracing::{info, info_span, Instrument};
+use uuid::Uuid;
+
+use crate::{
+    cronjobs::dataset::Dataset,
+    error::AppError,
+    model::{
+        sync_job::{SyncJob, SyncJobKind, SyncJobStatus},
+        sync_run::{SyncProgress, SyncRange},
+    },
+    state::AppState,
+};
+
+// Finished jobs are kept around so their outcome can still be looked up
+const MAX_FINISHED_JOBS: usize = 50;
+
+#[derive(Clone)]
+struct TrackedJob {
+    job: SyncJob,
+    ranges: Vec<SyncRange>,
+    progress: Arc<SyncProgress>,
+}
+
+impl TrackedJob {
+    fn snapshot(&self) -> SyncJob {
+        let mut job = self.job.clone();
+        job.report = self.progress.report();
+
+        // Ranges are fetched in order, so everything before the cursor is done
+        let cursor = self.progress.cursor();
+        if cursor > 0 {
+            let total: i64 = self.ranges.iter().map(|r| r.to - r.from).sum();
+            let covered: i64 = self
+                .ranges
+                .iter()
+                .map(|r| (cursor - r.from).clamp(0, r.to - r.from))
+                .sum();
+            job.cursor = Some(cursor);
+            job.progress = Some((covered as f64 / total.max(1) as f64).clamp(0.0, 1.0));
+        }
+        if job.status == SyncJobStatus::Succeeded {
+            job.progress = Some(1.0);
+        }
+        job
+    }
+}
+
+/// Registry of this instance's manual jobs, kept in [`AppState`].
+#[derive(Default)]
+pub struct SyncJobs {
+    jobs: Mutex<HashMap<Uuid, TrackedJob>>,
+}
+
+impl SyncJobs {
+    fn jobs(&self) -> MutexGuard<'_, HashMap<Uuid, TrackedJob>> {
+        self.jobs.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Starts a manual job syncing `ranges` of `dataset` one after the other in the
+/// background. Only one manual job per dataset runs at a time.
+pub fn spawn(
+    state: &AppState,
+    dataset: Dataset,
+    pool: Option<String>,
+    kind: SyncJobKind,
+    ranges: Vec<SyncRange>,
+) -> Result<SyncJob, AppError> {
+    if pool.is_some() && pool.as_deref() != dataset.pool() {
+        return Err(AppError::invalid_field(
+            "pool",
+            format!(
+                "Pool {} is not synced for {}, expected {}",
+                pool.unwrap_or_default(),
+                dataset,
+                dataset.pool().unwrap_or("no pool")
+            ),
+        ));
+    }
+
+    let mut jobs = spawn_all(state, kind, vec![(dataset, ranges)])?;
+    Ok(jobs.remove(0))
+}
+
+/// Starts a manual job per dataset like [`spawn`], all of them or none: if any of the
+/// datasets already has a job running nothing is started.
+pub fn spawn_all(
+    state: &AppState,
+    kind: SyncJobKind,
+    datasets: Vec<(Dataset, Vec<SyncRange>)>,
+) -> Result<Vec<SyncJob>, AppError> {
+    let tracked = datasets
+        .into_iter()
+        .map(|(dataset, ranges)| track(dataset, kind, ranges))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    {
+        let mut jobs = state.sync_jobs.jobs();
+        for new in &tracked {
+            let dataset = new.job.dataset;
+            if let Some(running) = jobs
+                .values()
+                .find(|t| t.job.dataset == dataset && t.job.status == SyncJobStatus::Running)
+            {
+                return Err(AppError::conflict(format!(
+                    "A sync job is already running for {}: {}",
+                    dataset, running.job.id
+                )));
+            }
+        }
+        prune_finished(&mut jobs);
+        for new in &tracked {
+            jobs.insert(new.job.id, new.clone());
+        }
+    }
+
+    Ok(tracked
+        .into_iter()
+        .map(|tracked| start(state, tracked))
+        .collect())
+}
+
+fn track(
+    dataset: Dataset,
+    kind: SyncJobKind,
+    mut ranges: Vec<SyncRange>,
+) -> Result<TrackedJob, AppError> {
+    if ranges.is_empty() {
+        return Err(AppError::validation("Nothing to sync"));
+    }
+    ranges.sort_by_key(|range| range.from);
+
+    let job = SyncJob {
+        id: Uuid::new_v4(),
+        dataset,
+        pool: dataset.pool().map(str::to_string),
+        kind,
+        status: SyncJobStatus::Running,
+        range: Some(SyncRange {
+            from: ranges[0].from,
+            to: ranges.iter().map(|r| r.to).max().unwrap_or_default(),
+        }),
+        ranges: if kind == SyncJobKind::Repair {
+            ranges.clone()
+        } else {
+            Vec::new()
+        },
+        cursor: None,
+        progress: Some(0.0),
+        report: Default::default(),
+        started_at: Utc::now(),
+        finished_at: None,
+        error_message: None,
+    };
+    Ok(TrackedJob {
+        job,
+        ranges,
+        progress: Arc::new(SyncProgress::default()),
+    })
+}
+
+/// Runs a registered job in the background.
+fn start(state: &AppState, tracked: TrackedJob) -> SyncJob {
+    let TrackedJob {
+        job,
+        ranges,
+        progress,
+    } = tracked;
+    let id = job.id;
+    let dataset = job.dataset;
+    let shutdown = state.shutdown.clone();
+    let state = state.clone();
+    let span = info_span!("sync_job", job_id = %id, dataset = %dataset, kind = ?job.kind);
+    let task = async move {
+        info!("Running the manual sync job");
+        let mut result = Ok(());
+        for range in ranges {
+            // On shutdown the job ends between ranges, what is left has to be triggered again
+            if state.shutdown.is_requested() {
+                break;
+            }
+            if let Err(e) = dataset.sync(&state, Some(range), &progress).await {
+                result = Err(e);
+                break;
+            }
+        }
+
+        let mut jobs = state.sync_jobs.jobs();
+        if let Some(tracked) = jobs.get_mut(&id) {
+            tracked.job.finished_at = Some(Utc::now());
+            // A sync stopped by the shutdown saves what it fetched and returns Ok, so
+            // the job may not have covered its ranges
+            tracked.job.status = match &result {
+                Ok(_) if state.shutdown.is_requested() => SyncJobStatus::Interrupted,
+                Ok(_) => SyncJobStatus::Succeeded,
+                Err(_) if progress.is_cancelled() => SyncJobStatus::Cancelled,
+                Err(_) => SyncJobStatus::Failed,
+            };
+            tracked.job.error_message = result.err().map(|e| e.message);
+            info!(status = ?tracked.job.status, "Manual sync job finished");
+        }
+    };
+    shutdown.spawn(task.instrument(span));
+
+    job
+}
+
+pub fn list(state: &AppState) -> Vec<SyncJob> {
+    let mut jobs: Vec<SyncJob> = state
+        .sync_jobs
+        .jobs()
+        .values()
+        .map(TrackedJob::snapshot)
+        .collect();
+    jobs.sort_by_key(|job| std::cmp::Reverse(job.started_at));
+    jobs
+}
+
+pub fn get(state: &AppState, id: Uuid) -> Result<SyncJob, AppError> {
+    state
+        .sync_jobs
+        .jobs()
+        .get(&id)
+        .map(TrackedJob::snapshot)
+        .ok_or_else(|| not_found(id))
+}
+
+/// Asks a running job to stop; it finishes the page in flight and saves nothing.
+pub fn cancel(state: &AppState, id: Uuid) -> Result<SyncJob, AppError> {
+    let jobs = state.sync_jobs.jobs();
+    let tracked = jobs.get(&id).ok_or_else(|| not_found(id))?;
+    if tracked.job.status != SyncJobStatus::Running {
+        return Err(AppError::conflict(format!(
+            "Job {} is no longer running",
+            id
+        )));
+    }
+    tracked.progress.cancel();
+    Ok(tracked.snapshot())
+}
+
+fn not_found(id: Uuid) -> AppError {
+    AppError::not_found(format!("Sync job {} not found", id))
+}
+
+fn prune_finished(jobs: &mut HashMap<Uuid, TrackedJob>) {
+    let mut finished: Vec<_> = jobs
+        .values()
+        .filter(|t| t.job.status != SyncJobStatus::Running)
+        .map(|t| (t.job.started_at, t.job.id))
+        .collect();
+    if finished.len() < MAX_FINISHED_JOBS {
+        return;
+    }
+    finished.sort();
+    for (_, id) in finished.iter().take(finished.len() + 1 - MAX_FINISHED_JOBS) {
+        jobs.remove(id);
+    }
+}
@@ -0,0 +1,50 @@
+use sqlx::{Connection, PgConnection};
+
+use crate::{cronjobs::dataset::Dataset, error::AppError};
+
+/// Session-level Postgres advisory lock that keeps replicas from syncing the same
+/// dataset/pool at once. It lives on its own connection so a long sync doesn't tie up
+/// the shared pool, and Postgres drops it by itself if that connection dies.
+pub struct SyncLock {
+    conn: PgConnection,
+    key: String,
+}
+
+impl SyncLock {
+    /// Takes the lock without waiting, `409 Conflict` if another instance holds it.
+    pub async fn try_acquire(database_url: &str, dataset: Dataset) -> Result<Self, AppError> {
+        let key = format!("sync:{}:{}", dataset, dataset.pool().unwrap_or("*"));
+        let mut conn = PgConnection::connect(database_url)
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to open sync lock connection"))?;
+
+        let acquired: bool =
+            sqlx::query_scalar("SELECT pg_try_advisory_lock(hashtextextended($1, 0))")
+                .bind(&key)
+                .fetch_one(&mut conn)
+                .await
+                .map_err(|e| AppError::from(e).context("Failed to acquire sync lock"))?;
+
+        if !acquired {
+            return Err(AppError::conflict(format!(
+                "{} is already being synced by another instance",
+                dataset
+            )));
+        }
+
+        Ok(Self { conn, key })
+    }
+
+    pub async fn release(mut self) -> Result<(), AppError> {
+        sqlx::query("SELECT pg_advisory_unlock(hashtextextended($1, 0))")
+            .bind(&self.key)
+            .execute(&mut self.conn)
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to release sync lock"))?;
+
+        self.conn
+            .close()
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to close sync lock connection"))
+    }
+}
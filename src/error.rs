@@ -1,64 +1,265 @@
-use axum::{http::StatusCode, response::IntoResponse, Json};
+use axum::{
+    extract::rejection::{JsonRejection, PathRejection, QueryRejection},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use serde::Serialize;
 use serde_json::json;
 use std::fmt;
 
+/// What went wrong, which decides the status and `code` of the error response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The request is malformed, e.g. a query parameter of the wrong type.
+    Validation,
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    /// The request clashes with what is already going on, e.g. a sync that is running.
+    Conflict,
+    /// The request is well-formed but can't be processed, e.g. a simulation on an empty pool.
+    Unprocessable,
+    /// The API key is over its rate limit or daily quota.
+    RateLimited,
+    /// Midgard failed or answered with something unusable.
+    Upstream,
+    /// The database is unreachable or timed out.
+    Database,
+    Internal,
+}
+
+impl ErrorKind {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ErrorKind::Validation => StatusCode::BAD_REQUEST,
+            ErrorKind::Unauthorized => StatusCode::UNAUTHORIZED,
+            ErrorKind::Forbidden => StatusCode::FORBIDDEN,
+            ErrorKind::NotFound => StatusCode::NOT_FOUND,
+            ErrorKind::Conflict => StatusCode::CONFLICT,
+            ErrorKind::Unprocessable => StatusCode::UNPROCESSABLE_ENTITY,
+            ErrorKind::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            ErrorKind::Upstream => StatusCode::BAD_GATEWAY,
+            ErrorKind::Database => StatusCode::SERVICE_UNAVAILABLE,
+            ErrorKind::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Machine-readable `code` of the error response.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ErrorKind::Validation => "validation_error",
+            ErrorKind::Unauthorized => "unauthorized",
+            ErrorKind::Forbidden => "forbidden",
+            ErrorKind::NotFound => "not_found",
+            ErrorKind::Conflict => "conflict",
+            ErrorKind::Unprocessable => "unprocessable",
+            ErrorKind::RateLimited => "rate_limited",
+            ErrorKind::Upstream => "upstream_error",
+            ErrorKind::Database => "database_unavailable",
+            ErrorKind::Internal => "internal_error",
+        }
+    }
+}
+
+/// A problem with one field of the request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
 #[derive(Debug)]
 pub struct AppError {
-    pub status_code: Option<StatusCode>,
+    pub kind: ErrorKind,
     pub message: String,
+    pub details: Vec<FieldError>,
 }
 
 impl AppError {
+    /// An internal error, use the other constructors for anything the caller can act on.
     pub fn new(message: impl Into<String>) -> Self {
+        Self::with_kind(ErrorKind::Internal, message)
+    }
+
+    pub fn with_kind(kind: ErrorKind, message: impl Into<String>) -> Self {
         Self {
-            status_code: None,
+            kind,
             message: message.into(),
+            details: Vec::new(),
         }
     }
 
-    pub fn with_status(mut self, status_code: StatusCode) -> Self {
-        self.status_code = Some(status_code);
+    pub fn validation(message: impl Into<String>) -> Self {
+        Self::with_kind(ErrorKind::Validation, message)
+    }
+
+    /// A validation error about a single field.
+    pub fn invalid_field(field: impl Into<String>, message: impl Into<String>) -> Self {
+        let message = message.into();
+        Self::validation(message.clone()).with_field(field, message)
+    }
+
+    pub fn unauthorized(message: impl Into<String>) -> Self {
+        Self::with_kind(ErrorKind::Unauthorized, message)
+    }
+
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        Self::with_kind(ErrorKind::Forbidden, message)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::with_kind(ErrorKind::NotFound, message)
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        Self::with_kind(ErrorKind::Conflict, message)
+    }
+
+    pub fn unprocessable(message: impl Into<String>) -> Self {
+        Self::with_kind(ErrorKind::Unprocessable, message)
+    }
+
+    pub fn rate_limited(message: impl Into<String>) -> Self {
+        Self::with_kind(ErrorKind::RateLimited, message)
+    }
+
+    pub fn upstream(message: impl Into<String>) -> Self {
+        Self::with_kind(ErrorKind::Upstream, message)
+    }
+
+    pub fn with_field(mut self, field: impl Into<String>, message: impl Into<String>) -> Self {
+        self.details.push(FieldError {
+            field: field.into(),
+            message: message.into(),
+        });
+        self
+    }
+
+    /// Prefixes the message with what was being done, keeping the kind and details.
+    pub fn context(mut self, context: impl fmt::Display) -> Self {
+        self.message = format!("{}: {}", context, self.message);
         self
     }
+
+    pub fn status(&self) -> StatusCode {
+        self.kind.status()
+    }
 }
 
-// Add common error conversions
+// Invalid input that only Postgres notices, a date it can't parse or a sort column
+// that doesn't exist, is the client's fault, and a duplicate key clashes with what is
+// stored. The database being unreachable is a 503, any other failing statement a bug.
 impl From<sqlx::Error> for AppError {
     fn from(err: sqlx::Error) -> Self {
-        AppError::new(err.to_string()).with_status(StatusCode::INTERNAL_SERVER_ERROR)
+        let kind = match &err {
+            sqlx::Error::RowNotFound => ErrorKind::NotFound,
+            sqlx::Error::Database(db) if db.is_unique_violation() => ErrorKind::Conflict,
+            sqlx::Error::Database(db) => match db.code() {
+                Some(code) if code.starts_with("22") || code == "42703" => ErrorKind::Validation,
+                // Connection exceptions, out of resources and the server shutting down
+                Some(code)
+                    if code.starts_with("08")
+                        || code.starts_with("53")
+                        || code.starts_with("57P") =>
+                {
+                    ErrorKind::Database
+                }
+                _ => ErrorKind::Internal,
+            },
+            sqlx::Error::Io(_)
+            | sqlx::Error::Tls(_)
+            | sqlx::Error::Protocol(_)
+            | sqlx::Error::PoolTimedOut
+            | sqlx::Error::PoolClosed
+            | sqlx::Error::WorkerCrashed => ErrorKind::Database,
+            _ => ErrorKind::Internal,
+        };
+        AppError::with_kind(kind, err.to_string())
     }
 }
 
 impl From<std::io::Error> for AppError {
     fn from(err: std::io::Error) -> Self {
-        AppError::new(err.to_string()).with_status(StatusCode::INTERNAL_SERVER_ERROR)
+        AppError::new(err.to_string())
+    }
+}
+
+impl From<QueryRejection> for AppError {
+    fn from(rejection: QueryRejection) -> Self {
+        rejection_error(rejection.body_text(), rejection.status())
+    }
+}
+
+impl From<PathRejection> for AppError {
+    fn from(rejection: PathRejection) -> Self {
+        rejection_error(rejection.body_text(), rejection.status())
+    }
+}
+
+impl From<JsonRejection> for AppError {
+    fn from(rejection: JsonRejection) -> Self {
+        rejection_error(rejection.body_text(), rejection.status())
+    }
+}
+
+// Axum words deserialization failures as `<what>: <field path>: <problem>`, the path
+// being left out when the input as a whole is wrong
+fn rejection_error(text: String, status: StatusCode) -> AppError {
+    let kind = if status.is_client_error() {
+        ErrorKind::Validation
+    } else {
+        ErrorKind::Internal
+    };
+    let field = text.split_once(": ").and_then(|(_, rest)| {
+        let (path, problem) = rest.split_once(": ")?;
+        let is_path = !path.is_empty()
+            && path
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "_.[]".contains(c));
+        is_path.then(|| (path.to_string(), problem.to_string()))
+    });
+
+    let error = AppError::with_kind(kind, text);
+    match field {
+        Some((field, problem)) => error.with_field(field, problem),
+        None => error,
     }
 }
 
 impl fmt::Display for AppError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "Error [{}]: {}",
-            self.status_code
-                .map_or("Unknown".to_string(), |s| s.to_string()),
-            self.message
-        )
+        write!(f, "Error [{}]: {}", self.status(), self.message)
     }
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> axum::response::Response {
-        let status = self
-            .status_code
-            .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
-        let body = Json(json!({
+        let status = self.status();
+        if status.is_server_error() {
+            tracing::error!(
+                status = status.as_u16(),
+                code = self.kind.code(),
+                "{}",
+                self.message
+            );
+        }
+
+        // Driver errors can carry SQL and connection details, those only go to the log
+        let message = match self.kind {
+            ErrorKind::Internal => "Internal server error".to_string(),
+            ErrorKind::Database => "The database is unavailable, try again later".to_string(),
+            _ => self.message,
+        };
+        let mut body = json!({
             "statusCode": status.as_u16(),
-            "message": self.message,
-        }));
+            "code": self.kind.code(),
+            "message": message,
+        });
+        if !self.details.is_empty() {
+            body["details"] = json!(self.details);
+        }
 
-        (status, body).into_response()
+        (status, Json(body)).into_response()
     }
 }
-
-
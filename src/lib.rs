@@ -0,0 +1,13 @@
+pub mod client;
+pub mod config;
+pub mod cronjobs;
+pub mod dtos;
+pub mod error;
+pub mod metrics;
+pub mod model;
+pub mod repository;
+pub mod routes;
+pub mod service;
+pub mod shutdown;
+pub mod state;
+pub mod utils;
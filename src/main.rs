@@ -1,57 +1,76 @@
-use axum::{http::StatusCode, routing::get, Router};
-use config::database;
 use dotenv::dotenv;
-use error::AppError;
-use routes::{
-    earning_history_route::get_all_earnings_history, price_history_route::get_price_depth_history,
-    rune_pool_route::get_all_runepools, swap_history_route::get_all_swap_history,
+use midguard_api::{
+    config::{app::AppConfig, database, logging},
+    cronjobs,
+    error::AppError,
+    routes,
+    shutdown::listen_for_signals,
+    state::AppState,
 };
-use tower_http::trace::TraceLayer;
-
-mod client;
-mod config;
-mod cronjobs;
-mod dtos;
-mod error;
-mod model;
-mod routes;
-mod service;
+use std::time::Duration;
+use tracing::{info, warn};
 
 #[tokio::main]
 async fn main() -> Result<(), AppError> {
     dotenv().ok();
 
-    println!("Connecting to database 📔");
-    config::database::initialize_database()
-        .await
-        .map_err(|e| AppError::new(e.to_string()).with_status(StatusCode::INTERNAL_SERVER_ERROR))?;
+    let config = AppConfig::load()?;
+    logging::init(&config.logging);
 
-    println!("Running database migrations 💿");
-    database::run_migrations()
-        .await
-        .map_err(|e| AppError::new(e.to_string()).with_status(StatusCode::INTERNAL_SERVER_ERROR))?;
-
-    tokio::task::spawn(async {
-        if let Err(e) = cronjobs::jobs::run().await {
-            eprintln!("Cronjob error: {}", e);
-        }
-    });
-
-    let app = Router::new()
-        .route("/depth-history", get(get_price_depth_history))
-        .route("/earning-history", get(get_all_earnings_history))
-        .route("/swap-history", get(get_all_swap_history))
-        .route("/runepool-history", get(get_all_runepools))
-        .layer(TraceLayer::new_for_http());
-
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:3000")
+    info!("Connecting to database");
+    let pool = database::connect(&config.database).await?;
+
+    info!("Running database migrations");
+    database::run_migrations(&pool).await?;
+
+    let address = format!("{}:{}", config.server.host, config.server.port);
+    let state = AppState::new(config, pool)?;
+    let shutdown = state.shutdown.clone();
+
+    tokio::task::spawn(listen_for_signals(shutdown.clone()));
+    shutdown.spawn(cronjobs::jobs::run(state.clone()));
+    let app = routes::router(state.clone());
+
+    let listener = tokio::net::TcpListener::bind(&address)
         .await
         .map_err(|e| AppError::new(e.to_string()))?;
-    println!("Running server at port🌐::{}", 3000);
+    info!(%address, "Server listening");
 
-    axum::serve(listener, app)
-        .await
-        .map_err(|e| AppError::new(e.to_string()).with_status(StatusCode::INTERNAL_SERVER_ERROR))?;
+    // Once a shutdown is requested the server stops accepting connections and
+    // finishes the requests in flight
+    let server = {
+        let shutdown = shutdown.clone();
+        let graceful = shutdown.clone();
+        let serve = axum::serve(listener, app)
+            .with_graceful_shutdown(async move { graceful.requested().await });
+        tokio::task::spawn(async move {
+            let served = serve.await;
+            // A server that stopped by itself takes the background tasks down with it
+            shutdown.trigger();
+            served
+        })
+    };
+
+    shutdown.requested().await;
+    let deadline = Duration::from_secs(state.config.server.shutdown_timeout_secs);
+    info!(
+        deadline_secs = deadline.as_secs(),
+        "Waiting for requests and syncs to finish"
+    );
+    let drained = tokio::time::timeout(deadline, async {
+        let served = server.await;
+        shutdown.drain().await;
+        served
+    })
+    .await;
 
+    state.pool.close().await;
+    match drained {
+        Ok(served) => served
+            .map_err(|e| AppError::new(e.to_string()))?
+            .map_err(|e| AppError::new(e.to_string()))?,
+        Err(_) => warn!("Shutdown deadline passed, exiting with work still in flight"),
+    }
+    info!("Shut down");
     Ok(())
 }
@@ -0,0 +1,216 @@
+use crate::{cronjobs::dataset::Dataset, error::AppError, state::AppState};
+use axum::http::StatusCode;
+use chrono::Utc;
+use prometheus::{
+    Encoder, GaugeVec, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+
+use std::{future::Future, sync::Arc, time::Instant};
+
+/// Prometheus metrics of one app, kept in its own registry so every [`AppState`]
+/// counts separately, e.g. one per test.
+#[derive(Clone)]
+pub struct Metrics {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_duration: HistogramVec,
+    query_duration: HistogramVec,
+    midgard_requests: IntCounterVec,
+    midgard_duration: HistogramVec,
+    rows_ingested: IntCounterVec,
+    db_connections: IntGaugeVec,
+    last_synced: IntGaugeVec,
+    freshness: GaugeVec,
+}
+
+impl Metrics {
+    pub fn new() -> Result<Self, AppError> {
+        let registry = Registry::new();
+        let inner = Inner {
+            http_requests: register(
+                &registry,
+                IntCounterVec::new(
+                    Opts::new("http_requests_total", "HTTP requests by route and status"),
+                    &["method", "route", "status"],
+                ),
+            )?,
+            http_duration: register(
+                &registry,
+                HistogramVec::new(
+                    HistogramOpts::new(
+                        "http_request_duration_seconds",
+                        "Latency of HTTP requests by route and status",
+                    ),
+                    &["method", "route", "status"],
+                ),
+            )?,
+            query_duration: register(
+                &registry,
+                HistogramVec::new(
+                    HistogramOpts::new(
+                        "db_query_duration_seconds",
+                        "Duration of the queries behind each endpoint, by service",
+                    ),
+                    &["service", "operation"],
+                ),
+            )?,
+            midgard_requests: register(
+                &registry,
+                IntCounterVec::new(
+                    Opts::new(
+                        "midgard_requests_total",
+                        "Midgard requests by dataset and outcome, every retry counted",
+                    ),
+                    &["dataset", "outcome"],
+                ),
+            )?,
+            midgard_duration: register(
+                &registry,
+                HistogramVec::new(
+                    HistogramOpts::new(
+                        "midgard_request_duration_seconds",
+                        "Latency of Midgard requests by dataset",
+                    ),
+                    &["dataset"],
+                ),
+            )?,
+            rows_ingested: register(
+                &registry,
+                IntCounterVec::new(
+                    Opts::new("sync_rows_ingested_total", "Rows saved by the syncs"),
+                    &["dataset"],
+                ),
+            )?,
+            db_connections: register(
+                &registry,
+                IntGaugeVec::new(
+                    Opts::new(
+                        "db_pool_connections",
+                        "Connections of the database pool by state",
+                    ),
+                    &["state"],
+                ),
+            )?,
+            last_synced: register(
+                &registry,
+                IntGaugeVec::new(
+                    Opts::new(
+                        "sync_last_interval_timestamp_seconds",
+                        "End of the newest stored interval",
+                    ),
+                    &["dataset", "pool"],
+                ),
+            )?,
+            freshness: register(
+                &registry,
+                GaugeVec::new(
+                    Opts::new(
+                        "sync_data_age_seconds",
+                        "Time since the end of the newest stored interval",
+                    ),
+                    &["dataset", "pool"],
+                ),
+            )?,
+            registry,
+        };
+        Ok(Self {
+            inner: Arc::new(inner),
+        })
+    }
+
+    /// Runs `query` and records how long it took under `service` and `operation`.
+    pub async fn time_query<T>(
+        &self,
+        service: &str,
+        operation: &str,
+        query: impl Future<Output = T>,
+    ) -> T {
+        let started = Instant::now();
+        let result = query.await;
+        self.inner
+            .query_duration
+            .with_label_values(&[service, operation])
+            .observe(started.elapsed().as_secs_f64());
+        result
+    }
+
+    /// Records one Midgard request, `outcome` being `ok` or what made it fail.
+    pub fn midgard_request(&self, dataset: Dataset, outcome: &str, started: Instant) {
+        self.inner
+            .midgard_requests
+            .with_label_values(&[dataset.as_str(), outcome])
+            .inc();
+        self.inner
+            .midgard_duration
+            .with_label_values(&[dataset.as_str()])
+            .observe(started.elapsed().as_secs_f64());
+    }
+
+    pub fn rows_ingested(&self, dataset: Dataset, rows: i64) {
+        self.inner
+            .rows_ingested
+            .with_label_values(&[dataset.as_str()])
+            .inc_by(rows.max(0) as u64);
+    }
+
+    pub fn http_request(&self, method: &str, route: &str, status: StatusCode, started: Instant) {
+        let status = status.as_str();
+        self.inner
+            .http_requests
+            .with_label_values(&[method, route, status])
+            .inc();
+        self.inner
+            .http_duration
+            .with_label_values(&[method, route, status])
+            .observe(started.elapsed().as_secs_f64());
+    }
+
+    /// The gauges read at scrape time, the pool and how fresh every dataset is.
+    pub async fn refresh(&self, state: &AppState) {
+        let size = state.pool.size() as i64;
+        let idle = state.pool.num_idle() as i64;
+        let connections = &self.inner.db_connections;
+        connections.with_label_values(&["idle"]).set(idle);
+        connections.with_label_values(&["in_use"]).set(size - idle);
+        connections
+            .with_label_values(&["max"])
+            .set(state.config.database.max_connections as i64);
+
+        let now = Utc::now().timestamp();
+        for dataset in Dataset::ALL {
+            let labels = [dataset.as_str(), dataset.pool().unwrap_or("")];
+            if let Some(last) = dataset.last_synced(state).await {
+                self.inner.last_synced.with_label_values(&labels).set(last);
+                self.inner
+                    .freshness
+                    .with_label_values(&labels)
+                    .set((now - last) as f64);
+            }
+        }
+    }
+
+    /// Everything registered, in the Prometheus text format.
+    pub fn render(&self) -> Result<String, AppError> {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.inner.registry.gather(), &mut buffer)
+            .map_err(|e| AppError::new(format!("Failed to encode metrics: {}", e)))?;
+        String::from_utf8(buffer).map_err(|e| AppError::new(e.to_string()))
+    }
+}
+
+fn register<T: prometheus::core::Collector + Clone + 'static>(
+    registry: &Registry,
+    metric: prometheus::Result<T>,
+) -> Result<T, AppError> {
+    let metric = metric.map_err(|e| AppError::new(format!("Invalid metric: {}", e)))?;
+    registry
+        .register(Box::new(metric.clone()))
+        .map_err(|e| AppError::new(format!("Failed to register metric: {}", e)))?;
+    Ok(metric)
+}
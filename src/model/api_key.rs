@@ -0,0 +1,141 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+use crate::{
+    error::AppError,
+    utils::validation::{Validate, Validator},
+};
+
+/// What an API key may call: `read` the data routes, `admin` everything.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    Read,
+    Admin,
+}
+
+impl Scope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::Read => "read",
+            Scope::Admin => "admin",
+        }
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Scope {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read" => Ok(Scope::Read),
+            "admin" => Ok(Scope::Admin),
+            _ => Err(AppError::new(format!("Unknown API key scope {}", s))),
+        }
+    }
+}
+
+/// A stored API key. Only a hash of the key itself is kept, `prefix` tells keys apart.
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiKey {
+    pub id: i64,
+    pub name: String,
+    pub prefix: String,
+    pub scopes: Vec<Scope>,
+    /// Overrides the configured requests per minute.
+    pub rate_limit_per_minute: Option<i32>,
+    /// Overrides the configured requests per UTC day.
+    pub daily_quota: Option<i64>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl ApiKey {
+    /// `admin` keys can call the read routes too.
+    pub fn allows(&self, scope: Scope) -> bool {
+        self.scopes.contains(&scope) || self.scopes.contains(&Scope::Admin)
+    }
+}
+
+fn default_scopes() -> Vec<Scope> {
+    vec![Scope::Read]
+}
+
+/// Body of `POST /admin/api-keys`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateApiKey {
+    pub name: String,
+    #[serde(default = "default_scopes")]
+    pub scopes: Vec<Scope>,
+    pub rate_limit_per_minute: Option<i32>,
+    pub daily_quota: Option<i64>,
+}
+
+impl Validate for CreateApiKey {
+    fn validate(&self) -> Result<(), AppError> {
+        let mut v = Validator::default();
+        v.check(!self.name.trim().is_empty(), "name", || {
+            "must not be empty".to_string()
+        });
+        v.check(!self.scopes.is_empty(), "scopes", || {
+            "must contain at least one scope".to_string()
+        });
+        if let Some(limit) = self.rate_limit_per_minute {
+            v.check(limit >= 1, "rate_limit_per_minute", || {
+                format!("must be at least 1, got {}", limit)
+            });
+        }
+        if let Some(quota) = self.daily_quota {
+            v.check(quota >= 1, "daily_quota", || {
+                format!("must be at least 1, got {}", quota)
+            });
+        }
+        v.finish()
+    }
+}
+
+/// Response of `POST /admin/api-keys`, the only time the key itself is shown.
+#[derive(Debug, Serialize)]
+pub struct CreatedApiKey {
+    pub key: String,
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+}
+
+/// Query of `GET /admin/api-keys/usage`.
+#[derive(Debug, Default, Deserialize)]
+pub struct UsageParams {
+    /// Days back from today, today included. 7 by default.
+    pub days: Option<i64>,
+    pub key_id: Option<i64>,
+}
+
+impl Validate for UsageParams {
+    fn validate(&self) -> Result<(), AppError> {
+        let mut v = Validator::default();
+        if let Some(days) = self.days {
+            v.check((1..=366).contains(&days), "days", || {
+                format!("must be between 1 and 366, got {}", days)
+            });
+        }
+        v.finish()
+    }
+}
+
+/// Requests one key made to one route on one UTC day.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ApiKeyUsage {
+    pub key_id: i64,
+    pub name: String,
+    pub day: NaiveDate,
+    pub route: String,
+    pub requests: i64,
+}
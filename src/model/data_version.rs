@@ -0,0 +1,10 @@
+use chrono::{DateTime, Utc};
+
+/// State of a history table that its responses are derived from. Appended rows move
+/// the newest `end_time`, rewritten rows get new ids and deleted ones lower the count.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DataVersion {
+    pub last_end_time: DateTime<Utc>,
+    pub last_id: i32,
+    pub rows: i64,
+}
@@ -1,20 +1,25 @@
-use chrono::{DateTime, TimeZone, Utc};
+use chrono::{DateTime, Utc};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
 use crate::dtos::responses::EarningInterval;
+use crate::error::AppError;
+use crate::utils::{
+    upstream::{number, timestamp},
+    validation::{Validate, Validator},
+};
 
 use super::earning_history_pool::EarningHistoryPool;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct QueryParams {
     pub interval: Option<String>,
-    pub limit: Option<i16>,
-    pub page: Option<i16>,
+    pub limit: Option<i32>,
+    pub page: Option<i32>,
     pub order: Option<String>,
     pub sort_by: Option<String>,
     pub date_range: Option<String>,
-    pub count: Option<i8>,
+    pub count: Option<i32>,
 
     // liquidity fees
     pub liquidity_fees_lt: Option<i64>,
@@ -73,7 +78,81 @@ pub struct QueryParams {
     pub pool_earnings_gt: Option<i32>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Columns `sort_by` accepts.
+pub const SORT_COLUMNS: &[&str] = &[
+    "id",
+    "start_time",
+    "end_time",
+    "liquidity_fees",
+    "block_rewards",
+    "earnings",
+    "bonding_earnings",
+    "liquidity_earnings",
+    "avg_node_count",
+    "rune_price_usd",
+];
+
+impl Validate for QueryParams {
+    fn validate(&self) -> Result<(), AppError> {
+        let mut v = Validator::default();
+        v.interval(&self.interval);
+        v.order(&self.order);
+        v.sort_by(&self.sort_by, SORT_COLUMNS);
+        v.date_range(&self.date_range);
+        v.page_size("count", self.count);
+        v.page_size("limit", self.limit);
+        // Unlike the other history queries, `page` is 0-based here
+        v.page(self.page, 0);
+        v.count_or_pages(self.count, self.limit, self.page);
+        v.finish()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BondingYieldParams {
+    pub interval: Option<String>,
+    pub date_range: Option<String>,
+    // Total bonded RUNE in 1e8 base units, falls back to the TOTAL_BOND env variable
+    pub total_bond: Option<i64>,
+}
+
+impl Validate for BondingYieldParams {
+    fn validate(&self) -> Result<(), AppError> {
+        let mut v = Validator::default();
+        v.interval(&self.interval);
+        v.date_range(&self.date_range);
+        if let Some(total_bond) = self.total_bond {
+            v.check(total_bond > 0, "total_bond", || {
+                format!("must be positive, got {}", total_bond)
+            });
+        }
+        v.finish()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BondingYieldBucket {
+    pub start_time: DateTime<Utc>,
+    pub duration_seconds: i64,
+    pub avg_node_count: f64,
+    pub block_rewards: i64,
+    pub bonding_earnings: i64,
+    pub liquidity_earnings: i64,
+    pub bonding_earnings_per_node: f64,
+    pub bonding_earnings_per_node_usd: f64,
+    pub bonding_share: f64,
+    pub liquidity_share: f64,
+    pub bond_apr: Option<f64>,
+    pub bond_apy: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BondingYield {
+    pub total_bond: Option<i64>,
+    pub buckets: Vec<BondingYieldBucket>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct EarningHistory {
     pub id: Option<i32>,
     pub start_time: DateTime<Utc>,
@@ -88,42 +167,42 @@ pub struct EarningHistory {
     pub pools: Option<Vec<EarningHistoryPool>>,
 }
 
-impl From<EarningInterval> for EarningHistory {
-    fn from(value: EarningInterval) -> Self {
+impl TryFrom<EarningInterval> for EarningHistory {
+    type Error = AppError;
+
+    fn try_from(value: EarningInterval) -> Result<Self, Self::Error> {
         let earnign_pools = value
             .pools
             .iter()
-            .map(|pool| EarningHistoryPool {
-                id: None,
-                earnings_history_id: None,
-                pool: pool.pool.clone(),
-                asset_liquidity_fees: pool.asset_liquidity_fees.parse().unwrap_or_default(),
-                rune_liquidity_fees: pool.rune_liquidity_fees.parse().unwrap_or_default(),
-                total_liquidity_fees_rune: pool
-                    .total_liquidity_fees_rune
-                    .parse()
-                    .unwrap_or_default(),
-                saver_earning: pool.saver_earning.parse().unwrap_or_default(),
-                rewards: pool.rewards.parse().unwrap_or_default(),
-                earnings: pool.earnings.parse().unwrap_or_default(),
+            .map(|pool| {
+                Ok(EarningHistoryPool {
+                    id: None,
+                    earnings_history_id: None,
+                    pool: pool.pool.clone(),
+                    asset_liquidity_fees: number("assetLiquidityFees", &pool.asset_liquidity_fees)?,
+                    rune_liquidity_fees: number("runeLiquidityFees", &pool.rune_liquidity_fees)?,
+                    total_liquidity_fees_rune: number(
+                        "totalLiquidityFeesRune",
+                        &pool.total_liquidity_fees_rune,
+                    )?,
+                    saver_earning: number("saverEarning", &pool.saver_earning)?,
+                    rewards: number("rewards", &pool.rewards)?,
+                    earnings: number("earnings", &pool.earnings)?,
+                })
             })
-            .collect();
-        Self {
+            .collect::<Result<Vec<_>, AppError>>()?;
+        Ok(Self {
             id: None,
-            start_time: Utc
-                .timestamp_opt(value.start_time.parse().unwrap_or(0), 0)
-                .unwrap(),
-            end_time: Utc
-                .timestamp_opt(value.end_time.parse().unwrap_or(0), 0)
-                .unwrap(),
-            avg_node_count: value.avg_node_count.parse().unwrap_or(Decimal::ZERO),
-            block_rewards: value.block_rewards.parse().unwrap_or(0),
-            bonding_earnings: value.bonding_earnings.parse().unwrap_or(0),
-            earnings: value.earnings.parse().unwrap_or(0),
-            liquidity_earnings: value.liquidity_earnings.parse().unwrap_or(0),
-            liquidity_fees: value.liquidity_fees.parse().unwrap_or(0),
-            rune_price_usd: value.rune_price_usd.parse().unwrap_or(Decimal::ZERO),
+            start_time: timestamp("startTime", &value.start_time)?,
+            end_time: timestamp("endTime", &value.end_time)?,
+            avg_node_count: number("avgNodeCount", &value.avg_node_count)?,
+            block_rewards: number("blockRewards", &value.block_rewards)?,
+            bonding_earnings: number("bondingEarnings", &value.bonding_earnings)?,
+            earnings: number("earnings", &value.earnings)?,
+            liquidity_earnings: number("liquidityEarnings", &value.liquidity_earnings)?,
+            liquidity_fees: number("liquidityFees", &value.liquidity_fees)?,
+            rune_price_usd: number("runePriceUsd", &value.rune_price_usd)?,
             pools: Some(earnign_pools),
-        }
+        })
     }
 }
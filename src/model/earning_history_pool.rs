@@ -2,6 +2,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::dtos::responses::Pool;
 
+#[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct EarningPoolQueryParams {
     pub pool_eq: Option<String>,
@@ -37,7 +38,7 @@ pub struct EarningPoolQueryParams {
     pub earnings_gt: Option<i32>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct EarningHistoryPool {
     pub id: Option<i32>,
     pub earnings_history_id: Option<i32>,
@@ -0,0 +1,56 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize)]
+pub struct GapParams {
+    pub dataset: Option<String>,
+    pub date_range: Option<String>,
+}
+
+/// Missing `[from, to)` stretch between two stored intervals.
+#[derive(Debug, Clone, Serialize)]
+pub struct IntervalGap {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub missing_intervals: i64,
+}
+
+/// Interval that starts before the previous one ended, e.g. a row saved twice.
+#[derive(Debug, Clone, Serialize)]
+pub struct IntervalOverlap {
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub previous_end_time: DateTime<Utc>,
+    pub duplicate: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DatasetGaps {
+    pub dataset: String,
+    pub pool: Option<String>,
+    pub intervals: i64,
+    pub first_start_time: Option<DateTime<Utc>>,
+    pub last_end_time: Option<DateTime<Utc>>,
+    pub missing_intervals: i64,
+    pub gaps: Vec<IntervalGap>,
+    pub overlaps: Vec<IntervalOverlap>,
+}
+
+/// Interval that doesn't start where the previous one ended, as the repositories
+/// report it before it's told apart as a gap or an overlap.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntervalBreak {
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub previous_start_time: DateTime<Utc>,
+    pub previous_end_time: DateTime<Utc>,
+}
+
+/// Totals of a history table together with every [`IntervalBreak`] in it.
+#[derive(Debug, Default)]
+pub struct IntervalBreaks {
+    pub intervals: i64,
+    pub first_start_time: Option<DateTime<Utc>>,
+    pub last_end_time: Option<DateTime<Utc>>,
+    pub breaks: Vec<IntervalBreak>,
+}
@@ -0,0 +1,61 @@
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Ok,
+    Failed,
+    /// Not run because a check it depends on failed.
+    Skipped,
+}
+
+/// Outcome of one readiness check.
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthCheck {
+    pub name: String,
+    pub status: CheckStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl HealthCheck {
+    pub fn ok(name: impl Into<String>, message: Option<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: CheckStatus::Ok,
+            message,
+        }
+    }
+
+    pub fn failed(name: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: CheckStatus::Failed,
+            message: Some(message.into()),
+        }
+    }
+
+    pub fn skipped(name: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: CheckStatus::Skipped,
+            message: Some(message.into()),
+        }
+    }
+}
+
+/// Body of `/readyz`, ready only when no check failed.
+#[derive(Debug, Clone, Serialize)]
+pub struct Readiness {
+    pub ready: bool,
+    pub checks: Vec<HealthCheck>,
+}
+
+impl Readiness {
+    pub fn new(checks: Vec<HealthCheck>) -> Self {
+        Self {
+            ready: checks.iter().all(|c| c.status != CheckStatus::Failed),
+            checks,
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize)]
+pub struct LpSimulationRequest {
+    pub pool: Option<String>,
+    pub deposit_time: DateTime<Utc>,
+    // Amounts are in Midgard base units (1e8)
+    pub rune_amount: Option<i64>,
+    pub asset_amount: Option<i64>,
+    pub interval: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct LpSimulationPoint {
+    pub time: DateTime<Utc>,
+    pub pool_share: f64,
+    pub position_rune: f64,
+    pub position_asset: f64,
+    pub position_value_rune: f64,
+    pub position_value_usd: f64,
+    pub hodl_value_rune: f64,
+    pub hodl_value_usd: f64,
+    pub impermanent_loss: f64,
+    pub fee_income_rune: f64,
+    pub fee_income_usd: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LpSimulationResponse {
+    pub pool: String,
+    pub deposit_time: DateTime<Utc>,
+    pub entry_time: DateTime<Utc>,
+    pub interval: String,
+    pub rune_amount: i64,
+    pub asset_amount: i64,
+    pub lp_units: f64,
+    pub points: Vec<LpSimulationPoint>,
+}
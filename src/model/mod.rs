@@ -1,5 +1,16 @@
+pub mod api_key;
+pub mod data_version;
 pub mod earning_history;
 pub mod earning_history_pool;
+pub mod gap;
+pub mod health;
+pub mod lp_simulation;
 pub mod price_history;
+pub mod reconciliation;
 pub mod rune_pool;
+pub mod rune_price;
+pub mod swap_analytics;
 pub mod swap_history;
+pub mod sync_job;
+pub mod sync_run;
+pub mod synth_analytics;
@@ -1,18 +1,23 @@
 use crate::dtos::responses::PriceDepthInterval;
-use chrono::{DateTime, TimeZone, Utc};
+use crate::error::AppError;
+use crate::utils::{
+    upstream::{number, timestamp},
+    validation::{Validate, Validator},
+};
+use chrono::{DateTime, Utc};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use sqlx::prelude::FromRow;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct PriceHistoryParams {
     pub interval: Option<String>,
-    pub limit: Option<i16>,
-    pub page: Option<i16>,
+    pub limit: Option<i32>,
+    pub page: Option<i32>,
     pub order: Option<String>,
     pub sort_by: Option<String>,
     pub date_range: Option<String>,
-    pub count: Option<i8>,
+    pub count: Option<i32>,
 
     // Asset depth filters
     pub asset_depth_gt: Option<i64>,
@@ -64,7 +69,39 @@ pub struct PriceHistoryParams {
     pub luvi_eq: Option<Decimal>,
 }
 
-#[derive(Debug, Deserialize, Serialize, FromRow)]
+/// Columns `sort_by` accepts.
+pub const SORT_COLUMNS: &[&str] = &[
+    "id",
+    "start_time",
+    "end_time",
+    "asset_depth",
+    "rune_depth",
+    "asset_price",
+    "asset_price_usd",
+    "liquidity_units",
+    "members_count",
+    "synth_units",
+    "synth_supply",
+    "units",
+    "luvi",
+];
+
+impl Validate for PriceHistoryParams {
+    fn validate(&self) -> Result<(), AppError> {
+        let mut v = Validator::default();
+        v.interval(&self.interval);
+        v.order(&self.order);
+        v.sort_by(&self.sort_by, SORT_COLUMNS);
+        v.date_range(&self.date_range);
+        v.page_size("count", self.count);
+        v.page_size("limit", self.limit);
+        v.page(self.page, 1);
+        v.count_or_pages(self.count, self.limit, self.page);
+        v.finish()
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, FromRow)]
 #[serde(rename_all = "camelCase")]
 pub struct PriceHistory {
     pub id: Option<i32>,
@@ -82,26 +119,24 @@ pub struct PriceHistory {
     pub luvi: Decimal,
 }
 
-impl From<PriceDepthInterval> for PriceHistory {
-    fn from(interval: PriceDepthInterval) -> Self {
-        Self {
+impl TryFrom<PriceDepthInterval> for PriceHistory {
+    type Error = AppError;
+
+    fn try_from(interval: PriceDepthInterval) -> Result<Self, Self::Error> {
+        Ok(Self {
             id: None,
-            start_time: Utc
-                .timestamp_opt(interval.start_time.parse().unwrap_or(0), 0)
-                .unwrap(),
-            end_time: Utc
-                .timestamp_opt(interval.end_time.parse().unwrap_or(0), 0)
-                .unwrap(),
-            asset_depth: interval.asset_depth.parse().unwrap_or(0),
-            rune_depth: interval.rune_depth.parse().unwrap_or(0),
-            asset_price: interval.asset_price.parse().unwrap_or(Decimal::ZERO),
-            asset_price_usd: interval.asset_price_usd.parse().unwrap_or(Decimal::ZERO),
-            liquidity_units: interval.liquidity_units.parse().unwrap_or(0),
-            members_count: interval.members_count.parse().unwrap_or(0),
-            synth_units: interval.synth_units.parse().unwrap_or(0),
-            synth_supply: interval.synth_supply.parse().unwrap_or(0),
-            units: interval.units.parse().unwrap_or(0),
-            luvi: interval.luvi.parse().unwrap_or(Decimal::ZERO),
-        }
+            start_time: timestamp("startTime", &interval.start_time)?,
+            end_time: timestamp("endTime", &interval.end_time)?,
+            asset_depth: number("assetDepth", &interval.asset_depth)?,
+            rune_depth: number("runeDepth", &interval.rune_depth)?,
+            asset_price: number("assetPrice", &interval.asset_price)?,
+            asset_price_usd: number("assetPriceUsd", &interval.asset_price_usd)?,
+            liquidity_units: number("liquidityUnits", &interval.liquidity_units)?,
+            members_count: number("membersCount", &interval.members_count)?,
+            synth_units: number("synthUnits", &interval.synth_units)?,
+            synth_supply: number("synthSupply", &interval.synth_supply)?,
+            units: number("units", &interval.units)?,
+            luvi: number("luvi", &interval.luvi)?,
+        })
     }
 }
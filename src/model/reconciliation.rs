@@ -0,0 +1,52 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::model::sync_run::SyncRange;
+
+/// Longest window a single reconcile request compares, it runs within the request.
+pub const MAX_RECONCILE_WINDOW_HOURS: i64 = 7 * 24;
+
+/// `from`/`to` are unix timestamps, the window defaults to the last 24 hours and is
+/// capped at `MAX_RECONCILE_WINDOW_HOURS`.
+#[derive(Debug, Deserialize)]
+pub struct ReconcileParams {
+    pub dataset: Option<String>,
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub repair: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldMismatch {
+    pub field: String,
+    pub stored: Value,
+    pub upstream: Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IntervalMismatch {
+    pub start_time: DateTime<Utc>,
+    pub fields: Vec<FieldMismatch>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DatasetReconciliation {
+    pub dataset: String,
+    pub pool: Option<String>,
+    pub range: SyncRange,
+    pub upstream_intervals: usize,
+    pub stored_intervals: usize,
+    pub matched: usize,
+    /// Intervals Midgard has that are not stored.
+    pub missing: Vec<DateTime<Utc>>,
+    /// Stored intervals Midgard no longer returns.
+    pub unexpected: Vec<DateTime<Utc>>,
+    /// Intervals stored more than once, divergent even when the first copy matches.
+    pub duplicates: Vec<DateTime<Utc>>,
+    pub mismatches: Vec<IntervalMismatch>,
+    /// Missing, mismatching and duplicated intervals rewritten from Midgard.
+    pub repaired: usize,
+    /// Unexpected intervals deleted.
+    pub removed: usize,
+}
@@ -1,10 +1,15 @@
-use chrono::{DateTime, TimeZone, Utc};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 
 use crate::dtos::responses::RunepoolInterval;
+use crate::error::AppError;
+use crate::utils::{
+    upstream::{number, timestamp},
+    validation::{Validate, Validator},
+};
 
-#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone, Default)]
 
 pub struct Runepool {
     pub id: Option<i32>,
@@ -26,20 +31,66 @@ pub struct QueryParams {
     pub units_gt: Option<i64>,
     pub units_lt: Option<i64>,
     pub units_eq: Option<i64>,
+    pub count_gt: Option<i64>,
+    pub count_lt: Option<i64>,
+    pub count_eq: Option<i64>,
 }
 
-impl From<RunepoolInterval> for Runepool {
-    fn from(value: RunepoolInterval) -> Self {
-        Self {
+/// Columns `sort_by` accepts.
+pub const SORT_COLUMNS: &[&str] = &["id", "count", "start_time", "end_time", "units"];
+
+impl Validate for QueryParams {
+    fn validate(&self) -> Result<(), AppError> {
+        let mut v = Validator::default();
+        v.interval(&self.interval);
+        v.order(&self.order);
+        v.sort_by(&self.sort_by, SORT_COLUMNS);
+        v.date_range(&self.date_range);
+        v.page_size("count", self.count);
+        v.page_size("limit", self.limit);
+        v.page(self.page, 1);
+        v.count_or_pages(self.count, self.limit, self.page);
+        v.finish()
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RunepoolAnalyticsParams {
+    pub interval: Option<String>,
+    pub date_range: Option<String>,
+}
+
+impl Validate for RunepoolAnalyticsParams {
+    fn validate(&self) -> Result<(), AppError> {
+        let mut v = Validator::default();
+        v.interval(&self.interval);
+        v.date_range(&self.date_range);
+        v.finish()
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct RunepoolAnalyticsBucket {
+    pub start_time: DateTime<Utc>,
+    pub count: i64,
+    pub units: i64,
+    pub member_change: i64,
+    pub member_growth: Option<f64>,
+    pub units_change: i64,
+    pub average_units_per_member: f64,
+    pub rune_price_usd: Option<f64>,
+}
+
+impl TryFrom<RunepoolInterval> for Runepool {
+    type Error = AppError;
+
+    fn try_from(value: RunepoolInterval) -> Result<Self, Self::Error> {
+        Ok(Self {
             id: None,
-            count: value.count.parse().unwrap_or(0),
-            start_time: Utc
-                .timestamp_opt(value.start_time.parse().unwrap_or(0), 0)
-                .unwrap(),
-            end_time: Utc
-                .timestamp_opt(value.end_time.parse().unwrap_or(0), 0)
-                .unwrap(),
-            units: value.units.parse().unwrap_or(0),
-        }
+            count: number("count", &value.count)?,
+            start_time: timestamp("startTime", &value.start_time)?,
+            end_time: timestamp("endTime", &value.end_time)?,
+            units: number("units", &value.units)?,
+        })
     }
 }
@@ -0,0 +1,59 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+use crate::utils::validation::{Validate, Validator};
+
+#[derive(Debug, Deserialize)]
+pub struct RunePriceParams {
+    pub interval: Option<String>,
+    pub date_range: Option<String>,
+    // Relative spread between sources above which an interval is flagged
+    pub threshold: Option<f64>,
+}
+
+impl Validate for RunePriceParams {
+    fn validate(&self) -> Result<(), AppError> {
+        let mut v = Validator::default();
+        v.interval(&self.interval);
+        v.date_range(&self.date_range);
+        if let Some(threshold) = self.threshold {
+            v.check(
+                threshold.is_finite() && threshold >= 0.0,
+                "threshold",
+                || format!("must be a non-negative number, got {}", threshold),
+            );
+        }
+        v.finish()
+    }
+}
+
+/// Where a RUNE/USD price was taken from, in order of preference.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RunePriceSource {
+    Swaps,
+    Earnings,
+    Depths,
+}
+
+#[derive(Debug, Serialize, Default)]
+pub struct RunePriceSourceCounts {
+    pub swaps: i64,
+    pub earnings: i64,
+    pub depths: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RunePricePoint {
+    pub start_time: DateTime<Utc>,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub source: RunePriceSource,
+    pub source_counts: RunePriceSourceCounts,
+    pub max_divergence: Option<f64>,
+    pub disagreement_count: i64,
+    pub disagreement: bool,
+}
@@ -0,0 +1,79 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+use crate::utils::validation::{Validate, Validator};
+
+/// Swap categories stored as `<category>_count/_fees/_volume/...` columns in `swap_history`.
+pub const SWAP_CATEGORIES: [&str; 6] = [
+    "to_asset",
+    "to_rune",
+    "synth_mint",
+    "synth_redeem",
+    "from_trade",
+    "to_trade",
+];
+
+#[derive(Debug, Deserialize)]
+pub struct SwapAnalyticsParams {
+    pub interval: Option<String>,
+    pub date_range: Option<String>,
+}
+
+impl Validate for SwapAnalyticsParams {
+    fn validate(&self) -> Result<(), AppError> {
+        let mut v = Validator::default();
+        v.interval(&self.interval);
+        v.date_range(&self.date_range);
+        v.finish()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct SwapCategoryBreakdown {
+    pub category: String,
+    pub count: i64,
+    pub fees: i64,
+    pub volume: i64,
+    pub fee_share: f64,
+    pub volume_share: f64,
+    pub effective_fee_rate: f64,
+    pub fee_revenue_usd: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SwapBreakdownBucket {
+    pub start_time: DateTime<Utc>,
+    pub total_count: i64,
+    pub total_fees: i64,
+    pub total_volume: i64,
+    pub effective_fee_rate: f64,
+    pub fee_revenue_usd: f64,
+    pub fees_change: Option<f64>,
+    pub volume_change: Option<f64>,
+    pub fee_revenue_usd_change: Option<f64>,
+    pub categories: Vec<SwapCategoryBreakdown>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CategorySlippage {
+    pub category: String,
+    pub count: i64,
+    pub weighted_average_slip: Option<f64>,
+    pub p50_slip: Option<f64>,
+    pub p90_slip: Option<f64>,
+    pub p99_slip: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SlippageBucket {
+    pub start_time: DateTime<Utc>,
+    pub total_count: i64,
+    pub weighted_average_slip: Option<f64>,
+    pub categories: Vec<CategorySlippage>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SlippageAnalytics {
+    pub buckets: Vec<SlippageBucket>,
+}
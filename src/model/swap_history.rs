@@ -1,9 +1,14 @@
-use chrono::{DateTime, TimeZone, Utc};
+use chrono::{DateTime, Utc};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use sqlx::prelude::FromRow;
 
 use crate::dtos::responses::SwapInterval;
+use crate::error::AppError;
+use crate::utils::{
+    upstream::{number, timestamp},
+    validation::{Validate, Validator},
+};
 
 #[derive(Deserialize, Debug)]
 pub struct QueryParams {
@@ -129,7 +134,65 @@ pub struct QueryParams {
     pub total_volume_eq: Option<i64>,
 }
 
-#[derive(Debug, Serialize, Deserialize, FromRow)]
+/// Columns `sort_by` accepts.
+pub const SORT_COLUMNS: &[&str] = &[
+    "id",
+    "average_slip",
+    "start_time",
+    "end_time",
+    "from_trade_average_slip",
+    "from_trade_count",
+    "from_trade_fees",
+    "from_trade_volume",
+    "from_trade_volume_usd",
+    "rune_price_usd",
+    "synth_mint_average_slip",
+    "synth_mint_count",
+    "synth_mint_fees",
+    "synth_mint_volume",
+    "synth_mint_volume_usd",
+    "synth_redeem_average_slip",
+    "synth_redeem_count",
+    "synth_redeem_fees",
+    "synth_redeem_volume",
+    "synth_redeem_volume_usd",
+    "to_asset_average_slip",
+    "to_asset_count",
+    "to_asset_fees",
+    "to_asset_volume",
+    "to_asset_volume_usd",
+    "to_rune_average_slip",
+    "to_rune_count",
+    "to_rune_fees",
+    "to_rune_volume",
+    "to_rune_volume_usd",
+    "to_trade_average_slip",
+    "to_trade_count",
+    "to_trade_fees",
+    "to_trade_volume",
+    "to_trade_volume_usd",
+    "total_count",
+    "total_fees",
+    "total_volume",
+    "total_volume_usd",
+];
+
+impl Validate for QueryParams {
+    fn validate(&self) -> Result<(), AppError> {
+        let mut v = Validator::default();
+        v.interval(&self.interval);
+        v.order(&self.order);
+        v.sort_by(&self.sort_by, SORT_COLUMNS);
+        v.date_range(&self.date_range);
+        v.page_size("count", self.count);
+        v.page_size("limit", self.limit);
+        v.page(self.page, 1);
+        v.count_or_pages(self.count, self.limit, self.page);
+        v.finish()
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, FromRow)]
 pub struct SwapHistory {
     pub id: Option<i32>,
     pub average_slip: Decimal,
@@ -172,52 +235,62 @@ pub struct SwapHistory {
     pub total_volume_usd: Decimal,
 }
 
-impl From<SwapInterval> for SwapHistory {
-    fn from(value: SwapInterval) -> Self {
-        Self {
+impl TryFrom<SwapInterval> for SwapHistory {
+    type Error = AppError;
+
+    fn try_from(value: SwapInterval) -> Result<Self, Self::Error> {
+        Ok(Self {
             id: None,
-            average_slip: value.average_slip.parse::<Decimal>().unwrap_or_default(),
-            start_time: Utc
-                .timestamp_opt(value.start_time.parse().unwrap_or(0), 0)
-                .unwrap(),
-            end_time: Utc
-                .timestamp_opt(value.end_time.parse().unwrap_or(0), 0)
-                .unwrap(),
-            from_trade_average_slip: value.from_trade_average_slip.parse().unwrap_or_default(),
-            from_trade_count: value.from_trade_count.parse().unwrap_or(0),
-            from_trade_fees: value.from_trade_fees.parse().unwrap_or(0),
-            from_trade_volume: value.from_trade_volume.parse().unwrap_or(0),
-            from_trade_volume_usd: value.from_trade_volume_usd.parse().unwrap_or_default(),
-            rune_price_usd: value.rune_price_usd.parse().unwrap_or_default(),
-            synth_mint_average_slip: value.synth_mint_average_slip.parse().unwrap_or_default(),
-            synth_mint_count: value.synth_mint_count.parse().unwrap_or(0),
-            synth_mint_fees: value.synth_mint_fees.parse().unwrap_or(0),
-            synth_mint_volume: value.synth_mint_volume.parse().unwrap_or(0),
-            synth_mint_volume_usd: value.synth_mint_volume_usd.parse().unwrap_or_default(),
-            synth_redeem_average_slip: value.synth_redeem_average_slip.parse().unwrap_or_default(),
-            synth_redeem_count: value.synth_redeem_count.parse().unwrap_or(0),
-            synth_redeem_fees: value.synth_redeem_fees.parse().unwrap_or(0),
-            synth_redeem_volume: value.synth_redeem_volume.parse().unwrap_or(0),
-            synth_redeem_volume_usd: value.synth_redeem_volume_usd.parse().unwrap_or_default(),
-            to_asset_average_slip: value.to_asset_average_slip.parse().unwrap_or_default(),
-            to_asset_count: value.to_asset_count.parse().unwrap_or(0),
-            to_asset_fees: value.to_asset_fees.parse().unwrap_or(0),
-            to_asset_volume: value.to_asset_volume.parse().unwrap_or(0),
-            to_asset_volume_usd: value.to_asset_volume_usd.parse().unwrap_or_default(),
-            to_rune_average_slip: value.to_rune_average_slip.parse().unwrap_or_default(),
-            to_rune_count: value.to_rune_count.parse().unwrap_or(0),
-            to_rune_fees: value.to_rune_fees.parse().unwrap_or(0),
-            to_rune_volume: value.to_rune_volume.parse().unwrap_or(0),
-            to_rune_volume_usd: value.to_rune_volume_usd.parse().unwrap_or_default(),
-            to_trade_average_slip: value.to_trade_average_slip.parse().unwrap_or_default(),
-            to_trade_count: value.to_trade_count.parse().unwrap_or(0),
-            to_trade_fees: value.to_trade_fees.parse().unwrap_or(0),
-            to_trade_volume: value.to_trade_volume.parse().unwrap_or(0),
-            to_trade_volume_usd: value.to_trade_volume_usd.parse().unwrap_or_default(),
-            total_count: value.total_count.parse().unwrap_or(0),
-            total_fees: value.total_fees.parse().unwrap_or(0),
-            total_volume: value.total_volume.parse().unwrap_or(0),
-            total_volume_usd: value.total_volume_usd.parse().unwrap_or_default(),
-        }
+            average_slip: number("averageSlip", &value.average_slip)?,
+            start_time: timestamp("startTime", &value.start_time)?,
+            end_time: timestamp("endTime", &value.end_time)?,
+            from_trade_average_slip: number(
+                "fromTradeAverageSlip",
+                &value.from_trade_average_slip,
+            )?,
+            from_trade_count: number("fromTradeCount", &value.from_trade_count)?,
+            from_trade_fees: number("fromTradeFees", &value.from_trade_fees)?,
+            from_trade_volume: number("fromTradeVolume", &value.from_trade_volume)?,
+            from_trade_volume_usd: number("fromTradeVolumeUsd", &value.from_trade_volume_usd)?,
+            rune_price_usd: number("runePriceUsd", &value.rune_price_usd)?,
+            synth_mint_average_slip: number(
+                "synthMintAverageSlip",
+                &value.synth_mint_average_slip,
+            )?,
+            synth_mint_count: number("synthMintCount", &value.synth_mint_count)?,
+            synth_mint_fees: number("synthMintFees", &value.synth_mint_fees)?,
+            synth_mint_volume: number("synthMintVolume", &value.synth_mint_volume)?,
+            synth_mint_volume_usd: number("synthMintVolumeUsd", &value.synth_mint_volume_usd)?,
+            synth_redeem_average_slip: number(
+                "synthRedeemAverageSlip",
+                &value.synth_redeem_average_slip,
+            )?,
+            synth_redeem_count: number("synthRedeemCount", &value.synth_redeem_count)?,
+            synth_redeem_fees: number("synthRedeemFees", &value.synth_redeem_fees)?,
+            synth_redeem_volume: number("synthRedeemVolume", &value.synth_redeem_volume)?,
+            synth_redeem_volume_usd: number(
+                "synthRedeemVolumeUsd",
+                &value.synth_redeem_volume_usd,
+            )?,
+            to_asset_average_slip: number("toAssetAverageSlip", &value.to_asset_average_slip)?,
+            to_asset_count: number("toAssetCount", &value.to_asset_count)?,
+            to_asset_fees: number("toAssetFees", &value.to_asset_fees)?,
+            to_asset_volume: number("toAssetVolume", &value.to_asset_volume)?,
+            to_asset_volume_usd: number("toAssetVolumeUsd", &value.to_asset_volume_usd)?,
+            to_rune_average_slip: number("toRuneAverageSlip", &value.to_rune_average_slip)?,
+            to_rune_count: number("toRuneCount", &value.to_rune_count)?,
+            to_rune_fees: number("toRuneFees", &value.to_rune_fees)?,
+            to_rune_volume: number("toRuneVolume", &value.to_rune_volume)?,
+            to_rune_volume_usd: number("toRuneVolumeUsd", &value.to_rune_volume_usd)?,
+            to_trade_average_slip: number("toTradeAverageSlip", &value.to_trade_average_slip)?,
+            to_trade_count: number("toTradeCount", &value.to_trade_count)?,
+            to_trade_fees: number("toTradeFees", &value.to_trade_fees)?,
+            to_trade_volume: number("toTradeVolume", &value.to_trade_volume)?,
+            to_trade_volume_usd: number("toTradeVolumeUsd", &value.to_trade_volume_usd)?,
+            total_count: number("totalCount", &value.total_count)?,
+            total_fees: number("totalFees", &value.total_fees)?,
+            total_volume: number("totalVolume", &value.total_volume)?,
+            total_volume_usd: number("totalVolumeUsd", &value.total_volume_usd)?,
+        })
     }
 }
@@ -0,0 +1,63 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    cronjobs::dataset::Dataset,
+    model::sync_run::{SyncRange, SyncReport},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncJobKind {
+    Sync,
+    Backfill,
+    Repair,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncJobStatus {
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+    /// Stopped by a shutdown, `progress` tells how far it got.
+    Interrupted,
+}
+
+/// Query of `POST /admin/sync/{dataset}`.
+#[derive(Debug, Default, Deserialize)]
+pub struct TriggerSyncParams {
+    pub pool: Option<String>,
+}
+
+/// Body of `POST /admin/sync/{dataset}/backfill`, `from`/`to` are unix timestamps.
+#[derive(Debug, Deserialize)]
+pub struct BackfillRequest {
+    pub from: i64,
+    pub to: i64,
+    pub pool: Option<String>,
+}
+
+/// A manually triggered sync running in the background.
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncJob {
+    pub id: Uuid,
+    pub dataset: Dataset,
+    pub pool: Option<String>,
+    pub kind: SyncJobKind,
+    pub status: SyncJobStatus,
+    pub range: Option<SyncRange>,
+    /// Gaps a repair job re-fetches one after the other.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ranges: Vec<SyncRange>,
+    pub cursor: Option<i64>,
+    /// Share of the range already fetched, between 0 and 1.
+    pub progress: Option<f64>,
+    #[serde(flatten)]
+    pub report: SyncReport,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub error_message: Option<String>,
+}
@@ -0,0 +1,121 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicI64, Ordering};
+
+/// Counters a sync fills in while it pages through Midgard.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct SyncReport {
+    pub pages_fetched: i32,
+    pub rows_inserted: i64,
+    pub rows_skipped: i64,
+}
+
+impl SyncReport {
+    /// Counters added since an earlier snapshot of the same progress.
+    pub fn since(&self, earlier: &SyncReport) -> SyncReport {
+        SyncReport {
+            pages_fetched: self.pages_fetched - earlier.pages_fetched,
+            rows_inserted: self.rows_inserted - earlier.rows_inserted,
+            rows_skipped: self.rows_skipped - earlier.rows_skipped,
+        }
+    }
+}
+
+/// Half-open `[from, to)` window of unix timestamps a sync covers.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct SyncRange {
+    pub from: i64,
+    pub to: i64,
+}
+
+impl SyncRange {
+    /// The last `hours` of complete 5min intervals, leaving out the one still in progress.
+    pub fn last_hours(hours: i64) -> Self {
+        let now = Utc::now().timestamp();
+        let to = now - now % 300;
+        Self {
+            from: to - hours * 3600,
+            to,
+        }
+    }
+
+    /// Whether an interval starting at `start_time` falls inside the range.
+    pub fn contains(&self, start_time: DateTime<Utc>) -> bool {
+        let start = start_time.timestamp();
+        start >= self.from && start < self.to
+    }
+}
+
+/// Live counters of a running sync, shared with whoever wants to watch or cancel it.
+#[derive(Debug, Default)]
+pub struct SyncProgress {
+    pages_fetched: AtomicI32,
+    rows_inserted: AtomicI64,
+    rows_skipped: AtomicI64,
+    cursor: AtomicI64,
+    cancelled: AtomicBool,
+}
+
+impl SyncProgress {
+    pub fn page_fetched(&self, cursor: i64) {
+        self.pages_fetched.fetch_add(1, Ordering::Relaxed);
+        self.cursor.store(cursor, Ordering::Relaxed);
+    }
+
+    pub fn rows_inserted(&self, rows: usize) {
+        self.rows_inserted.fetch_add(rows as i64, Ordering::Relaxed);
+    }
+
+    pub fn rows_skipped(&self, rows: usize) {
+        self.rows_skipped.fetch_add(rows as i64, Ordering::Relaxed);
+    }
+
+    /// End time of the last fetched page, 0 before the first page.
+    pub fn cursor(&self) -> i64 {
+        self.cursor.load(Ordering::Relaxed)
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn report(&self) -> SyncReport {
+        SyncReport {
+            pages_fetched: self.pages_fetched.load(Ordering::Relaxed),
+            rows_inserted: self.rows_inserted.load(Ordering::Relaxed),
+            rows_skipped: self.rows_skipped.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, FromRow, Clone)]
+pub struct SyncRun {
+    pub id: i32,
+    pub dataset: String,
+    pub pool: Option<String>,
+    pub provider: String,
+    pub status: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Window the run synced, `None` for runs recorded before it was tracked.
+    pub range_from: Option<DateTime<Utc>>,
+    pub range_to: Option<DateTime<Utc>>,
+    pub pages_fetched: i32,
+    pub rows_inserted: i64,
+    pub rows_skipped: i64,
+    pub error_message: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DatasetSyncStatus {
+    pub dataset: String,
+    pub pool: Option<String>,
+    pub latest_run: Option<SyncRun>,
+    pub newest_end_time: Option<DateTime<Utc>>,
+    pub lag_seconds: Option<i64>,
+}
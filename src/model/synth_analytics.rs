@@ -0,0 +1,66 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::client::proxy::DEPTH_POOL;
+use crate::error::AppError;
+use crate::utils::validation::{Validate, Validator};
+
+#[derive(Debug, Deserialize)]
+pub struct SynthAnalyticsParams {
+    pub pool: Option<String>,
+    pub interval: Option<String>,
+    pub date_range: Option<String>,
+    // THORChain MaxSynthPerPoolDepth mimir, in basis points of total pool depth
+    pub max_synth_per_pool_depth: Option<i64>,
+}
+
+impl Validate for SynthAnalyticsParams {
+    fn validate(&self) -> Result<(), AppError> {
+        let mut v = Validator::default();
+        if let Some(pool) = &self.pool {
+            v.check(pool == DEPTH_POOL, "pool", || {
+                format!("depth history is only available for pool {}", DEPTH_POOL)
+            });
+        }
+        v.interval(&self.interval);
+        v.date_range(&self.date_range);
+        if let Some(bps) = self.max_synth_per_pool_depth {
+            v.check(
+                (0..=10_000).contains(&bps),
+                "max_synth_per_pool_depth",
+                || format!("must be between 0 and 10000 bps, got {}", bps),
+            );
+        }
+        v.finish()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct SynthAnalyticsBucket {
+    pub start_time: DateTime<Utc>,
+    pub asset_depth: i64,
+    pub units: i64,
+    pub synth_units: i64,
+    pub synth_supply: i64,
+    pub synth_utilisation: f64,
+    pub synth_cap: i64,
+    pub synth_cap_headroom: i64,
+    pub synth_cap_headroom_ratio: f64,
+    pub network: SynthNetworkFlows,
+}
+
+/// Synth mints and redeems of the bucket across all pools. Midgard's swap history is
+/// network-wide, so these can't be attributed to the pool of the bucket.
+#[derive(Debug, Serialize)]
+pub struct SynthNetworkFlows {
+    pub synth_mint_volume: i64,
+    pub synth_redeem_volume: i64,
+    pub net_mint_volume: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SynthAnalytics {
+    pub pool: String,
+    pub max_synth_per_pool_depth: i64,
+    pub buckets: Vec<SynthAnalyticsBucket>,
+}
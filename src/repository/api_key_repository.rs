@@ -0,0 +1,333 @@
+use crate::{
+    error::AppError,
+    model::api_key::{ApiKey, ApiKeyUsage, CreateApiKey, Scope},
+};
+use async_trait::async_trait;
+use chrono::{Duration, NaiveDate, Utc};
+use sqlx::{postgres::PgRow, PgPool, Row};
+use std::{collections::HashMap, sync::RwLock};
+use tracing::instrument;
+
+#[async_trait]
+pub trait ApiKeyRepository: Send + Sync {
+    /// Stores a new key under the hash of the key, which itself is never stored.
+    async fn create(
+        &self,
+        request: &CreateApiKey,
+        prefix: &str,
+        key_hash: &str,
+    ) -> Result<ApiKey, AppError>;
+
+    async fn list(&self) -> Result<Vec<ApiKey>, AppError>;
+
+    /// Revokes the key, `None` when there is no key with that id.
+    async fn revoke(&self, id: i64) -> Result<Option<ApiKey>, AppError>;
+
+    /// The key with this hash, unless it is revoked.
+    async fn find_active(&self, key_hash: &str) -> Result<Option<ApiKey>, AppError>;
+
+    /// Counts one request of the key to `route` on `day` and returns the key's requests
+    /// that day across routes, this one included. Concurrent requests of a key are
+    /// counted one after the other, so no two of them get the same total. `last_used_at`
+    /// is kept to the minute.
+    async fn record_usage(&self, key_id: i64, day: NaiveDate, route: &str)
+        -> Result<i64, AppError>;
+
+    /// Requests per key, day and route since `since`, busiest first within a day.
+    async fn usage(
+        &self,
+        since: NaiveDate,
+        key_id: Option<i64>,
+    ) -> Result<Vec<ApiKeyUsage>, AppError>;
+}
+
+const COLUMNS: &str = "id, name, prefix, scopes, rate_limit_per_minute, daily_quota, \
+                       created_at, last_used_at, revoked_at";
+
+pub struct PgApiKeyRepository {
+    pool: PgPool,
+}
+
+impl PgApiKeyRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+fn api_key_from_row(row: &PgRow) -> Result<ApiKey, AppError> {
+    let scopes = row
+        .try_get::<Vec<String>, _>("scopes")?
+        .iter()
+        .map(|scope| scope.parse())
+        .collect::<Result<Vec<Scope>, AppError>>()?;
+    Ok(ApiKey {
+        id: row.try_get("id")?,
+        name: row.try_get("name")?,
+        prefix: row.try_get("prefix")?,
+        scopes,
+        rate_limit_per_minute: row.try_get("rate_limit_per_minute")?,
+        daily_quota: row.try_get("daily_quota")?,
+        created_at: row.try_get("created_at")?,
+        last_used_at: row.try_get("last_used_at")?,
+        revoked_at: row.try_get("revoked_at")?,
+    })
+}
+
+#[async_trait]
+impl ApiKeyRepository for PgApiKeyRepository {
+    #[instrument(skip_all)]
+    async fn create(
+        &self,
+        request: &CreateApiKey,
+        prefix: &str,
+        key_hash: &str,
+    ) -> Result<ApiKey, AppError> {
+        let scopes: Vec<&str> = request.scopes.iter().map(Scope::as_str).collect();
+        let row = sqlx::query(&format!(
+            "INSERT INTO api_keys (name, prefix, key_hash, scopes, rate_limit_per_minute, daily_quota) \
+             VALUES ($1, $2, $3, $4, $5, $6) RETURNING {}",
+            COLUMNS
+        ))
+        .bind(request.name.trim())
+        .bind(prefix)
+        .bind(key_hash)
+        .bind(&scopes)
+        .bind(request.rate_limit_per_minute)
+        .bind(request.daily_quota)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| AppError::from(e).context("Failed to create API key"))?;
+        api_key_from_row(&row)
+    }
+
+    #[instrument(skip_all)]
+    async fn list(&self) -> Result<Vec<ApiKey>, AppError> {
+        sqlx::query(&format!("SELECT {} FROM api_keys ORDER BY id", COLUMNS))
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to list API keys"))?
+            .iter()
+            .map(api_key_from_row)
+            .collect()
+    }
+
+    #[instrument(skip_all)]
+    async fn revoke(&self, id: i64) -> Result<Option<ApiKey>, AppError> {
+        let row = sqlx::query(&format!(
+            "UPDATE api_keys SET revoked_at = COALESCE(revoked_at, now()) WHERE id = $1 RETURNING {}",
+            COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AppError::from(e).context("Failed to revoke API key"))?;
+        row.as_ref().map(api_key_from_row).transpose()
+    }
+
+    #[instrument(skip_all)]
+    async fn find_active(&self, key_hash: &str) -> Result<Option<ApiKey>, AppError> {
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM api_keys WHERE key_hash = $1 AND revoked_at IS NULL",
+            COLUMNS
+        ))
+        .bind(key_hash)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AppError::from(e).context("Failed to look up API key"))?;
+        row.as_ref().map(api_key_from_row).transpose()
+    }
+
+    #[instrument(skip_all)]
+    async fn record_usage(
+        &self,
+        key_id: i64,
+        day: NaiveDate,
+        route: &str,
+    ) -> Result<i64, AppError> {
+        let context = "Failed to record API key usage";
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| AppError::from(e).context(context))?;
+
+        // Locks the key until commit, so the sum below sees every earlier request of it.
+        // Requests of other keys don't wait, and last_used_at is only rewritten once a
+        // minute, so a busy key doesn't leave a dead api_keys row behind on every request.
+        sqlx::query(
+            "WITH locked AS ( \
+                 SELECT id, last_used_at FROM api_keys WHERE id = $1 FOR NO KEY UPDATE) \
+             UPDATE api_keys SET last_used_at = now() FROM locked \
+             WHERE api_keys.id = locked.id \
+               AND (locked.last_used_at IS NULL \
+                    OR locked.last_used_at < now() - INTERVAL '1 minute')",
+        )
+        .bind(key_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| AppError::from(e).context(context))?;
+        let requests: i64 = sqlx::query_scalar(
+            "WITH counted AS ( \
+                 INSERT INTO api_key_usage (key_id, day, route, requests) VALUES ($1, $2, $3, 1) \
+                 ON CONFLICT (key_id, day, route) \
+                 DO UPDATE SET requests = api_key_usage.requests + 1 \
+                 RETURNING requests) \
+             SELECT ((SELECT requests FROM counted) + COALESCE(SUM(requests), 0))::BIGINT \
+             FROM api_key_usage WHERE key_id = $1 AND day = $2 AND route <> $3",
+        )
+        .bind(key_id)
+        .bind(day)
+        .bind(route)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| AppError::from(e).context(context))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::from(e).context(context))?;
+        Ok(requests)
+    }
+
+    #[instrument(skip_all)]
+    async fn usage(
+        &self,
+        since: NaiveDate,
+        key_id: Option<i64>,
+    ) -> Result<Vec<ApiKeyUsage>, AppError> {
+        let rows = sqlx::query(
+            "SELECT u.key_id, k.name, u.day, u.route, u.requests \
+             FROM api_key_usage u JOIN api_keys k ON k.id = u.key_id \
+             WHERE u.day >= $1 AND ($2::BIGINT IS NULL OR u.key_id = $2) \
+             ORDER BY u.day DESC, u.requests DESC, u.key_id, u.route",
+        )
+        .bind(since)
+        .bind(key_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AppError::from(e).context("Failed to read API key usage"))?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(ApiKeyUsage {
+                    key_id: row.try_get("key_id")?,
+                    name: row.try_get("name")?,
+                    day: row.try_get("day")?,
+                    route: row.try_get("route")?,
+                    requests: row.try_get("requests")?,
+                })
+            })
+            .collect()
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryApiKeyRepository {
+    keys: RwLock<Vec<(ApiKey, String)>>,
+    usage: RwLock<HashMap<(i64, NaiveDate, String), i64>>,
+}
+
+#[async_trait]
+impl ApiKeyRepository for InMemoryApiKeyRepository {
+    async fn create(
+        &self,
+        request: &CreateApiKey,
+        prefix: &str,
+        key_hash: &str,
+    ) -> Result<ApiKey, AppError> {
+        let mut keys = self.keys.write().unwrap_or_else(|e| e.into_inner());
+        let api_key = ApiKey {
+            id: keys.len() as i64 + 1,
+            name: request.name.trim().to_string(),
+            prefix: prefix.to_string(),
+            scopes: request.scopes.clone(),
+            rate_limit_per_minute: request.rate_limit_per_minute,
+            daily_quota: request.daily_quota,
+            created_at: Utc::now(),
+            last_used_at: None,
+            revoked_at: None,
+        };
+        keys.push((api_key.clone(), key_hash.to_string()));
+        Ok(api_key)
+    }
+
+    async fn list(&self) -> Result<Vec<ApiKey>, AppError> {
+        let keys = self.keys.read().unwrap_or_else(|e| e.into_inner());
+        Ok(keys.iter().map(|(key, _)| key.clone()).collect())
+    }
+
+    async fn revoke(&self, id: i64) -> Result<Option<ApiKey>, AppError> {
+        let mut keys = self.keys.write().unwrap_or_else(|e| e.into_inner());
+        Ok(keys
+            .iter_mut()
+            .find(|(key, _)| key.id == id)
+            .map(|(key, _)| {
+                key.revoked_at.get_or_insert_with(Utc::now);
+                key.clone()
+            }))
+    }
+
+    async fn find_active(&self, key_hash: &str) -> Result<Option<ApiKey>, AppError> {
+        let keys = self.keys.read().unwrap_or_else(|e| e.into_inner());
+        Ok(keys
+            .iter()
+            .find(|(key, hash)| hash == key_hash && key.revoked_at.is_none())
+            .map(|(key, _)| key.clone()))
+    }
+
+    async fn record_usage(
+        &self,
+        key_id: i64,
+        day: NaiveDate,
+        route: &str,
+    ) -> Result<i64, AppError> {
+        let mut keys = self.keys.write().unwrap_or_else(|e| e.into_inner());
+        if let Some((key, _)) = keys.iter_mut().find(|(key, _)| key.id == key_id) {
+            let now = Utc::now();
+            if key
+                .last_used_at
+                .is_none_or(|used| used < now - Duration::minutes(1))
+            {
+                key.last_used_at = Some(now);
+            }
+        }
+        let mut usage = self.usage.write().unwrap_or_else(|e| e.into_inner());
+        *usage.entry((key_id, day, route.to_string())).or_default() += 1;
+        Ok(usage
+            .iter()
+            .filter(|((id, used_on, _), _)| *id == key_id && *used_on == day)
+            .map(|(_, requests)| requests)
+            .sum())
+    }
+
+    async fn usage(
+        &self,
+        since: NaiveDate,
+        key_id: Option<i64>,
+    ) -> Result<Vec<ApiKeyUsage>, AppError> {
+        let keys = self.keys.read().unwrap_or_else(|e| e.into_inner());
+        let usage = self.usage.read().unwrap_or_else(|e| e.into_inner());
+        let mut rows: Vec<ApiKeyUsage> = usage
+            .iter()
+            .filter(|((id, day, _), _)| *day >= since && key_id.is_none_or(|k| k == *id))
+            .map(|((id, day, route), requests)| ApiKeyUsage {
+                key_id: *id,
+                name: keys
+                    .iter()
+                    .find(|(key, _)| key.id == *id)
+                    .map(|(key, _)| key.name.clone())
+                    .unwrap_or_default(),
+                day: *day,
+                route: route.clone(),
+                requests: *requests,
+            })
+            .collect();
+        rows.sort_by(|a, b| {
+            b.day
+                .cmp(&a.day)
+                .then(b.requests.cmp(&a.requests))
+                .then(a.key_id.cmp(&b.key_id))
+                .then(a.route.cmp(&b.route))
+        });
+        Ok(rows)
+    }
+}
@@ -0,0 +1,855 @@
+use crate::{
+    error::AppError,
+    model::{
+        data_version::DataVersion,
+        earning_history::{EarningHistory, QueryParams},
+        earning_history_pool::EarningHistoryPool,
+        gap::IntervalBreaks,
+        sync_run::SyncRange,
+    },
+    repository::{
+        memory::{
+            self, compare, first_per_bucket, in_date_range, paginate, sort_rows, SortColumns,
+            SortKey,
+        },
+        postgres,
+    },
+    utils::interval::interval_trunc,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgPool, Postgres, QueryBuilder, Row};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::RwLock,
+};
+use tracing::{debug, instrument};
+
+#[async_trait]
+pub trait EarningHistoryRepository: Send + Sync {
+    /// Stores the rows with their pools and returns their ids in the same order.
+    async fn save_batch(&self, earning_histories: &[EarningHistory]) -> Result<Vec<i32>, AppError>;
+
+    /// Replaces the rows starting at the same times as `earning_histories` with them, all or
+    /// nothing, and returns the new ids in the same order.
+    async fn replace_intervals(
+        &self,
+        earning_histories: &[EarningHistory],
+    ) -> Result<Vec<i32>, AppError>;
+
+    /// Deletes the rows starting at any of `start_times`.
+    async fn delete_intervals(&self, start_times: &[DateTime<Utc>]) -> Result<(), AppError>;
+
+    /// Rows matching the params, each with its pools matching the `pool_` params.
+    async fn query(&self, params: &QueryParams) -> Result<Vec<EarningHistory>, AppError>;
+
+    /// Unix `start_time` of the newest row, `None` while empty.
+    async fn last_timestamp(&self) -> Result<Option<i64>, AppError>;
+
+    /// Newest `end_time`, `None` while empty.
+    async fn last_end_time(&self) -> Result<Option<chrono::DateTime<chrono::Utc>>, AppError>;
+
+    /// Version of the stored rows for the response validators, `None` while empty.
+    async fn data_version(&self) -> Result<Option<DataVersion>, AppError>;
+
+    /// Rows starting inside the range with their pools, oldest first.
+    async fn find_in_range(&self, range: &SyncRange) -> Result<Vec<EarningHistory>, AppError>;
+
+    /// Unix `start_time`s of the rows starting inside the range.
+    async fn start_times(&self, range: &SyncRange) -> Result<HashSet<i64>, AppError>;
+
+    /// Intervals within the date range that don't start where the previous one ended.
+    async fn interval_breaks(
+        &self,
+        date_range: &Option<String>,
+    ) -> Result<IntervalBreaks, AppError>;
+}
+
+pub struct PgEarningHistoryRepository {
+    pool: PgPool,
+}
+
+impl PgEarningHistoryRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Writes the rows on `conn` and returns their ids in the same order.
+    async fn insert_batch(
+        conn: &mut PgConnection,
+        earning_histories: &[EarningHistory],
+    ) -> Result<Vec<i32>, AppError> {
+        // First insert main records
+        let copy = String::from(
+            "COPY earnings_history (start_time, end_time, liquidity_fees, block_rewards, \
+         earnings, bonding_earnings, liquidity_earnings, avg_node_count, rune_price_usd) \
+         FROM STDIN WITH (FORMAT text, DELIMITER '\t')",
+        );
+
+        let mut writer = conn.copy_in_raw(&copy).await?;
+
+        // Process main records in chunks of 5000
+        for chunk in earning_histories.chunks(5000) {
+            let mut batch_data = String::with_capacity(chunk.len() * 256);
+
+            for earning in chunk {
+                batch_data.push_str(&format!(
+                    "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+                    earning.start_time.format("%Y-%m-%d %H:%M:%S UTC"),
+                    earning.end_time.format("%Y-%m-%d %H:%M:%S UTC"),
+                    earning.liquidity_fees,
+                    earning.block_rewards,
+                    earning.earnings,
+                    earning.bonding_earnings,
+                    earning.liquidity_earnings,
+                    earning.avg_node_count,
+                    earning.rune_price_usd
+                ));
+            }
+
+            writer.send(batch_data.as_bytes()).await?;
+        }
+
+        writer.finish().await?;
+
+        // Get inserted IDs, oldest first so they line up with `earning_histories`
+        let ids = sqlx::query_as::<_, (i32,)>(
+            "SELECT id FROM earnings_history ORDER BY id DESC LIMIT $1",
+        )
+        .bind(earning_histories.len() as i32)
+        .fetch_all(&mut *conn)
+        .await?
+        .into_iter()
+        .rev()
+        .map(|(id,)| id)
+        .collect::<Vec<i32>>();
+
+        // Now handle pools
+        let mut all_pools = Vec::new();
+        for (idx, earning) in earning_histories.iter().enumerate() {
+            if let Some(pools) = &earning.pools {
+                for pool in pools {
+                    all_pools.push((ids[idx], pool));
+                }
+            }
+        }
+
+        if !all_pools.is_empty() {
+            let copy_pools = String::from(
+                "COPY pool_earnings (earnings_history_id, pool, asset_liquidity_fees, \
+             rune_liquidity_fees, total_liquidity_fees_rune, saver_earning, rewards, \
+             earnings) FROM STDIN WITH (FORMAT text, DELIMITER '\t')",
+            );
+
+            let mut pool_writer = conn.copy_in_raw(&copy_pools).await?;
+
+            // Process pools in chunks
+            for chunk in all_pools.chunks(5000) {
+                let mut pool_data = String::with_capacity(chunk.len() * 256);
+
+                for (earning_id, pool) in chunk {
+                    pool_data.push_str(&format!(
+                        "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+                        earning_id,
+                        pool.pool,
+                        pool.asset_liquidity_fees,
+                        pool.rune_liquidity_fees,
+                        pool.total_liquidity_fees_rune,
+                        pool.saver_earning,
+                        pool.rewards,
+                        pool.earnings
+                    ));
+                }
+
+                pool_writer.send(pool_data.as_bytes()).await?;
+            }
+
+            pool_writer.finish().await?;
+        }
+
+        debug!(
+            rows = earning_histories.len(),
+            pools = all_pools.len(),
+            "Saved earning history"
+        );
+        Ok(ids)
+    }
+
+    async fn get_pools(
+        &self,
+        earning_history_id: i32,
+        params: &QueryParams,
+    ) -> Result<Vec<EarningHistoryPool>, AppError> {
+        let mut qb = QueryBuilder::<Postgres>::new(
+            "SELECT * FROM pool_earnings WHERE earnings_history_id = ",
+        );
+        qb.push(earning_history_id);
+
+        // Pool filter
+        if let Some(pool) = &params.pool_eq {
+            qb.push(" AND pool = ").push_bind(pool);
+        }
+
+        // Asset liquidity fees filters
+        if let Some(lt) = params.pool_asset_liquidity_fees_lt {
+            qb.push(" AND asset_liquidity_fees < ").push_bind(lt);
+        }
+        if let Some(eq) = params.pool_asset_liquidity_fees_eq {
+            qb.push(" AND asset_liquidity_fees = ").push_bind(eq);
+        }
+        if let Some(gt) = params.pool_asset_liquidity_fees_gt {
+            qb.push(" AND asset_liquidity_fees > ").push_bind(gt);
+        }
+
+        // Rune liquidity fees filters
+        if let Some(lt) = params.pool_rune_liquidity_fees_lt {
+            qb.push(" AND rune_liquidity_fees < ").push_bind(lt);
+        }
+        if let Some(eq) = params.pool_rune_liquidity_fees_eq {
+            qb.push(" AND rune_liquidity_fees = ").push_bind(eq);
+        }
+        if let Some(gt) = params.pool_rune_liquidity_fees_gt {
+            qb.push(" AND rune_liquidity_fees > ").push_bind(gt);
+        }
+
+        // Total liquidity fees filters
+        if let Some(lt) = params.pool_total_liquidity_fees_lt {
+            qb.push(" AND total_liquidity_fees_rune < ").push_bind(lt);
+        }
+        if let Some(eq) = params.pool_total_liquidity_fees_eq {
+            qb.push(" AND total_liquidity_fees_rune = ").push_bind(eq);
+        }
+        if let Some(gt) = params.pool_total_liquidity_fees_gt {
+            qb.push(" AND total_liquidity_fees_rune > ").push_bind(gt);
+        }
+
+        // Saver earning filters
+        if let Some(lt) = params.pool_saver_earning_lt {
+            qb.push(" AND saver_earning < ").push_bind(lt);
+        }
+        if let Some(eq) = params.pool_saver_earning_eq {
+            qb.push(" AND saver_earning = ").push_bind(eq);
+        }
+        if let Some(gt) = params.pool_saver_earning_gt {
+            qb.push(" AND saver_earning > ").push_bind(gt);
+        }
+
+        // Pool rewards filters
+        if let Some(lt) = params.pool_rewards_lt {
+            qb.push(" AND rewards < ").push_bind(lt);
+        }
+        if let Some(eq) = params.pool_rewards_eq {
+            qb.push(" AND rewards = ").push_bind(eq);
+        }
+        if let Some(gt) = params.pool_rewards_gt {
+            qb.push(" AND rewards > ").push_bind(gt);
+        }
+
+        // Pool earnings filters
+        if let Some(lt) = params.pool_earnings_lt {
+            qb.push(" AND earnings < ").push_bind(lt);
+        }
+        if let Some(eq) = params.pool_earnings_eq {
+            qb.push(" AND earnings = ").push_bind(eq);
+        }
+        if let Some(gt) = params.pool_earnings_gt {
+            qb.push(" AND earnings > ").push_bind(gt);
+        }
+
+        let query = qb.build();
+        let result = query
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to fetch pools"))?;
+
+        Ok(result
+            .into_iter()
+            .map(|record| EarningHistoryPool {
+                id: Some(record.get("id")),
+                earnings_history_id: Some(record.get("earnings_history_id")),
+                pool: record.get("pool"),
+                asset_liquidity_fees: record.get("asset_liquidity_fees"),
+                rune_liquidity_fees: record.get("rune_liquidity_fees"),
+                total_liquidity_fees_rune: record.get("total_liquidity_fees_rune"),
+                saver_earning: record.get("saver_earning"),
+                rewards: record.get("rewards"),
+                earnings: record.get("earnings"),
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl EarningHistoryRepository for PgEarningHistoryRepository {
+    #[instrument(skip_all, fields(rows = earning_histories.len()))]
+    async fn save_batch(&self, earning_histories: &[EarningHistory]) -> Result<Vec<i32>, AppError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to start transaction"))?;
+        let ids = Self::insert_batch(&mut tx, earning_histories).await?;
+        tx.commit()
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to commit transaction"))?;
+        Ok(ids)
+    }
+
+    #[instrument(skip_all, fields(rows = earning_histories.len()))]
+    async fn replace_intervals(
+        &self,
+        earning_histories: &[EarningHistory],
+    ) -> Result<Vec<i32>, AppError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to start transaction"))?;
+        let start_times: Vec<DateTime<Utc>> =
+            earning_histories.iter().map(|r| r.start_time).collect();
+        postgres::delete_intervals(&mut tx, "earnings_history", &start_times).await?;
+        let ids = Self::insert_batch(&mut tx, earning_histories).await?;
+        tx.commit()
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to commit transaction"))?;
+        Ok(ids)
+    }
+
+    #[instrument(skip_all, fields(rows = start_times.len()))]
+    async fn delete_intervals(&self, start_times: &[DateTime<Utc>]) -> Result<(), AppError> {
+        let mut conn = self
+            .pool
+            .acquire()
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to acquire a connection"))?;
+        postgres::delete_intervals(&mut conn, "earnings_history", start_times).await
+    }
+
+    #[instrument(skip_all)]
+    async fn query(&self, params: &QueryParams) -> Result<Vec<EarningHistory>, AppError> {
+        let mut qb = QueryBuilder::<Postgres>::new("SELECT * FROM earnings_history WHERE true");
+        if let Some(interval) = &params.interval {
+            let bucket = interval_trunc(interval);
+
+            qb.push(" AND start_time in (");
+            qb.push("SELECT DISTINCT ON (")
+                .push(bucket)
+                .push(") start_time FROM earnings_history");
+            qb.push(" WHERE true ");
+            // Keeps the first row of every bucket
+            qb.push(" ORDER BY ").push(bucket).push(", start_time");
+            qb.push(")");
+        }
+
+        if let Some(date_range) = &params.date_range {
+            let dates: Vec<&str> = date_range.split(',').collect();
+            qb.push(" AND start_time >= ")
+                .push("TO_TIMESTAMP(")
+                .push_bind(dates[0])
+                .push(", 'YYYY-MM-DD')")
+                .push(" AND end_time <= ")
+                .push("TO_TIMESTAMP(")
+                .push_bind(dates[1])
+                .push(", 'YYYY-MM-DD')");
+        }
+
+        // Remaining query params filters
+        // Liquidity fees
+        if let Some(lt) = params.liquidity_fees_lt {
+            qb.push(" AND liquidity_fees < ").push_bind(lt);
+        }
+        if let Some(eq) = params.liquidity_fees_eq {
+            qb.push(" AND liquidity_fees = ").push_bind(eq);
+        }
+        if let Some(gt) = params.liquidity_fees_gt {
+            qb.push(" AND liquidity_fees > ").push_bind(gt);
+        }
+
+        // Block rewards
+        if let Some(lt) = params.block_rewards_lt {
+            qb.push(" AND block_rewards < ").push_bind(lt);
+        }
+        if let Some(eq) = params.block_rewards_eq {
+            qb.push(" AND block_rewards = ").push_bind(eq);
+        }
+        if let Some(gt) = params.block_rewards_gt {
+            qb.push(" AND block_rewards > ").push_bind(gt);
+        }
+
+        // Earnings
+        if let Some(lt) = params.earnings_lt {
+            qb.push(" AND earnings < ").push_bind(lt);
+        }
+        if let Some(eq) = params.earnings_eq {
+            qb.push(" AND earnings = ").push_bind(eq);
+        }
+        if let Some(gt) = params.earnings_gt {
+            qb.push(" AND earnings > ").push_bind(gt);
+        }
+
+        // Bonding earnings
+        if let Some(lt) = params.bonding_earnings_lt {
+            qb.push(" AND bonding_earnings < ").push_bind(lt);
+        }
+        if let Some(eq) = params.bonding_earnings_eq {
+            qb.push(" AND bonding_earnings = ").push_bind(eq);
+        }
+        if let Some(gt) = params.bonding_earnings_gt {
+            qb.push(" AND bonding_earnings > ").push_bind(gt);
+        }
+
+        // Liquidity earnings
+        if let Some(lt) = params.liquidity_earnings_lt {
+            qb.push(" AND liquidity_earnings < ").push_bind(lt);
+        }
+        if let Some(eq) = params.liquidity_earnings_eq {
+            qb.push(" AND liquidity_earnings = ").push_bind(eq);
+        }
+        if let Some(gt) = params.liquidity_earnings_gt {
+            qb.push(" AND liquidity_earnings > ").push_bind(gt);
+        }
+
+        // Avg node count
+        if let Some(lt) = params.avg_node_count_lt {
+            qb.push(" AND avg_node_count < ").push_bind(lt);
+        }
+        if let Some(eq) = params.avg_node_count_eq {
+            qb.push(" AND avg_node_count = ").push_bind(eq);
+        }
+        if let Some(gt) = params.avg_node_count_gt {
+            qb.push(" AND avg_node_count > ").push_bind(gt);
+        }
+
+        // Rune price USD
+        if let Some(lt) = params.rune_price_usd_lt {
+            qb.push(" AND rune_price_usd < ").push_bind(lt);
+        }
+        if let Some(eq) = params.rune_price_usd_eq {
+            qb.push(" AND rune_price_usd = ").push_bind(eq);
+        }
+        if let Some(gt) = params.rune_price_usd_gt {
+            qb.push(" AND rune_price_usd > ").push_bind(gt);
+        }
+
+        // Sort order
+        if let Some(sort_by) = &params.sort_by {
+            qb.push(" ORDER BY ").push(sort_by); // Directly appending column name
+        }
+
+        if let Some(order) = &params.order {
+            qb.push(" ").push(order.to_lowercase()); // Directly appending order (ASC/DESC)
+        }
+
+        //Count
+        if let Some(count) = params.count {
+            qb.push(" LIMIT ").push_bind(count as i64);
+        }
+
+        // Pagination
+        if let Some(limit) = params.limit {
+            qb.push(" LIMIT ").push_bind(limit as i64);
+        }
+        if let Some(page) = params.page {
+            let offset = page as i64 * params.limit.unwrap_or(10) as i64;
+            qb.push(" OFFSET ").push_bind(offset);
+        }
+
+        let query = qb.build();
+        let result = query
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to fetch earnings history"))?;
+
+        let mut earnings = result
+            .into_iter()
+            .map(|record| EarningHistory {
+                id: Some(record.get("id")),
+                start_time: record.get("start_time"),
+                end_time: record.get("end_time"),
+                liquidity_fees: record.get("liquidity_fees"),
+                block_rewards: record.get("block_rewards"),
+                earnings: record.get("earnings"),
+                bonding_earnings: record.get("bonding_earnings"),
+                liquidity_earnings: record.get("liquidity_earnings"),
+                avg_node_count: record.get("avg_node_count"),
+                rune_price_usd: record.get("rune_price_usd"),
+                pools: None,
+            })
+            .collect::<Vec<EarningHistory>>();
+
+        for earning in &mut earnings {
+            let pools = self.get_pools(earning.id.unwrap(), params).await?;
+            earning.pools = Some(pools);
+        }
+
+        Ok(earnings)
+    }
+
+    async fn last_timestamp(&self) -> Result<Option<i64>, AppError> {
+        let record =
+            sqlx::query("SELECT start_time FROM earnings_history ORDER BY start_time DESC LIMIT 1")
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(|e| AppError::from(e).context("Failed to get last timestamp"))?;
+
+        Ok(record.map(|record| {
+            record
+                .get::<chrono::DateTime<chrono::Utc>, _>("start_time")
+                .timestamp()
+        }))
+    }
+
+    async fn last_end_time(&self) -> Result<Option<chrono::DateTime<chrono::Utc>>, AppError> {
+        sqlx::query_scalar("SELECT MAX(end_time) FROM earnings_history")
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to get last end time"))
+    }
+
+    async fn data_version(&self) -> Result<Option<DataVersion>, AppError> {
+        postgres::data_version(&self.pool, "earnings_history").await
+    }
+
+    async fn find_in_range(&self, range: &SyncRange) -> Result<Vec<EarningHistory>, AppError> {
+        let mut earnings = sqlx::query(
+            "SELECT * FROM earnings_history \
+             WHERE start_time >= TO_TIMESTAMP($1) AND start_time < TO_TIMESTAMP($2) \
+             ORDER BY start_time, id",
+        )
+        .bind(range.from as f64)
+        .bind(range.to as f64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AppError::from(e).context("Failed to fetch stored earnings"))?
+        .into_iter()
+        .map(|record| EarningHistory {
+            id: Some(record.get("id")),
+            start_time: record.get("start_time"),
+            end_time: record.get("end_time"),
+            liquidity_fees: record.get("liquidity_fees"),
+            block_rewards: record.get("block_rewards"),
+            earnings: record.get("earnings"),
+            bonding_earnings: record.get("bonding_earnings"),
+            liquidity_earnings: record.get("liquidity_earnings"),
+            avg_node_count: record.get("avg_node_count"),
+            rune_price_usd: record.get("rune_price_usd"),
+            pools: Some(Vec::new()),
+        })
+        .collect::<Vec<EarningHistory>>();
+
+        let ids: Vec<i32> = earnings.iter().filter_map(|e| e.id).collect();
+        let pools = sqlx::query("SELECT * FROM pool_earnings WHERE earnings_history_id = ANY($1)")
+            .bind(&ids)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to fetch stored pool earnings"))?;
+
+        let mut pools_by_earning: HashMap<i32, Vec<EarningHistoryPool>> = HashMap::new();
+        for record in pools {
+            let earnings_history_id: i32 = record.get("earnings_history_id");
+            pools_by_earning
+                .entry(earnings_history_id)
+                .or_default()
+                .push(EarningHistoryPool {
+                    id: Some(record.get("id")),
+                    earnings_history_id: Some(earnings_history_id),
+                    pool: record.get("pool"),
+                    asset_liquidity_fees: record.get("asset_liquidity_fees"),
+                    rune_liquidity_fees: record.get("rune_liquidity_fees"),
+                    total_liquidity_fees_rune: record.get("total_liquidity_fees_rune"),
+                    saver_earning: record.get("saver_earning"),
+                    rewards: record.get("rewards"),
+                    earnings: record.get("earnings"),
+                });
+        }
+        for earning in &mut earnings {
+            if let Some(pools) = earning.id.and_then(|id| pools_by_earning.remove(&id)) {
+                earning.pools = Some(pools);
+            }
+        }
+
+        Ok(earnings)
+    }
+
+    async fn start_times(&self, range: &SyncRange) -> Result<HashSet<i64>, AppError> {
+        postgres::start_times(&self.pool, "earnings_history", range).await
+    }
+
+    async fn interval_breaks(
+        &self,
+        date_range: &Option<String>,
+    ) -> Result<IntervalBreaks, AppError> {
+        postgres::interval_breaks(&self.pool, "earnings_history", date_range).await
+    }
+}
+
+impl SortColumns for EarningHistory {
+    fn sort_key(&self, column: &str) -> Option<SortKey> {
+        Some(match column {
+            "id" => self.id.into(),
+            "start_time" => self.start_time.into(),
+            "end_time" => self.end_time.into(),
+            "liquidity_fees" => self.liquidity_fees.into(),
+            "block_rewards" => self.block_rewards.into(),
+            "earnings" => self.earnings.into(),
+            "bonding_earnings" => self.bonding_earnings.into(),
+            "liquidity_earnings" => self.liquidity_earnings.into(),
+            "avg_node_count" => self.avg_node_count.into(),
+            "rune_price_usd" => self.rune_price_usd.into(),
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryEarningHistoryRepository {
+    rows: RwLock<Vec<EarningHistory>>,
+    last_id: RwLock<i32>,
+    pool_count: RwLock<i32>,
+}
+
+impl InMemoryEarningHistoryRepository {
+    /// Appends the rows and their pools with ids from a sequence, so deleted ids aren't
+    /// handed out again.
+    fn insert(
+        &self,
+        rows: &mut Vec<EarningHistory>,
+        earning_histories: &[EarningHistory],
+    ) -> Vec<i32> {
+        let mut last_id = self.last_id.write().unwrap_or_else(|e| e.into_inner());
+        let mut pool_count = self.pool_count.write().unwrap_or_else(|e| e.into_inner());
+        let mut ids = Vec::with_capacity(earning_histories.len());
+        for earning in earning_histories {
+            *last_id += 1;
+            let id = *last_id;
+            let pools = earning.pools.as_ref().map(|pools| {
+                pools
+                    .iter()
+                    .map(|pool| {
+                        *pool_count += 1;
+                        EarningHistoryPool {
+                            id: Some(*pool_count),
+                            earnings_history_id: Some(id),
+                            ..pool.clone()
+                        }
+                    })
+                    .collect()
+            });
+            rows.push(EarningHistory {
+                id: Some(id),
+                pools,
+                ..earning.clone()
+            });
+            ids.push(id);
+        }
+        ids
+    }
+}
+
+fn pool_matches(pool: &EarningHistoryPool, params: &QueryParams) -> bool {
+    let widen = |value: Option<i32>| value.map(i64::from);
+    params.pool_eq.as_ref().is_none_or(|eq| &pool.pool == eq)
+        && compare(
+            pool.asset_liquidity_fees,
+            widen(params.pool_asset_liquidity_fees_gt),
+            widen(params.pool_asset_liquidity_fees_lt),
+            widen(params.pool_asset_liquidity_fees_eq),
+        )
+        && compare(
+            pool.rune_liquidity_fees,
+            widen(params.pool_rune_liquidity_fees_gt),
+            widen(params.pool_rune_liquidity_fees_lt),
+            widen(params.pool_rune_liquidity_fees_eq),
+        )
+        && compare(
+            pool.total_liquidity_fees_rune,
+            widen(params.pool_total_liquidity_fees_gt),
+            widen(params.pool_total_liquidity_fees_lt),
+            widen(params.pool_total_liquidity_fees_eq),
+        )
+        && compare(
+            pool.saver_earning,
+            widen(params.pool_saver_earning_gt),
+            widen(params.pool_saver_earning_lt),
+            widen(params.pool_saver_earning_eq),
+        )
+        && compare(
+            pool.rewards,
+            widen(params.pool_rewards_gt),
+            widen(params.pool_rewards_lt),
+            widen(params.pool_rewards_eq),
+        )
+        && compare(
+            pool.earnings,
+            widen(params.pool_earnings_gt),
+            widen(params.pool_earnings_lt),
+            widen(params.pool_earnings_eq),
+        )
+}
+
+#[async_trait]
+impl EarningHistoryRepository for InMemoryEarningHistoryRepository {
+    async fn save_batch(&self, earning_histories: &[EarningHistory]) -> Result<Vec<i32>, AppError> {
+        let mut rows = self.rows.write().unwrap_or_else(|e| e.into_inner());
+        Ok(self.insert(&mut rows, earning_histories))
+    }
+
+    async fn replace_intervals(
+        &self,
+        earning_histories: &[EarningHistory],
+    ) -> Result<Vec<i32>, AppError> {
+        // One write lock for both, so readers never see the intervals half replaced
+        let mut rows = self.rows.write().unwrap_or_else(|e| e.into_inner());
+        rows.retain(|row| {
+            !earning_histories
+                .iter()
+                .any(|r| r.start_time == row.start_time)
+        });
+        Ok(self.insert(&mut rows, earning_histories))
+    }
+
+    async fn delete_intervals(&self, start_times: &[DateTime<Utc>]) -> Result<(), AppError> {
+        let mut rows = self.rows.write().unwrap_or_else(|e| e.into_inner());
+        rows.retain(|row| !start_times.contains(&row.start_time));
+        Ok(())
+    }
+
+    async fn query(&self, params: &QueryParams) -> Result<Vec<EarningHistory>, AppError> {
+        let rows = self.rows.read().unwrap_or_else(|e| e.into_inner());
+        let buckets = params
+            .interval
+            .as_deref()
+            .map(|interval| first_per_bucket(&rows, interval, |r| r.start_time));
+
+        let mut result = Vec::new();
+        for row in rows.iter() {
+            if buckets
+                .as_ref()
+                .is_some_and(|b| !b.contains(&row.start_time))
+                || !in_date_range(&params.date_range, row.start_time, row.end_time)?
+                || !compare(
+                    row.liquidity_fees,
+                    params.liquidity_fees_gt,
+                    params.liquidity_fees_lt,
+                    params.liquidity_fees_eq,
+                )
+                || !compare(
+                    row.block_rewards,
+                    params.block_rewards_gt,
+                    params.block_rewards_lt,
+                    params.block_rewards_eq,
+                )
+                || !compare(
+                    row.earnings,
+                    params.earnings_gt,
+                    params.earnings_lt,
+                    params.earnings_eq,
+                )
+                || !compare(
+                    row.bonding_earnings,
+                    params.bonding_earnings_gt,
+                    params.bonding_earnings_lt,
+                    params.bonding_earnings_eq,
+                )
+                || !compare(
+                    row.liquidity_earnings,
+                    params.liquidity_earnings_gt,
+                    params.liquidity_earnings_lt,
+                    params.liquidity_earnings_eq,
+                )
+                || !compare(
+                    row.avg_node_count,
+                    params.avg_node_count_gt,
+                    params.avg_node_count_lt,
+                    params.avg_node_count_eq,
+                )
+                || !compare(
+                    row.rune_price_usd,
+                    params.rune_price_usd_gt,
+                    params.rune_price_usd_lt,
+                    params.rune_price_usd_eq,
+                )
+            {
+                continue;
+            }
+
+            // Pools are filtered per row, a row is kept even when none of its pools match
+            let pools = row
+                .pools
+                .iter()
+                .flatten()
+                .filter(|pool| pool_matches(pool, params))
+                .cloned()
+                .collect();
+            result.push(EarningHistory {
+                pools: Some(pools),
+                ..row.clone()
+            });
+        }
+
+        if let Some(sort_by) = &params.sort_by {
+            sort_rows(&mut result, sort_by, params.order.as_deref())?;
+        }
+
+        // Unlike the other history queries, `page` is 0-based here
+        let limit = params.count.map(i64::from).or(params.limit.map(i64::from));
+        let offset = params
+            .page
+            .map_or(0, |page| page as i64 * params.limit.unwrap_or(10) as i64);
+        Ok(paginate(result, limit, offset))
+    }
+
+    async fn last_timestamp(&self) -> Result<Option<i64>, AppError> {
+        let rows = self.rows.read().unwrap_or_else(|e| e.into_inner());
+        Ok(rows.iter().map(|r| r.start_time.timestamp()).max())
+    }
+
+    async fn last_end_time(&self) -> Result<Option<chrono::DateTime<chrono::Utc>>, AppError> {
+        let rows = self.rows.read().unwrap_or_else(|e| e.into_inner());
+        Ok(rows.iter().map(|r| r.end_time).max())
+    }
+
+    async fn data_version(&self) -> Result<Option<DataVersion>, AppError> {
+        let rows = self.rows.read().unwrap_or_else(|e| e.into_inner());
+        Ok(memory::data_version(
+            rows.iter().map(|r| (r.end_time, r.id)),
+        ))
+    }
+
+    async fn find_in_range(&self, range: &SyncRange) -> Result<Vec<EarningHistory>, AppError> {
+        let rows = self.rows.read().unwrap_or_else(|e| e.into_inner());
+        let mut result: Vec<EarningHistory> = rows
+            .iter()
+            .filter(|r| range.contains(r.start_time))
+            .map(|r| EarningHistory {
+                pools: Some(r.pools.clone().unwrap_or_default()),
+                ..r.clone()
+            })
+            .collect();
+        result.sort_by_key(|r| (r.start_time, r.id));
+        Ok(result)
+    }
+
+    async fn start_times(&self, range: &SyncRange) -> Result<HashSet<i64>, AppError> {
+        let rows = self.rows.read().unwrap_or_else(|e| e.into_inner());
+        Ok(rows
+            .iter()
+            .filter(|r| range.contains(r.start_time))
+            .map(|r| r.start_time.timestamp())
+            .collect())
+    }
+
+    async fn interval_breaks(
+        &self,
+        date_range: &Option<String>,
+    ) -> Result<IntervalBreaks, AppError> {
+        let rows = self.rows.read().unwrap_or_else(|e| e.into_inner());
+        let mut bounds = Vec::with_capacity(rows.len());
+        for row in rows.iter() {
+            if in_date_range(date_range, row.start_time, row.end_time)? {
+                bounds.push((row.start_time, row.end_time));
+            }
+        }
+        Ok(memory::interval_breaks(bounds))
+    }
+}
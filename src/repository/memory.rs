@@ -0,0 +1,222 @@
+//! Helpers shared by the in-memory repositories, mirroring what the SQL of the
+//! Postgres repositories does so both return the same rows for the same params.
+
+use crate::{
+    error::AppError,
+    model::{
+        data_version::DataVersion,
+        gap::{IntervalBreak, IntervalBreaks},
+    },
+};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+use rust_decimal::Decimal;
+use std::{cmp::Ordering, collections::HashSet};
+
+/// Start of the `interval` bucket `time` falls in, like `date_trunc`. Unknown
+/// intervals fall back to hourly buckets.
+pub fn bucket_start(time: DateTime<Utc>, interval: &str) -> DateTime<Utc> {
+    let date = time.date_naive();
+    let midnight = |date: NaiveDate| Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).unwrap());
+    let first_of_month =
+        |year: i32, month: u32| midnight(NaiveDate::from_ymd_opt(year, month, 1).unwrap());
+
+    match interval {
+        "5min" => Utc
+            .timestamp_opt(time.timestamp() - time.timestamp() % 300, 0)
+            .unwrap(),
+        "day" => midnight(date),
+        "week" => midnight(date) - Duration::days(date.weekday().num_days_from_monday() as i64),
+        "month" => first_of_month(date.year(), date.month()),
+        "quarter" => first_of_month(date.year(), (date.month0() / 3) * 3 + 1),
+        "year" => first_of_month(date.year(), 1),
+        _ => Utc
+            .timestamp_opt(time.timestamp() - time.timestamp() % 3600, 0)
+            .unwrap(),
+    }
+}
+
+/// Start times of the first row of every `interval` bucket, the rows the
+/// `DISTINCT ON` interval filter keeps.
+pub fn first_per_bucket<T>(
+    rows: &[T],
+    interval: &str,
+    start_time: impl Fn(&T) -> DateTime<Utc>,
+) -> HashSet<DateTime<Utc>> {
+    let mut starts: Vec<DateTime<Utc>> = rows.iter().map(start_time).collect();
+    starts.sort();
+
+    let mut seen = HashSet::new();
+    starts
+        .into_iter()
+        .filter(|start| seen.insert(bucket_start(*start, interval)))
+        .collect()
+}
+
+/// The `YYYY-MM-DD,YYYY-MM-DD` filter of the history endpoints: the row has to
+/// start on or after the first day and end by midnight of the second.
+pub fn in_date_range(
+    date_range: &Option<String>,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+) -> Result<bool, AppError> {
+    let Some(date_range) = date_range else {
+        return Ok(true);
+    };
+    let dates: Vec<&str> = date_range.split(',').map(str::trim).collect();
+    if dates.len() != 2 {
+        return Ok(true);
+    }
+
+    let parse = |date: &str| {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map(|date| Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).unwrap()))
+            .map_err(|e| {
+                AppError::invalid_field("date_range", format!("Invalid date {}: {}", date, e))
+            })
+    };
+    Ok(start_time >= parse(dates[0])? && end_time <= parse(dates[1])?)
+}
+
+/// The `_gt`/`_lt`/`_eq` filters of a single column.
+pub fn compare<T: PartialOrd>(value: T, gt: Option<T>, lt: Option<T>, eq: Option<T>) -> bool {
+    gt.is_none_or(|gt| value > gt)
+        && lt.is_none_or(|lt| value < lt)
+        && eq.is_none_or(|eq| value == eq)
+}
+
+/// The value of a row's column, ordered the way Postgres orders the column.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub enum SortKey {
+    Int(i64),
+    Decimal(Decimal),
+    Time(DateTime<Utc>),
+    /// Sorts after every value, like Postgres' default `NULLS LAST` (`NULLS FIRST` on `DESC`).
+    Null,
+}
+
+impl From<i64> for SortKey {
+    fn from(value: i64) -> Self {
+        SortKey::Int(value)
+    }
+}
+
+impl From<Option<i32>> for SortKey {
+    fn from(value: Option<i32>) -> Self {
+        value.map_or(SortKey::Null, |value| SortKey::Int(value.into()))
+    }
+}
+
+impl From<Decimal> for SortKey {
+    fn from(value: Decimal) -> Self {
+        SortKey::Decimal(value)
+    }
+}
+
+impl From<DateTime<Utc>> for SortKey {
+    fn from(value: DateTime<Utc>) -> Self {
+        SortKey::Time(value)
+    }
+}
+
+/// Rows `sort_rows` can order: maps every column of the model's `SORT_COLUMNS`
+/// to the field stored in it, `None` for anything else.
+pub trait SortColumns {
+    fn sort_key(&self, column: &str) -> Option<SortKey>;
+}
+
+/// `ORDER BY <sort_by> <order>`, keeping ties in insertion order.
+pub fn sort_rows<T: SortColumns>(
+    rows: &mut Vec<T>,
+    sort_by: &str,
+    order: Option<&str>,
+) -> Result<(), AppError> {
+    let descending = order.is_some_and(|order| order.eq_ignore_ascii_case("desc"));
+    let mut keyed = Vec::with_capacity(rows.len());
+    for row in rows.drain(..) {
+        let key = row.sort_key(sort_by).ok_or_else(|| {
+            AppError::invalid_field("sort_by", format!("column \"{}\" does not exist", sort_by))
+        })?;
+        keyed.push((key, row));
+    }
+
+    keyed.sort_by(|(a, _), (b, _)| {
+        let ordering = a.partial_cmp(b).unwrap_or(Ordering::Equal);
+        if descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+    rows.extend(keyed.into_iter().map(|(_, row)| row));
+    Ok(())
+}
+
+/// `LIMIT`/`OFFSET`, negative values count as 0.
+pub fn paginate<T>(rows: Vec<T>, limit: Option<i64>, offset: i64) -> Vec<T> {
+    let rows = rows.into_iter().skip(offset.max(0) as usize);
+    match limit {
+        Some(limit) => rows.take(limit.max(0) as usize).collect(),
+        None => rows.collect(),
+    }
+}
+
+/// `count`, else `limit` with a 1-based `page`, like the swap and runepool queries.
+pub fn paginate_pages<T>(
+    rows: Vec<T>,
+    count: Option<i64>,
+    limit: Option<i64>,
+    page: Option<i64>,
+) -> Vec<T> {
+    match (count, limit) {
+        (Some(count), _) => paginate(rows, Some(count), 0),
+        (None, Some(limit)) => {
+            let offset = page.map_or(0, |page| page.saturating_sub(1) * limit);
+            paginate(rows, Some(limit), offset)
+        }
+        (None, None) => rows,
+    }
+}
+
+/// Totals and breaks of the `(start_time, end_time)` of a table's rows, like the `LAG`
+/// window query of the Postgres repositories.
+pub fn interval_breaks(mut bounds: Vec<(DateTime<Utc>, DateTime<Utc>)>) -> IntervalBreaks {
+    bounds.sort();
+    let mut breaks = IntervalBreaks {
+        intervals: bounds.len() as i64,
+        first_start_time: bounds.first().map(|(start_time, _)| *start_time),
+        last_end_time: bounds.iter().map(|(_, end_time)| *end_time).max(),
+        breaks: Vec::new(),
+    };
+    for pair in bounds.windows(2) {
+        let ((previous_start_time, previous_end_time), (start_time, end_time)) = (pair[0], pair[1]);
+        if start_time != previous_end_time {
+            breaks.breaks.push(IntervalBreak {
+                start_time,
+                end_time,
+                previous_start_time,
+                previous_end_time,
+            });
+        }
+    }
+    breaks
+}
+
+/// Newest `end_time`, newest id and count of the `(end_time, id)` of a table's rows, like
+/// the aggregate query of the Postgres repositories.
+pub fn data_version(
+    rows: impl Iterator<Item = (DateTime<Utc>, Option<i32>)>,
+) -> Option<DataVersion> {
+    let mut version: Option<DataVersion> = None;
+    for (end_time, id) in rows {
+        let id = id.unwrap_or_default();
+        let version = version.get_or_insert(DataVersion {
+            last_end_time: end_time,
+            last_id: id,
+            rows: 0,
+        });
+        version.last_end_time = version.last_end_time.max(end_time);
+        version.last_id = version.last_id.max(id);
+        version.rows += 1;
+    }
+    version
+}
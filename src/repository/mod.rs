@@ -0,0 +1,98 @@
+pub mod api_key_repository;
+pub mod earning_history_repository;
+pub mod memory;
+pub mod postgres;
+pub mod price_history_repository;
+pub mod run_pool_repository;
+pub mod swap_history_repository;
+
+use crate::{
+    cronjobs::dataset::Dataset,
+    error::AppError,
+    model::{gap::IntervalBreaks, sync_run::SyncRange},
+};
+use api_key_repository::{ApiKeyRepository, InMemoryApiKeyRepository, PgApiKeyRepository};
+use chrono::{DateTime, Utc};
+use earning_history_repository::{
+    EarningHistoryRepository, InMemoryEarningHistoryRepository, PgEarningHistoryRepository,
+};
+use price_history_repository::{
+    InMemoryPriceHistoryRepository, PgPriceHistoryRepository, PriceHistoryRepository,
+};
+use run_pool_repository::{InMemoryRunePoolRepository, PgRunePoolRepository, RunePoolRepository};
+use sqlx::PgPool;
+use std::{collections::HashSet, sync::Arc};
+use swap_history_repository::{
+    InMemorySwapHistoryRepository, PgSwapHistoryRepository, SwapHistoryRepository,
+};
+
+/// Storage of the synced history tables and the API keys, Postgres in the app and in
+/// memory in tests.
+#[derive(Clone)]
+pub struct Repositories {
+    pub price_history: Arc<dyn PriceHistoryRepository>,
+    pub earning_history: Arc<dyn EarningHistoryRepository>,
+    pub swap_history: Arc<dyn SwapHistoryRepository>,
+    pub rune_pool: Arc<dyn RunePoolRepository>,
+    pub api_keys: Arc<dyn ApiKeyRepository>,
+}
+
+impl Repositories {
+    pub fn postgres(pool: PgPool) -> Self {
+        Self {
+            price_history: Arc::new(PgPriceHistoryRepository::new(pool.clone())),
+            earning_history: Arc::new(PgEarningHistoryRepository::new(pool.clone())),
+            swap_history: Arc::new(PgSwapHistoryRepository::new(pool.clone())),
+            rune_pool: Arc::new(PgRunePoolRepository::new(pool.clone())),
+            api_keys: Arc::new(PgApiKeyRepository::new(pool)),
+        }
+    }
+
+    pub fn in_memory() -> Self {
+        Self {
+            price_history: Arc::new(InMemoryPriceHistoryRepository::default()),
+            earning_history: Arc::new(InMemoryEarningHistoryRepository::default()),
+            swap_history: Arc::new(InMemorySwapHistoryRepository::default()),
+            rune_pool: Arc::new(InMemoryRunePoolRepository::default()),
+            api_keys: Arc::new(InMemoryApiKeyRepository::default()),
+        }
+    }
+
+    /// Unix `start_time`s of the dataset's rows that start inside the range.
+    pub async fn start_times(
+        &self,
+        dataset: Dataset,
+        range: &SyncRange,
+    ) -> Result<HashSet<i64>, AppError> {
+        match dataset {
+            Dataset::Depths => self.price_history.start_times(range).await,
+            Dataset::Earnings => self.earning_history.start_times(range).await,
+            Dataset::Swaps => self.swap_history.start_times(range).await,
+            Dataset::Runepool => self.rune_pool.start_times(range).await,
+        }
+    }
+
+    /// Intervals of the dataset that don't start where the previous one ended.
+    pub async fn interval_breaks(
+        &self,
+        dataset: Dataset,
+        date_range: &Option<String>,
+    ) -> Result<IntervalBreaks, AppError> {
+        match dataset {
+            Dataset::Depths => self.price_history.interval_breaks(date_range).await,
+            Dataset::Earnings => self.earning_history.interval_breaks(date_range).await,
+            Dataset::Swaps => self.swap_history.interval_breaks(date_range).await,
+            Dataset::Runepool => self.rune_pool.interval_breaks(date_range).await,
+        }
+    }
+
+    /// Newest `end_time` of the dataset, `None` while empty.
+    pub async fn last_end_time(&self, dataset: Dataset) -> Result<Option<DateTime<Utc>>, AppError> {
+        match dataset {
+            Dataset::Depths => self.price_history.last_end_time().await,
+            Dataset::Earnings => self.earning_history.last_end_time().await,
+            Dataset::Swaps => self.swap_history.last_end_time().await,
+            Dataset::Runepool => self.rune_pool.last_end_time().await,
+        }
+    }
+}
@@ -0,0 +1,122 @@
+//! Queries shared by the Postgres history repositories, which only differ in the
+//! table they read.
+
+use crate::{
+    error::AppError,
+    model::{
+        data_version::DataVersion,
+        gap::{IntervalBreak, IntervalBreaks},
+        sync_run::SyncRange,
+    },
+    utils::query::push_date_range,
+};
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgPool, Postgres, QueryBuilder, Row};
+use std::collections::HashSet;
+
+/// Unix `start_time`s of the table's rows that start inside the range.
+pub async fn start_times(
+    pool: &PgPool,
+    table: &str,
+    range: &SyncRange,
+) -> Result<HashSet<i64>, AppError> {
+    let rows = sqlx::query(&format!(
+        "SELECT EXTRACT(EPOCH FROM start_time)::BIGINT AS start_time FROM {} \
+         WHERE start_time >= TO_TIMESTAMP($1) AND start_time < TO_TIMESTAMP($2)",
+        table
+    ))
+    .bind(range.from as f64)
+    .bind(range.to as f64)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::from(e).context("Failed to fetch synced intervals"))?;
+
+    Ok(rows.iter().map(|row| row.get("start_time")).collect())
+}
+
+/// Walks the table's intervals in order and keeps the ones that don't start exactly
+/// where the previous one ended.
+pub async fn interval_breaks(
+    pool: &PgPool,
+    table: &str,
+    date_range: &Option<String>,
+) -> Result<IntervalBreaks, AppError> {
+    let mut qb =
+        QueryBuilder::<Postgres>::new("SELECT *, previous_end_time IS NULL AS is_first FROM (");
+    qb.push(format!(
+        "SELECT start_time, end_time, \
+         LAG(start_time) OVER (ORDER BY start_time, end_time) AS previous_start_time, \
+         LAG(end_time) OVER (ORDER BY start_time, end_time) AS previous_end_time, \
+         COUNT(*) OVER () AS intervals, \
+         MIN(start_time) OVER () AS first_start_time, \
+         MAX(end_time) OVER () AS last_end_time \
+         FROM {} WHERE true",
+        table
+    ));
+    push_date_range(&mut qb, date_range);
+    // Keep the first row around so the totals are there even without any break
+    qb.push(
+        ") t WHERE previous_end_time IS NULL OR start_time <> previous_end_time \
+         ORDER BY start_time",
+    );
+
+    let rows = qb
+        .build()
+        .fetch_all(pool)
+        .await
+        .map_err(|e| AppError::from(e).context(format!("Failed to scan {} for gaps", table)))?;
+
+    let mut breaks = IntervalBreaks::default();
+    for row in rows {
+        breaks.intervals = row.get("intervals");
+        breaks.first_start_time = row.get("first_start_time");
+        breaks.last_end_time = row.get("last_end_time");
+        if row.get::<bool, _>("is_first") {
+            continue;
+        }
+        breaks.breaks.push(IntervalBreak {
+            start_time: row.get("start_time"),
+            end_time: row.get("end_time"),
+            previous_start_time: row.get("previous_start_time"),
+            previous_end_time: row.get("previous_end_time"),
+        });
+    }
+    Ok(breaks)
+}
+
+/// Deletes the table's rows starting at any of `start_times`, on `conn` so it can be
+/// part of the caller's transaction.
+pub async fn delete_intervals(
+    conn: &mut PgConnection,
+    table: &str,
+    start_times: &[DateTime<Utc>],
+) -> Result<(), AppError> {
+    sqlx::query(&format!("DELETE FROM {} WHERE start_time = ANY($1)", table))
+        .bind(start_times)
+        .execute(conn)
+        .await
+        .map_err(|e| {
+            AppError::from(e).context(format!("Failed to delete intervals of {}", table))
+        })?;
+    Ok(())
+}
+
+/// Newest `end_time`, newest id and row count of the table, `None` while it is empty.
+pub async fn data_version(pool: &PgPool, table: &str) -> Result<Option<DataVersion>, AppError> {
+    let (last_end_time, last_id, rows): (Option<DateTime<Utc>>, Option<i32>, i64) = sqlx::query_as(
+        &format!("SELECT MAX(end_time), MAX(id), COUNT(*) FROM {}", table),
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(|e| {
+        AppError::from(e).context(format!("Failed to read the data version of {}", table))
+    })?;
+
+    Ok(last_end_time
+        .zip(last_id)
+        .map(|(last_end_time, last_id)| DataVersion {
+            last_end_time,
+            last_id,
+            rows,
+        }))
+}
@@ -0,0 +1,571 @@
+use crate::{
+    error::AppError,
+    model::{
+        data_version::DataVersion,
+        gap::IntervalBreaks,
+        price_history::{PriceHistory, PriceHistoryParams},
+        sync_run::SyncRange,
+    },
+    repository::{
+        memory::{
+            self, compare, first_per_bucket, in_date_range, paginate_pages, sort_rows, SortColumns,
+            SortKey,
+        },
+        postgres,
+    },
+    utils::{
+        interval::interval_trunc,
+        query::{add_comparison_filters, push_date_range},
+    },
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgPool, Postgres, QueryBuilder, Row};
+use std::{collections::HashSet, sync::RwLock};
+use tracing::{debug, instrument};
+
+#[async_trait]
+pub trait PriceHistoryRepository: Send + Sync {
+    /// Stores the rows and returns their ids in the same order.
+    async fn save_batch(&self, price_histories: &[PriceHistory]) -> Result<Vec<i32>, AppError>;
+
+    /// Replaces the rows starting at the same times as `price_histories` with them, all or
+    /// nothing, and returns the new ids in the same order.
+    async fn replace_intervals(
+        &self,
+        price_histories: &[PriceHistory],
+    ) -> Result<Vec<i32>, AppError>;
+
+    /// Deletes the rows starting at any of `start_times`.
+    async fn delete_intervals(&self, start_times: &[DateTime<Utc>]) -> Result<(), AppError>;
+
+    async fn query(&self, params: &PriceHistoryParams) -> Result<Vec<PriceHistory>, AppError>;
+
+    /// Unix `start_time` of the newest row, `None` while empty.
+    async fn last_timestamp(&self) -> Result<Option<i64>, AppError>;
+
+    /// Newest `end_time`, `None` while empty.
+    async fn last_end_time(&self) -> Result<Option<chrono::DateTime<chrono::Utc>>, AppError>;
+
+    /// Version of the stored rows for the response validators, `None` while empty.
+    async fn data_version(&self) -> Result<Option<DataVersion>, AppError>;
+
+    /// Rows starting inside the range, oldest first.
+    async fn find_in_range(&self, range: &SyncRange) -> Result<Vec<PriceHistory>, AppError>;
+
+    /// Unix `start_time`s of the rows starting inside the range.
+    async fn start_times(&self, range: &SyncRange) -> Result<HashSet<i64>, AppError>;
+
+    /// Intervals within the date range that don't start where the previous one ended.
+    async fn interval_breaks(
+        &self,
+        date_range: &Option<String>,
+    ) -> Result<IntervalBreaks, AppError>;
+}
+
+pub struct PgPriceHistoryRepository {
+    pool: PgPool,
+}
+
+impl PgPriceHistoryRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Writes the rows on `conn` and returns their ids in the same order.
+    async fn insert_batch(
+        conn: &mut PgConnection,
+        price_histories: &[PriceHistory],
+    ) -> Result<Vec<i32>, AppError> {
+        let copy = String::from(
+            "COPY depth_price_history (start_time, end_time, asset_depth, rune_depth, \
+         asset_price, asset_price_usd, liquidity_units, members_count, synth_units, \
+         synth_supply, units, luvi) FROM STDIN WITH (FORMAT text, DELIMITER '\t')",
+        );
+
+        let mut writer = conn
+            .copy_in_raw(&copy)
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to initialize batch write"))?;
+
+        // Process in chunks of 5000 records
+        for chunk in price_histories.chunks(5000) {
+            let mut batch_data = String::with_capacity(chunk.len() * 256);
+
+            for price_history in chunk {
+                batch_data.push_str(&format!(
+                    "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+                    price_history.start_time,
+                    price_history.end_time,
+                    price_history.asset_depth,
+                    price_history.rune_depth,
+                    price_history.asset_price,
+                    price_history.asset_price_usd,
+                    price_history.liquidity_units,
+                    price_history.members_count,
+                    price_history.synth_units,
+                    price_history.synth_supply,
+                    price_history.units,
+                    price_history.luvi,
+                ));
+            }
+
+            writer
+                .send(batch_data.as_bytes())
+                .await
+                .map_err(|e| AppError::from(e).context("Failed to write batch data"))?;
+        }
+
+        writer
+            .finish()
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to finish batch write"))?;
+
+        let ids = sqlx::query_as::<_, (i32,)>(
+            "SELECT id FROM depth_price_history ORDER BY id DESC LIMIT $1",
+        )
+        .bind(price_histories.len() as i32)
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| AppError::from(e).context("Failed to retrieve inserted IDs"))?;
+
+        let ids = ids.into_iter().rev().map(|(id,)| id).collect();
+        debug!(rows = price_histories.len(), "Saved price history");
+        Ok(ids)
+    }
+}
+
+#[async_trait]
+impl PriceHistoryRepository for PgPriceHistoryRepository {
+    #[instrument(skip_all, fields(rows = price_histories.len()))]
+    async fn save_batch(&self, price_histories: &[PriceHistory]) -> Result<Vec<i32>, AppError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to start transaction"))?;
+        let ids = Self::insert_batch(&mut tx, price_histories).await?;
+        tx.commit()
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to commit transaction"))?;
+        Ok(ids)
+    }
+
+    #[instrument(skip_all, fields(rows = price_histories.len()))]
+    async fn replace_intervals(
+        &self,
+        price_histories: &[PriceHistory],
+    ) -> Result<Vec<i32>, AppError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to start transaction"))?;
+        let start_times: Vec<DateTime<Utc>> =
+            price_histories.iter().map(|r| r.start_time).collect();
+        postgres::delete_intervals(&mut tx, "depth_price_history", &start_times).await?;
+        let ids = Self::insert_batch(&mut tx, price_histories).await?;
+        tx.commit()
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to commit transaction"))?;
+        Ok(ids)
+    }
+
+    #[instrument(skip_all, fields(rows = start_times.len()))]
+    async fn delete_intervals(&self, start_times: &[DateTime<Utc>]) -> Result<(), AppError> {
+        let mut conn = self
+            .pool
+            .acquire()
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to acquire a connection"))?;
+        postgres::delete_intervals(&mut conn, "depth_price_history", start_times).await
+    }
+
+    #[instrument(skip_all)]
+    async fn query(&self, params: &PriceHistoryParams) -> Result<Vec<PriceHistory>, AppError> {
+        let mut qb = QueryBuilder::<Postgres>::new("SELECT * FROM depth_price_history WHERE true");
+        // Interval filter
+        if let Some(interval) = &params.interval {
+            let bucket = interval_trunc(interval);
+
+            qb.push(" AND start_time IN (");
+            qb.push("SELECT DISTINCT ON (")
+                .push(bucket)
+                .push(") start_time FROM depth_price_history");
+            qb.push(" WHERE true");
+            // Keeps the first row of every bucket
+            qb.push(" ORDER BY ").push(bucket).push(", start_time");
+            qb.push(")");
+        }
+
+        push_date_range(&mut qb, &params.date_range);
+
+        add_comparison_filters(
+            &mut qb,
+            "asset_depth",
+            params.asset_depth_gt,
+            params.asset_depth_lt,
+            params.asset_depth_eq,
+        );
+
+        add_comparison_filters(
+            &mut qb,
+            "rune_depth",
+            params.rune_depth_gt,
+            params.rune_depth_lt,
+            params.rune_depth_eq,
+        );
+
+        add_comparison_filters(
+            &mut qb,
+            "asset_price",
+            params.asset_price_gt,
+            params.asset_price_lt,
+            params.asset_price_eq,
+        );
+
+        add_comparison_filters(
+            &mut qb,
+            "asset_price_usd",
+            params.asset_price_usd_gt,
+            params.asset_price_usd_lt,
+            params.asset_price_usd_eq,
+        );
+
+        add_comparison_filters(
+            &mut qb,
+            "liquidity_units",
+            params.liquidity_units_gt,
+            params.liquidity_units_lt,
+            params.liquidity_units_eq,
+        );
+
+        add_comparison_filters(
+            &mut qb,
+            "members_count",
+            params.members_count_gt,
+            params.members_count_lt,
+            params.members_count_eq,
+        );
+
+        add_comparison_filters(
+            &mut qb,
+            "synth_units",
+            params.synth_units_gt,
+            params.synth_units_lt,
+            params.synth_units_eq,
+        );
+
+        add_comparison_filters(
+            &mut qb,
+            "synth_supply",
+            params.synth_supply_gt,
+            params.synth_supply_lt,
+            params.synth_supply_eq,
+        );
+
+        add_comparison_filters(
+            &mut qb,
+            "units",
+            params.units_gt,
+            params.units_lt,
+            params.units_eq,
+        );
+
+        add_comparison_filters(
+            &mut qb,
+            "luvi",
+            params.luvi_gt,
+            params.luvi_lt,
+            params.luvi_eq,
+        );
+
+        // Sorting
+        if let Some(sort_by) = &params.sort_by {
+            qb.push(" ORDER BY ").push(sort_by);
+
+            if let Some(order) = &params.order {
+                match order.to_lowercase().as_str() {
+                    "asc" => qb.push(" ASC"),
+                    "desc" => qb.push(" DESC"),
+                    _ => qb.push(" ASC"),
+                };
+            }
+        }
+
+        // Pagination
+        if let Some(count) = params.count {
+            qb.push(" LIMIT ").push_bind(count);
+        } else if let Some(limit) = params.limit {
+            qb.push(" LIMIT ").push_bind(limit);
+
+            if let Some(page) = params.page {
+                let offset = (page as i64).saturating_sub(1) * limit as i64;
+                qb.push(" OFFSET ").push_bind(offset);
+            }
+        }
+
+        let result = qb
+            .build_query_as::<PriceHistory>()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to fetch price history"))?;
+
+        debug!(rows = result.len(), "Fetched price history");
+        Ok(result)
+    }
+
+    async fn last_timestamp(&self) -> Result<Option<i64>, AppError> {
+        let record = sqlx::query(
+            "SELECT start_time FROM depth_price_history ORDER BY start_time DESC LIMIT 1",
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AppError::from(e).context("Failed to get last timestamp"))?;
+
+        Ok(record.map(|record| {
+            record
+                .get::<chrono::DateTime<chrono::Utc>, _>("start_time")
+                .timestamp()
+        }))
+    }
+
+    async fn last_end_time(&self) -> Result<Option<chrono::DateTime<chrono::Utc>>, AppError> {
+        sqlx::query_scalar("SELECT MAX(end_time) FROM depth_price_history")
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to get last end time"))
+    }
+
+    async fn data_version(&self) -> Result<Option<DataVersion>, AppError> {
+        postgres::data_version(&self.pool, "depth_price_history").await
+    }
+
+    async fn find_in_range(&self, range: &SyncRange) -> Result<Vec<PriceHistory>, AppError> {
+        sqlx::query_as::<_, PriceHistory>(
+            "SELECT * FROM depth_price_history \
+             WHERE start_time >= TO_TIMESTAMP($1) AND start_time < TO_TIMESTAMP($2) \
+             ORDER BY start_time, id",
+        )
+        .bind(range.from as f64)
+        .bind(range.to as f64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AppError::from(e).context("Failed to fetch stored price history"))
+    }
+
+    async fn start_times(&self, range: &SyncRange) -> Result<HashSet<i64>, AppError> {
+        postgres::start_times(&self.pool, "depth_price_history", range).await
+    }
+
+    async fn interval_breaks(
+        &self,
+        date_range: &Option<String>,
+    ) -> Result<IntervalBreaks, AppError> {
+        postgres::interval_breaks(&self.pool, "depth_price_history", date_range).await
+    }
+}
+
+impl SortColumns for PriceHistory {
+    fn sort_key(&self, column: &str) -> Option<SortKey> {
+        Some(match column {
+            "id" => self.id.into(),
+            "start_time" => self.start_time.into(),
+            "end_time" => self.end_time.into(),
+            "asset_depth" => self.asset_depth.into(),
+            "rune_depth" => self.rune_depth.into(),
+            "asset_price" => self.asset_price.into(),
+            "asset_price_usd" => self.asset_price_usd.into(),
+            "liquidity_units" => self.liquidity_units.into(),
+            "members_count" => self.members_count.into(),
+            "synth_units" => self.synth_units.into(),
+            "synth_supply" => self.synth_supply.into(),
+            "units" => self.units.into(),
+            "luvi" => self.luvi.into(),
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryPriceHistoryRepository {
+    rows: RwLock<Vec<PriceHistory>>,
+    last_id: RwLock<i32>,
+}
+
+impl InMemoryPriceHistoryRepository {
+    /// Appends the rows with ids from a sequence, so deleted ids aren't handed out again.
+    fn insert(&self, rows: &mut Vec<PriceHistory>, price_histories: &[PriceHistory]) -> Vec<i32> {
+        let mut last_id = self.last_id.write().unwrap_or_else(|e| e.into_inner());
+        let mut ids = Vec::with_capacity(price_histories.len());
+        for price_history in price_histories {
+            *last_id += 1;
+            rows.push(PriceHistory {
+                id: Some(*last_id),
+                ..price_history.clone()
+            });
+            ids.push(*last_id);
+        }
+        ids
+    }
+}
+
+#[async_trait]
+impl PriceHistoryRepository for InMemoryPriceHistoryRepository {
+    async fn save_batch(&self, price_histories: &[PriceHistory]) -> Result<Vec<i32>, AppError> {
+        let mut rows = self.rows.write().unwrap_or_else(|e| e.into_inner());
+        Ok(self.insert(&mut rows, price_histories))
+    }
+
+    async fn replace_intervals(
+        &self,
+        price_histories: &[PriceHistory],
+    ) -> Result<Vec<i32>, AppError> {
+        // One write lock for both, so readers never see the intervals half replaced
+        let mut rows = self.rows.write().unwrap_or_else(|e| e.into_inner());
+        rows.retain(|row| {
+            !price_histories
+                .iter()
+                .any(|r| r.start_time == row.start_time)
+        });
+        Ok(self.insert(&mut rows, price_histories))
+    }
+
+    async fn delete_intervals(&self, start_times: &[DateTime<Utc>]) -> Result<(), AppError> {
+        let mut rows = self.rows.write().unwrap_or_else(|e| e.into_inner());
+        rows.retain(|row| !start_times.contains(&row.start_time));
+        Ok(())
+    }
+
+    async fn query(&self, params: &PriceHistoryParams) -> Result<Vec<PriceHistory>, AppError> {
+        let rows = self.rows.read().unwrap_or_else(|e| e.into_inner());
+        let buckets = params
+            .interval
+            .as_deref()
+            .map(|interval| first_per_bucket(&rows, interval, |r| r.start_time));
+
+        let mut result = Vec::new();
+        for row in rows.iter() {
+            if buckets
+                .as_ref()
+                .is_some_and(|b| !b.contains(&row.start_time))
+                || !in_date_range(&params.date_range, row.start_time, row.end_time)?
+                || !compare(
+                    row.asset_depth,
+                    params.asset_depth_gt,
+                    params.asset_depth_lt,
+                    params.asset_depth_eq,
+                )
+                || !compare(
+                    row.rune_depth,
+                    params.rune_depth_gt,
+                    params.rune_depth_lt,
+                    params.rune_depth_eq,
+                )
+                || !compare(
+                    row.asset_price,
+                    params.asset_price_gt,
+                    params.asset_price_lt,
+                    params.asset_price_eq,
+                )
+                || !compare(
+                    row.asset_price_usd,
+                    params.asset_price_usd_gt,
+                    params.asset_price_usd_lt,
+                    params.asset_price_usd_eq,
+                )
+                || !compare(
+                    row.liquidity_units,
+                    params.liquidity_units_gt,
+                    params.liquidity_units_lt,
+                    params.liquidity_units_eq,
+                )
+                || !compare(
+                    row.members_count,
+                    params.members_count_gt,
+                    params.members_count_lt,
+                    params.members_count_eq,
+                )
+                || !compare(
+                    row.synth_units,
+                    params.synth_units_gt,
+                    params.synth_units_lt,
+                    params.synth_units_eq,
+                )
+                || !compare(
+                    row.synth_supply,
+                    params.synth_supply_gt,
+                    params.synth_supply_lt,
+                    params.synth_supply_eq,
+                )
+                || !compare(row.units, params.units_gt, params.units_lt, params.units_eq)
+                || !compare(row.luvi, params.luvi_gt, params.luvi_lt, params.luvi_eq)
+            {
+                continue;
+            }
+            result.push(row.clone());
+        }
+
+        if let Some(sort_by) = &params.sort_by {
+            sort_rows(&mut result, sort_by, params.order.as_deref())?;
+        }
+
+        Ok(paginate_pages(
+            result,
+            params.count.map(i64::from),
+            params.limit.map(i64::from),
+            params.page.map(i64::from),
+        ))
+    }
+
+    async fn last_timestamp(&self) -> Result<Option<i64>, AppError> {
+        let rows = self.rows.read().unwrap_or_else(|e| e.into_inner());
+        Ok(rows.iter().map(|r| r.start_time.timestamp()).max())
+    }
+
+    async fn last_end_time(&self) -> Result<Option<chrono::DateTime<chrono::Utc>>, AppError> {
+        let rows = self.rows.read().unwrap_or_else(|e| e.into_inner());
+        Ok(rows.iter().map(|r| r.end_time).max())
+    }
+
+    async fn data_version(&self) -> Result<Option<DataVersion>, AppError> {
+        let rows = self.rows.read().unwrap_or_else(|e| e.into_inner());
+        Ok(memory::data_version(
+            rows.iter().map(|r| (r.end_time, r.id)),
+        ))
+    }
+
+    async fn find_in_range(&self, range: &SyncRange) -> Result<Vec<PriceHistory>, AppError> {
+        let rows = self.rows.read().unwrap_or_else(|e| e.into_inner());
+        let mut result: Vec<PriceHistory> = rows
+            .iter()
+            .filter(|r| range.contains(r.start_time))
+            .cloned()
+            .collect();
+        result.sort_by_key(|r| (r.start_time, r.id));
+        Ok(result)
+    }
+
+    async fn start_times(&self, range: &SyncRange) -> Result<HashSet<i64>, AppError> {
+        let rows = self.rows.read().unwrap_or_else(|e| e.into_inner());
+        Ok(rows
+            .iter()
+            .filter(|r| range.contains(r.start_time))
+            .map(|r| r.start_time.timestamp())
+            .collect())
+    }
+
+    async fn interval_breaks(
+        &self,
+        date_range: &Option<String>,
+    ) -> Result<IntervalBreaks, AppError> {
+        let rows = self.rows.read().unwrap_or_else(|e| e.into_inner());
+        let mut bounds = Vec::with_capacity(rows.len());
+        for row in rows.iter() {
+            if in_date_range(date_range, row.start_time, row.end_time)? {
+                bounds.push((row.start_time, row.end_time));
+            }
+        }
+        Ok(memory::interval_breaks(bounds))
+    }
+}
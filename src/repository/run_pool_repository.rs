@@ -0,0 +1,452 @@
+use crate::{
+    error::AppError,
+    model::{
+        data_version::DataVersion,
+        gap::IntervalBreaks,
+        rune_pool::{QueryParams, Runepool},
+        sync_run::SyncRange,
+    },
+    repository::{
+        memory::{
+            self, compare, first_per_bucket, in_date_range, paginate_pages, sort_rows, SortColumns,
+            SortKey,
+        },
+        postgres,
+    },
+    utils::interval::interval_trunc,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgPool, Postgres, QueryBuilder, Row};
+use std::{collections::HashSet, sync::RwLock};
+use tracing::{debug, instrument};
+
+#[async_trait]
+pub trait RunePoolRepository: Send + Sync {
+    /// Stores the rows and returns their ids in the same order.
+    async fn save_batch(&self, rune_pools: &[Runepool]) -> Result<Vec<i32>, AppError>;
+
+    /// Replaces the rows starting at the same times as `rune_pools` with them, all or
+    /// nothing, and returns the new ids in the same order.
+    async fn replace_intervals(&self, rune_pools: &[Runepool]) -> Result<Vec<i32>, AppError>;
+
+    /// Deletes the rows starting at any of `start_times`.
+    async fn delete_intervals(&self, start_times: &[DateTime<Utc>]) -> Result<(), AppError>;
+
+    async fn query(&self, params: &QueryParams) -> Result<Vec<Runepool>, AppError>;
+
+    /// Unix `start_time` of the newest row, `None` while empty.
+    async fn last_timestamp(&self) -> Result<Option<i64>, AppError>;
+
+    /// Newest `end_time`, `None` while empty.
+    async fn last_end_time(&self) -> Result<Option<chrono::DateTime<chrono::Utc>>, AppError>;
+
+    /// Version of the stored rows for the response validators, `None` while empty.
+    async fn data_version(&self) -> Result<Option<DataVersion>, AppError>;
+
+    /// Rows starting inside the range, oldest first.
+    async fn find_in_range(&self, range: &SyncRange) -> Result<Vec<Runepool>, AppError>;
+
+    /// Unix `start_time`s of the rows starting inside the range.
+    async fn start_times(&self, range: &SyncRange) -> Result<HashSet<i64>, AppError>;
+
+    /// Intervals within the date range that don't start where the previous one ended.
+    async fn interval_breaks(
+        &self,
+        date_range: &Option<String>,
+    ) -> Result<IntervalBreaks, AppError>;
+}
+
+pub struct PgRunePoolRepository {
+    pool: PgPool,
+}
+
+impl PgRunePoolRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Writes the rows on `conn` and returns their ids in the same order.
+    async fn insert_batch(
+        conn: &mut PgConnection,
+        rune_pools: &[Runepool],
+    ) -> Result<Vec<i32>, AppError> {
+        let copy = String::from(
+            "COPY rune_pool_history (start_time, end_time, count, units) \
+             FROM STDIN WITH (FORMAT text, DELIMITER '\t')",
+        );
+
+        let mut writer = conn
+            .copy_in_raw(&copy)
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to initialize batch write"))?;
+
+        // Process in chunks of 5000 records
+        for chunk in rune_pools.chunks(5000) {
+            let mut batch_data = String::with_capacity(chunk.len() * 256);
+
+            for rune_pool in chunk {
+                batch_data.push_str(&format!(
+                    "{}\t{}\t{}\t{}\n",
+                    rune_pool.start_time.format("%Y-%m-%d %H:%M:%S UTC"),
+                    rune_pool.end_time.format("%Y-%m-%d %H:%M:%S UTC"),
+                    rune_pool.count,
+                    rune_pool.units
+                ));
+            }
+
+            writer
+                .send(batch_data.as_bytes())
+                .await
+                .map_err(|e| AppError::from(e).context("Failed to write batch data"))?;
+        }
+
+        writer
+            .finish()
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to finish batch write"))?;
+
+        let ids = sqlx::query_as::<_, (i32,)>(
+            "SELECT id FROM rune_pool_history ORDER BY id DESC LIMIT $1",
+        )
+        .bind(rune_pools.len() as i32)
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| AppError::from(e).context("Failed to retrieve inserted IDs"))?;
+
+        let ids = ids.into_iter().rev().map(|(id,)| id).collect();
+        debug!(rows = rune_pools.len(), "Saved rune pool history");
+        Ok(ids)
+    }
+}
+
+#[async_trait]
+impl RunePoolRepository for PgRunePoolRepository {
+    #[instrument(skip_all, fields(rows = rune_pools.len()))]
+    async fn save_batch(&self, rune_pools: &[Runepool]) -> Result<Vec<i32>, AppError> {
+        if rune_pools.is_empty() {
+            return Err(AppError::new("No rune pools provided for batch save"));
+        }
+
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to start transaction"))?;
+        let ids = Self::insert_batch(&mut tx, rune_pools).await?;
+        tx.commit()
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to commit transaction"))?;
+        Ok(ids)
+    }
+
+    #[instrument(skip_all, fields(rows = rune_pools.len()))]
+    async fn replace_intervals(&self, rune_pools: &[Runepool]) -> Result<Vec<i32>, AppError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to start transaction"))?;
+        let start_times: Vec<DateTime<Utc>> = rune_pools.iter().map(|r| r.start_time).collect();
+        postgres::delete_intervals(&mut tx, "rune_pool_history", &start_times).await?;
+        let ids = Self::insert_batch(&mut tx, rune_pools).await?;
+        tx.commit()
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to commit transaction"))?;
+        Ok(ids)
+    }
+
+    #[instrument(skip_all, fields(rows = start_times.len()))]
+    async fn delete_intervals(&self, start_times: &[DateTime<Utc>]) -> Result<(), AppError> {
+        let mut conn = self
+            .pool
+            .acquire()
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to acquire a connection"))?;
+        postgres::delete_intervals(&mut conn, "rune_pool_history", start_times).await
+    }
+
+    #[instrument(skip_all)]
+    async fn query(&self, params: &QueryParams) -> Result<Vec<Runepool>, AppError> {
+        let mut qb = QueryBuilder::<Postgres>::new("SELECT * FROM rune_pool_history WHERE true");
+
+        // Interval filter
+        if let Some(interval) = &params.interval {
+            let bucket = interval_trunc(interval);
+
+            qb.push(" AND start_time IN (");
+            qb.push("SELECT DISTINCT ON (")
+                .push(bucket)
+                .push(") start_time FROM rune_pool_history WHERE true");
+            // Keeps the first row of every bucket
+            qb.push(" ORDER BY ").push(bucket).push(", start_time");
+            qb.push(")");
+        }
+
+        // Date range filter
+        if let Some(date_range) = &params.date_range {
+            let dates: Vec<&str> = date_range.split(',').collect();
+            if dates.len() == 2 {
+                qb.push(" AND start_time >= ")
+                    .push("TO_TIMESTAMP(")
+                    .push_bind(dates[0])
+                    .push(", 'YYYY-MM-DD')")
+                    .push(" AND end_time <= ")
+                    .push("TO_TIMESTAMP(")
+                    .push_bind(dates[1])
+                    .push(", 'YYYY-MM-DD')");
+            }
+        }
+
+        // Units filters
+        if let Some(units_gt) = params.units_gt {
+            qb.push(" AND units > ").push_bind(units_gt);
+        }
+        if let Some(units_lt) = params.units_lt {
+            qb.push(" AND units < ").push_bind(units_lt);
+        }
+        if let Some(units_eq) = params.units_eq {
+            qb.push(" AND units = ").push_bind(units_eq);
+        }
+
+        // Member count filters
+        if let Some(count_gt) = params.count_gt {
+            qb.push(" AND count > ").push_bind(count_gt);
+        }
+        if let Some(count_lt) = params.count_lt {
+            qb.push(" AND count < ").push_bind(count_lt);
+        }
+        if let Some(count_eq) = params.count_eq {
+            qb.push(" AND count = ").push_bind(count_eq);
+        }
+
+        // Sorting
+        if let Some(sort_by) = &params.sort_by {
+            qb.push(" ORDER BY ").push(sort_by);
+
+            if let Some(order) = &params.order {
+                match order.to_lowercase().as_str() {
+                    "asc" => qb.push(" ASC"),
+                    "desc" => qb.push(" DESC"),
+                    _ => qb.push(" ASC"), // default to ascending
+                };
+            }
+        }
+
+        // Pagination and limits
+        if let Some(count) = params.count {
+            qb.push(" LIMIT ").push_bind(count);
+        } else if let Some(limit) = params.limit {
+            qb.push(" LIMIT ").push_bind(limit);
+
+            if let Some(page) = params.page {
+                let offset = (page as i64).saturating_sub(1) * limit as i64;
+                qb.push(" OFFSET ").push_bind(offset);
+            }
+        }
+
+        let query = qb.build_query_as::<Runepool>();
+        let result = query
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to fetch rune pools"))?;
+
+        debug!(rows = result.len(), "Fetched rune pool history");
+        Ok(result)
+    }
+
+    async fn last_timestamp(&self) -> Result<Option<i64>, AppError> {
+        let record = sqlx::query(
+            "SELECT start_time FROM rune_pool_history ORDER BY start_time DESC LIMIT 1",
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AppError::from(e).context("Failed to get last timestamp"))?;
+
+        Ok(record.map(|record| {
+            record
+                .get::<chrono::DateTime<chrono::Utc>, _>("start_time")
+                .timestamp()
+        }))
+    }
+
+    async fn last_end_time(&self) -> Result<Option<chrono::DateTime<chrono::Utc>>, AppError> {
+        sqlx::query_scalar("SELECT MAX(end_time) FROM rune_pool_history")
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to get last end time"))
+    }
+
+    async fn data_version(&self) -> Result<Option<DataVersion>, AppError> {
+        postgres::data_version(&self.pool, "rune_pool_history").await
+    }
+
+    async fn find_in_range(&self, range: &SyncRange) -> Result<Vec<Runepool>, AppError> {
+        sqlx::query_as::<_, Runepool>(
+            "SELECT * FROM rune_pool_history \
+             WHERE start_time >= TO_TIMESTAMP($1) AND start_time < TO_TIMESTAMP($2) \
+             ORDER BY start_time, id",
+        )
+        .bind(range.from as f64)
+        .bind(range.to as f64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AppError::from(e).context("Failed to fetch stored rune pool history"))
+    }
+
+    async fn start_times(&self, range: &SyncRange) -> Result<HashSet<i64>, AppError> {
+        postgres::start_times(&self.pool, "rune_pool_history", range).await
+    }
+
+    async fn interval_breaks(
+        &self,
+        date_range: &Option<String>,
+    ) -> Result<IntervalBreaks, AppError> {
+        postgres::interval_breaks(&self.pool, "rune_pool_history", date_range).await
+    }
+}
+
+impl SortColumns for Runepool {
+    fn sort_key(&self, column: &str) -> Option<SortKey> {
+        Some(match column {
+            "id" => self.id.into(),
+            "count" => self.count.into(),
+            "start_time" => self.start_time.into(),
+            "end_time" => self.end_time.into(),
+            "units" => self.units.into(),
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryRunePoolRepository {
+    rows: RwLock<Vec<Runepool>>,
+    last_id: RwLock<i32>,
+}
+
+impl InMemoryRunePoolRepository {
+    /// Appends the rows with ids from a sequence, so deleted ids aren't handed out again.
+    fn insert(&self, rows: &mut Vec<Runepool>, rune_pools: &[Runepool]) -> Vec<i32> {
+        let mut last_id = self.last_id.write().unwrap_or_else(|e| e.into_inner());
+        let mut ids = Vec::with_capacity(rune_pools.len());
+        for rune_pool in rune_pools {
+            *last_id += 1;
+            rows.push(Runepool {
+                id: Some(*last_id),
+                ..rune_pool.clone()
+            });
+            ids.push(*last_id);
+        }
+        ids
+    }
+}
+
+#[async_trait]
+impl RunePoolRepository for InMemoryRunePoolRepository {
+    async fn save_batch(&self, rune_pools: &[Runepool]) -> Result<Vec<i32>, AppError> {
+        if rune_pools.is_empty() {
+            return Err(AppError::new("No rune pools provided for batch save"));
+        }
+
+        let mut rows = self.rows.write().unwrap_or_else(|e| e.into_inner());
+        Ok(self.insert(&mut rows, rune_pools))
+    }
+
+    async fn replace_intervals(&self, rune_pools: &[Runepool]) -> Result<Vec<i32>, AppError> {
+        // One write lock for both, so readers never see the intervals half replaced
+        let mut rows = self.rows.write().unwrap_or_else(|e| e.into_inner());
+        rows.retain(|row| !rune_pools.iter().any(|r| r.start_time == row.start_time));
+        Ok(self.insert(&mut rows, rune_pools))
+    }
+
+    async fn delete_intervals(&self, start_times: &[DateTime<Utc>]) -> Result<(), AppError> {
+        let mut rows = self.rows.write().unwrap_or_else(|e| e.into_inner());
+        rows.retain(|row| !start_times.contains(&row.start_time));
+        Ok(())
+    }
+
+    async fn query(&self, params: &QueryParams) -> Result<Vec<Runepool>, AppError> {
+        let rows = self.rows.read().unwrap_or_else(|e| e.into_inner());
+        let buckets = params
+            .interval
+            .as_deref()
+            .map(|interval| first_per_bucket(&rows, interval, |r| r.start_time));
+
+        let mut result = Vec::new();
+        for row in rows.iter() {
+            if buckets
+                .as_ref()
+                .is_some_and(|b| !b.contains(&row.start_time))
+                || !in_date_range(&params.date_range, row.start_time, row.end_time)?
+                || !compare(row.units, params.units_gt, params.units_lt, params.units_eq)
+                || !compare(row.count, params.count_gt, params.count_lt, params.count_eq)
+            {
+                continue;
+            }
+            result.push(row.clone());
+        }
+
+        if let Some(sort_by) = &params.sort_by {
+            sort_rows(&mut result, sort_by, params.order.as_deref())?;
+        }
+
+        Ok(paginate_pages(
+            result,
+            params.count.map(i64::from),
+            params.limit.map(i64::from),
+            params.page.map(i64::from),
+        ))
+    }
+
+    async fn last_timestamp(&self) -> Result<Option<i64>, AppError> {
+        let rows = self.rows.read().unwrap_or_else(|e| e.into_inner());
+        Ok(rows.iter().map(|r| r.start_time.timestamp()).max())
+    }
+
+    async fn last_end_time(&self) -> Result<Option<chrono::DateTime<chrono::Utc>>, AppError> {
+        let rows = self.rows.read().unwrap_or_else(|e| e.into_inner());
+        Ok(rows.iter().map(|r| r.end_time).max())
+    }
+
+    async fn data_version(&self) -> Result<Option<DataVersion>, AppError> {
+        let rows = self.rows.read().unwrap_or_else(|e| e.into_inner());
+        Ok(memory::data_version(
+            rows.iter().map(|r| (r.end_time, r.id)),
+        ))
+    }
+
+    async fn find_in_range(&self, range: &SyncRange) -> Result<Vec<Runepool>, AppError> {
+        let rows = self.rows.read().unwrap_or_else(|e| e.into_inner());
+        let mut result: Vec<Runepool> = rows
+            .iter()
+            .filter(|r| range.contains(r.start_time))
+            .cloned()
+            .collect();
+        result.sort_by_key(|r| (r.start_time, r.id));
+        Ok(result)
+    }
+
+    async fn start_times(&self, range: &SyncRange) -> Result<HashSet<i64>, AppError> {
+        let rows = self.rows.read().unwrap_or_else(|e| e.into_inner());
+        Ok(rows
+            .iter()
+            .filter(|r| range.contains(r.start_time))
+            .map(|r| r.start_time.timestamp())
+            .collect())
+    }
+
+    async fn interval_breaks(
+        &self,
+        date_range: &Option<String>,
+    ) -> Result<IntervalBreaks, AppError> {
+        let rows = self.rows.read().unwrap_or_else(|e| e.into_inner());
+        let mut bounds = Vec::with_capacity(rows.len());
+        for row in rows.iter() {
+            if in_date_range(date_range, row.start_time, row.end_time)? {
+                bounds.push((row.start_time, row.end_time));
+            }
+        }
+        Ok(memory::interval_breaks(bounds))
+    }
+}
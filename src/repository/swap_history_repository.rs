@@ -0,0 +1,951 @@
+use crate::{
+    error::AppError,
+    model::{
+        data_version::DataVersion,
+        gap::IntervalBreaks,
+        swap_history::{QueryParams, SwapHistory},
+        sync_run::SyncRange,
+    },
+    repository::{
+        memory::{
+            self, compare, first_per_bucket, in_date_range, paginate_pages, sort_rows, SortColumns,
+            SortKey,
+        },
+        postgres,
+    },
+    utils::{interval::interval_trunc, query::add_comparison_filters},
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgPool, Postgres, QueryBuilder, Row};
+use std::{collections::HashSet, sync::RwLock};
+use tracing::instrument;
+
+#[async_trait]
+pub trait SwapHistoryRepository: Send + Sync {
+    /// Stores the rows and returns their ids in the same order.
+    async fn save_batch(&self, swap_histories: &[SwapHistory]) -> Result<Vec<i32>, AppError>;
+
+    /// Replaces the rows starting at the same times as `swap_histories` with them, all or
+    /// nothing, and returns the new ids in the same order.
+    async fn replace_intervals(&self, swap_histories: &[SwapHistory])
+        -> Result<Vec<i32>, AppError>;
+
+    /// Deletes the rows starting at any of `start_times`.
+    async fn delete_intervals(&self, start_times: &[DateTime<Utc>]) -> Result<(), AppError>;
+
+    async fn query(&self, params: &QueryParams) -> Result<Vec<SwapHistory>, AppError>;
+
+    /// Unix `start_time` of the newest row, `None` while empty.
+    async fn last_timestamp(&self) -> Result<Option<i64>, AppError>;
+
+    /// Newest `end_time`, `None` while empty.
+    async fn last_end_time(&self) -> Result<Option<chrono::DateTime<chrono::Utc>>, AppError>;
+
+    /// Version of the stored rows for the response validators, `None` while empty.
+    async fn data_version(&self) -> Result<Option<DataVersion>, AppError>;
+
+    /// Rows starting inside the range, oldest first.
+    async fn find_in_range(&self, range: &SyncRange) -> Result<Vec<SwapHistory>, AppError>;
+
+    /// Unix `start_time`s of the rows starting inside the range.
+    async fn start_times(&self, range: &SyncRange) -> Result<HashSet<i64>, AppError>;
+
+    /// Intervals within the date range that don't start where the previous one ended.
+    async fn interval_breaks(
+        &self,
+        date_range: &Option<String>,
+    ) -> Result<IntervalBreaks, AppError>;
+}
+
+pub struct PgSwapHistoryRepository {
+    pool: PgPool,
+}
+
+impl PgSwapHistoryRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Writes the rows on `conn` and returns their ids in the same order.
+    async fn insert_batch(
+        conn: &mut PgConnection,
+        swap_histories: &[SwapHistory],
+    ) -> Result<Vec<i32>, AppError> {
+        let mut ids = Vec::with_capacity(swap_histories.len());
+        for record in swap_histories {
+            let id = sqlx::query(
+                r#"
+                INSERT INTO swap_history (
+                    average_slip, end_time, from_trade_average_slip, from_trade_count,
+                    from_trade_fees, from_trade_volume, from_trade_volume_usd, rune_price_usd,
+                    start_time, synth_mint_average_slip, synth_mint_count, synth_mint_fees,
+                    synth_mint_volume, synth_mint_volume_usd, synth_redeem_average_slip,
+                    synth_redeem_count, synth_redeem_fees, synth_redeem_volume,
+                    synth_redeem_volume_usd, to_asset_average_slip, to_asset_count,
+                    to_asset_fees, to_asset_volume, to_asset_volume_usd, to_rune_average_slip,
+                    to_rune_count, to_rune_fees, to_rune_volume, to_rune_volume_usd,
+                    to_trade_average_slip, to_trade_count, to_trade_fees, to_trade_volume,
+                    to_trade_volume_usd, total_count, total_fees, total_volume, total_volume_usd
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, 
+                        $16, $17, $18, $19, $20, $21, $22, $23, $24, $25, $26, $27, $28, 
+                        $29, $30, $31, $32, $33, $34, $35, $36, $37, $38)
+                RETURNING id"#,
+            )
+            .bind(record.average_slip)
+            .bind(record.end_time)
+            .bind(record.from_trade_average_slip)
+            .bind(record.from_trade_count)
+            .bind(record.from_trade_fees)
+            .bind(record.from_trade_volume)
+            .bind(record.from_trade_volume_usd)
+            .bind(record.rune_price_usd)
+            .bind(record.start_time)
+            .bind(record.synth_mint_average_slip)
+            .bind(record.synth_mint_count)
+            .bind(record.synth_mint_fees)
+            .bind(record.synth_mint_volume)
+            .bind(record.synth_mint_volume_usd)
+            .bind(record.synth_redeem_average_slip)
+            .bind(record.synth_redeem_count)
+            .bind(record.synth_redeem_fees)
+            .bind(record.synth_redeem_volume)
+            .bind(record.synth_redeem_volume_usd)
+            .bind(record.to_asset_average_slip)
+            .bind(record.to_asset_count)
+            .bind(record.to_asset_fees)
+            .bind(record.to_asset_volume)
+            .bind(record.to_asset_volume_usd)
+            .bind(record.to_rune_average_slip)
+            .bind(record.to_rune_count)
+            .bind(record.to_rune_fees)
+            .bind(record.to_rune_volume)
+            .bind(record.to_rune_volume_usd)
+            .bind(record.to_trade_average_slip)
+            .bind(record.to_trade_count)
+            .bind(record.to_trade_fees)
+            .bind(record.to_trade_volume)
+            .bind(record.to_trade_volume_usd)
+            .bind(record.total_count)
+            .bind(record.total_fees)
+            .bind(record.total_volume)
+            .bind(record.total_volume_usd)
+            .fetch_one(&mut *conn)
+            .await?;
+
+            ids.push(id.get::<i32, _>("id"));
+        }
+        Ok(ids)
+    }
+}
+
+#[async_trait]
+impl SwapHistoryRepository for PgSwapHistoryRepository {
+    #[instrument(skip_all, fields(rows = swap_histories.len()))]
+    async fn save_batch(&self, swap_histories: &[SwapHistory]) -> Result<Vec<i32>, AppError> {
+        const BATCH_SIZE: usize = 1000;
+        let mut results = Vec::new();
+
+        for chunk in swap_histories.chunks(BATCH_SIZE) {
+            let mut tx = self.pool.begin().await?;
+
+            results.extend(Self::insert_batch(&mut tx, chunk).await?);
+
+            tx.commit().await?;
+        }
+
+        Ok(results)
+    }
+
+    #[instrument(skip_all, fields(rows = swap_histories.len()))]
+    async fn replace_intervals(
+        &self,
+        swap_histories: &[SwapHistory],
+    ) -> Result<Vec<i32>, AppError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to start transaction"))?;
+        let start_times: Vec<DateTime<Utc>> = swap_histories.iter().map(|r| r.start_time).collect();
+        postgres::delete_intervals(&mut tx, "swap_history", &start_times).await?;
+        let ids = Self::insert_batch(&mut tx, swap_histories).await?;
+        tx.commit()
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to commit transaction"))?;
+        Ok(ids)
+    }
+
+    #[instrument(skip_all, fields(rows = start_times.len()))]
+    async fn delete_intervals(&self, start_times: &[DateTime<Utc>]) -> Result<(), AppError> {
+        let mut conn = self
+            .pool
+            .acquire()
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to acquire a connection"))?;
+        postgres::delete_intervals(&mut conn, "swap_history", start_times).await
+    }
+
+    #[instrument(skip_all)]
+    async fn query(&self, params: &QueryParams) -> Result<Vec<SwapHistory>, AppError> {
+        let mut qb = QueryBuilder::<Postgres>::new("SELECT * FROM swap_history WHERE true");
+
+        // Interval filter
+        if let Some(interval) = &params.interval {
+            let bucket = interval_trunc(interval);
+
+            qb.push(" AND start_time IN (");
+            qb.push("SELECT DISTINCT ON (")
+                .push(bucket)
+                .push(") start_time FROM swap_history WHERE true");
+            // Keeps the first row of every bucket
+            qb.push(" ORDER BY ").push(bucket).push(", start_time");
+            qb.push(")");
+        }
+
+        // Date range filter
+        if let Some(date_range) = &params.date_range {
+            let dates: Vec<&str> = date_range.split(',').collect();
+            qb.push(" AND start_time >= ")
+                .push("TO_TIMESTAMP(")
+                .push_bind(dates[0])
+                .push(", 'YYYY-MM-DD')")
+                .push(" AND end_time <= ")
+                .push("TO_TIMESTAMP(")
+                .push_bind(dates[1])
+                .push(", 'YYYY-MM-DD')");
+        }
+
+        // Add all comparison filters
+        add_comparison_filters(
+            &mut qb,
+            "from_trade_average_slip",
+            params.from_trade_average_slip_gt,
+            params.from_trade_average_slip_lt,
+            params.from_trade_average_slip_eq,
+        );
+
+        add_comparison_filters(
+            &mut qb,
+            "from_trade_count",
+            params.from_trade_count_gt,
+            params.from_trade_count_lt,
+            params.from_trade_count_eq,
+        );
+
+        add_comparison_filters(
+            &mut qb,
+            "from_trade_fees",
+            params.from_trade_fees_gt,
+            params.from_trade_fees_lt,
+            params.from_trade_fees_eq,
+        );
+
+        add_comparison_filters(
+            &mut qb,
+            "from_trade_volume",
+            params.from_trade_volume_gt,
+            params.from_trade_volume_lt,
+            params.from_trade_volume_eq,
+        );
+
+        add_comparison_filters(
+            &mut qb,
+            "from_trade_volume_usd",
+            params.from_trade_volume_usd_gt,
+            params.from_trade_volume_usd_lt,
+            params.from_trade_volume_usd_eq,
+        );
+
+        // Synth mint filters
+        add_comparison_filters(
+            &mut qb,
+            "synth_mint_average_slip",
+            params.synth_mint_average_slip_gt,
+            params.synth_mint_average_slip_lt,
+            params.synth_mint_average_slip_eq,
+        );
+
+        add_comparison_filters(
+            &mut qb,
+            "synth_mint_count",
+            params.synth_mint_count_gt,
+            params.synth_mint_count_lt,
+            params.synth_mint_count_eq,
+        );
+
+        add_comparison_filters(
+            &mut qb,
+            "synth_mint_fees",
+            params.synth_mint_fees_gt,
+            params.synth_mint_fees_lt,
+            params.synth_mint_fees_eq,
+        );
+
+        add_comparison_filters(
+            &mut qb,
+            "synth_mint_volume",
+            params.synth_mint_volume_gt,
+            params.synth_mint_volume_lt,
+            params.synth_mint_volume_eq,
+        );
+
+        add_comparison_filters(
+            &mut qb,
+            "synth_mint_volume_usd",
+            params.synth_mint_volume_usd_gt,
+            params.synth_mint_volume_usd_lt,
+            params.synth_mint_volume_usd_eq,
+        );
+
+        // Synth redeem filters
+        add_comparison_filters(
+            &mut qb,
+            "synth_redeem_average_slip",
+            params.synth_redeem_average_slip_gt,
+            params.synth_redeem_average_slip_lt,
+            params.synth_redeem_average_slip_eq,
+        );
+
+        add_comparison_filters(
+            &mut qb,
+            "synth_redeem_count",
+            params.synth_redeem_count_gt,
+            params.synth_redeem_count_lt,
+            params.synth_redeem_count_eq,
+        );
+
+        add_comparison_filters(
+            &mut qb,
+            "synth_redeem_fees",
+            params.synth_redeem_fees_gt,
+            params.synth_redeem_fees_lt,
+            params.synth_redeem_fees_eq,
+        );
+
+        add_comparison_filters(
+            &mut qb,
+            "synth_redeem_volume",
+            params.synth_redeem_volume_gt,
+            params.synth_redeem_volume_lt,
+            params.synth_redeem_volume_eq,
+        );
+
+        add_comparison_filters(
+            &mut qb,
+            "synth_redeem_volume_usd",
+            params.synth_redeem_volume_usd_gt,
+            params.synth_redeem_volume_usd_lt,
+            params.synth_redeem_volume_usd_eq,
+        );
+
+        // To asset filters
+        add_comparison_filters(
+            &mut qb,
+            "to_asset_average_slip",
+            params.to_asset_average_slip_gt,
+            params.to_asset_average_slip_lt,
+            params.to_asset_average_slip_eq,
+        );
+
+        add_comparison_filters(
+            &mut qb,
+            "to_asset_count",
+            params.to_asset_count_gt,
+            params.to_asset_count_lt,
+            params.to_asset_count_eq,
+        );
+
+        add_comparison_filters(
+            &mut qb,
+            "to_asset_fees",
+            params.to_asset_fees_gt,
+            params.to_asset_fees_lt,
+            params.to_asset_fees_eq,
+        );
+
+        add_comparison_filters(
+            &mut qb,
+            "to_asset_volume",
+            params.to_asset_volume_gt,
+            params.to_asset_volume_lt,
+            params.to_asset_volume_eq,
+        );
+
+        add_comparison_filters(
+            &mut qb,
+            "to_asset_volume_usd",
+            params.to_asset_volume_usd_gt,
+            params.to_asset_volume_usd_lt,
+            params.to_asset_volume_usd_eq,
+        );
+
+        // To rune filters
+        add_comparison_filters(
+            &mut qb,
+            "to_rune_average_slip",
+            params.to_rune_average_slip_gt,
+            params.to_rune_average_slip_lt,
+            params.to_rune_average_slip_eq,
+        );
+
+        add_comparison_filters(
+            &mut qb,
+            "to_rune_count",
+            params.to_rune_count_gt,
+            params.to_rune_count_lt,
+            params.to_rune_count_eq,
+        );
+
+        add_comparison_filters(
+            &mut qb,
+            "to_rune_fees",
+            params.to_rune_fees_gt,
+            params.to_rune_fees_lt,
+            params.to_rune_fees_eq,
+        );
+
+        add_comparison_filters(
+            &mut qb,
+            "to_rune_volume",
+            params.to_rune_volume_gt,
+            params.to_rune_volume_lt,
+            params.to_rune_volume_eq,
+        );
+
+        add_comparison_filters(
+            &mut qb,
+            "to_rune_volume_usd",
+            params.to_rune_volume_usd_gt,
+            params.to_rune_volume_usd_lt,
+            params.to_rune_volume_usd_eq,
+        );
+
+        // To trade filters
+        add_comparison_filters(
+            &mut qb,
+            "to_trade_average_slip",
+            params.to_trade_average_slip_gt,
+            params.to_trade_average_slip_lt,
+            params.to_trade_average_slip_eq,
+        );
+
+        add_comparison_filters(
+            &mut qb,
+            "to_trade_count",
+            params.to_trade_count_gt,
+            params.to_trade_count_lt,
+            params.to_trade_count_eq,
+        );
+
+        add_comparison_filters(
+            &mut qb,
+            "to_trade_fees",
+            params.to_trade_fees_gt,
+            params.to_trade_fees_lt,
+            params.to_trade_fees_eq,
+        );
+
+        add_comparison_filters(
+            &mut qb,
+            "to_trade_volume",
+            params.to_trade_volume_gt,
+            params.to_trade_volume_lt,
+            params.to_trade_volume_eq,
+        );
+
+        add_comparison_filters(
+            &mut qb,
+            "to_trade_volume_usd",
+            params.to_trade_volume_usd_gt,
+            params.to_trade_volume_usd_lt,
+            params.to_trade_volume_usd_eq,
+        );
+
+        // Total metrics filters
+        add_comparison_filters(
+            &mut qb,
+            "total_count",
+            params.total_count_gt,
+            params.total_count_lt,
+            params.total_count_eq,
+        );
+
+        add_comparison_filters(
+            &mut qb,
+            "total_fees",
+            params.total_fees_gt,
+            params.total_fees_lt,
+            params.total_fees_eq,
+        );
+
+        add_comparison_filters(
+            &mut qb,
+            "total_volume",
+            params.total_volume_gt,
+            params.total_volume_lt,
+            params.total_volume_eq,
+        );
+
+        // Sorting
+        if let Some(sort_by) = &params.sort_by {
+            qb.push(" ORDER BY ").push(sort_by);
+
+            if let Some(order) = &params.order {
+                match order.to_lowercase().as_str() {
+                    "asc" => qb.push(" ASC"),
+                    "desc" => qb.push(" DESC"),
+                    _ => qb.push(" ASC"),
+                };
+            }
+        }
+
+        // Pagination
+        if let Some(count) = params.count {
+            qb.push(" LIMIT ").push_bind(count);
+        } else if let Some(limit) = params.limit {
+            qb.push(" LIMIT ").push_bind(limit);
+
+            if let Some(page) = params.page {
+                let offset = (page as i64).saturating_sub(1) * limit as i64;
+                qb.push(" OFFSET ").push_bind(offset);
+            }
+        }
+
+        let query = qb.build_query_as::<SwapHistory>();
+        let result = query
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to fetch swap history"))?;
+
+        Ok(result)
+    }
+
+    async fn last_timestamp(&self) -> Result<Option<i64>, AppError> {
+        let record =
+            sqlx::query("SELECT start_time FROM swap_history ORDER BY start_time DESC LIMIT 1")
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(|e| AppError::from(e).context("Failed to get last timestamp"))?;
+
+        Ok(record.map(|record| {
+            record
+                .get::<chrono::DateTime<chrono::Utc>, _>("start_time")
+                .timestamp()
+        }))
+    }
+
+    async fn last_end_time(&self) -> Result<Option<chrono::DateTime<chrono::Utc>>, AppError> {
+        sqlx::query_scalar("SELECT MAX(end_time) FROM swap_history")
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to get last end time"))
+    }
+
+    async fn data_version(&self) -> Result<Option<DataVersion>, AppError> {
+        postgres::data_version(&self.pool, "swap_history").await
+    }
+
+    async fn find_in_range(&self, range: &SyncRange) -> Result<Vec<SwapHistory>, AppError> {
+        sqlx::query_as::<_, SwapHistory>(
+            "SELECT * FROM swap_history \
+             WHERE start_time >= TO_TIMESTAMP($1) AND start_time < TO_TIMESTAMP($2) \
+             ORDER BY start_time, id",
+        )
+        .bind(range.from as f64)
+        .bind(range.to as f64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AppError::from(e).context("Failed to fetch stored swap history"))
+    }
+
+    async fn start_times(&self, range: &SyncRange) -> Result<HashSet<i64>, AppError> {
+        postgres::start_times(&self.pool, "swap_history", range).await
+    }
+
+    async fn interval_breaks(
+        &self,
+        date_range: &Option<String>,
+    ) -> Result<IntervalBreaks, AppError> {
+        postgres::interval_breaks(&self.pool, "swap_history", date_range).await
+    }
+}
+
+impl SortColumns for SwapHistory {
+    fn sort_key(&self, column: &str) -> Option<SortKey> {
+        Some(match column {
+            "id" => self.id.into(),
+            "average_slip" => self.average_slip.into(),
+            "start_time" => self.start_time.into(),
+            "end_time" => self.end_time.into(),
+            "from_trade_average_slip" => self.from_trade_average_slip.into(),
+            "from_trade_count" => self.from_trade_count.into(),
+            "from_trade_fees" => self.from_trade_fees.into(),
+            "from_trade_volume" => self.from_trade_volume.into(),
+            "from_trade_volume_usd" => self.from_trade_volume_usd.into(),
+            "rune_price_usd" => self.rune_price_usd.into(),
+            "synth_mint_average_slip" => self.synth_mint_average_slip.into(),
+            "synth_mint_count" => self.synth_mint_count.into(),
+            "synth_mint_fees" => self.synth_mint_fees.into(),
+            "synth_mint_volume" => self.synth_mint_volume.into(),
+            "synth_mint_volume_usd" => self.synth_mint_volume_usd.into(),
+            "synth_redeem_average_slip" => self.synth_redeem_average_slip.into(),
+            "synth_redeem_count" => self.synth_redeem_count.into(),
+            "synth_redeem_fees" => self.synth_redeem_fees.into(),
+            "synth_redeem_volume" => self.synth_redeem_volume.into(),
+            "synth_redeem_volume_usd" => self.synth_redeem_volume_usd.into(),
+            "to_asset_average_slip" => self.to_asset_average_slip.into(),
+            "to_asset_count" => self.to_asset_count.into(),
+            "to_asset_fees" => self.to_asset_fees.into(),
+            "to_asset_volume" => self.to_asset_volume.into(),
+            "to_asset_volume_usd" => self.to_asset_volume_usd.into(),
+            "to_rune_average_slip" => self.to_rune_average_slip.into(),
+            "to_rune_count" => self.to_rune_count.into(),
+            "to_rune_fees" => self.to_rune_fees.into(),
+            "to_rune_volume" => self.to_rune_volume.into(),
+            "to_rune_volume_usd" => self.to_rune_volume_usd.into(),
+            "to_trade_average_slip" => self.to_trade_average_slip.into(),
+            "to_trade_count" => self.to_trade_count.into(),
+            "to_trade_fees" => self.to_trade_fees.into(),
+            "to_trade_volume" => self.to_trade_volume.into(),
+            "to_trade_volume_usd" => self.to_trade_volume_usd.into(),
+            "total_count" => self.total_count.into(),
+            "total_fees" => self.total_fees.into(),
+            "total_volume" => self.total_volume.into(),
+            "total_volume_usd" => self.total_volume_usd.into(),
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct InMemorySwapHistoryRepository {
+    rows: RwLock<Vec<SwapHistory>>,
+    last_id: RwLock<i32>,
+}
+
+impl InMemorySwapHistoryRepository {
+    /// Appends the rows with ids from a sequence, so deleted ids aren't handed out again.
+    fn insert(&self, rows: &mut Vec<SwapHistory>, swap_histories: &[SwapHistory]) -> Vec<i32> {
+        let mut last_id = self.last_id.write().unwrap_or_else(|e| e.into_inner());
+        let mut ids = Vec::with_capacity(swap_histories.len());
+        for swap_history in swap_histories {
+            *last_id += 1;
+            rows.push(SwapHistory {
+                id: Some(*last_id),
+                ..swap_history.clone()
+            });
+            ids.push(*last_id);
+        }
+        ids
+    }
+}
+
+#[async_trait]
+impl SwapHistoryRepository for InMemorySwapHistoryRepository {
+    async fn save_batch(&self, swap_histories: &[SwapHistory]) -> Result<Vec<i32>, AppError> {
+        let mut rows = self.rows.write().unwrap_or_else(|e| e.into_inner());
+        Ok(self.insert(&mut rows, swap_histories))
+    }
+
+    async fn replace_intervals(
+        &self,
+        swap_histories: &[SwapHistory],
+    ) -> Result<Vec<i32>, AppError> {
+        // One write lock for both, so readers never see the intervals half replaced
+        let mut rows = self.rows.write().unwrap_or_else(|e| e.into_inner());
+        rows.retain(|row| {
+            !swap_histories
+                .iter()
+                .any(|r| r.start_time == row.start_time)
+        });
+        Ok(self.insert(&mut rows, swap_histories))
+    }
+
+    async fn delete_intervals(&self, start_times: &[DateTime<Utc>]) -> Result<(), AppError> {
+        let mut rows = self.rows.write().unwrap_or_else(|e| e.into_inner());
+        rows.retain(|row| !start_times.contains(&row.start_time));
+        Ok(())
+    }
+
+    async fn query(&self, params: &QueryParams) -> Result<Vec<SwapHistory>, AppError> {
+        let rows = self.rows.read().unwrap_or_else(|e| e.into_inner());
+        let buckets = params
+            .interval
+            .as_deref()
+            .map(|interval| first_per_bucket(&rows, interval, |r| r.start_time));
+
+        let mut result = Vec::new();
+        for row in rows.iter() {
+            if buckets
+                .as_ref()
+                .is_some_and(|b| !b.contains(&row.start_time))
+                || !in_date_range(&params.date_range, row.start_time, row.end_time)?
+                || !compare(
+                    row.from_trade_average_slip,
+                    params.from_trade_average_slip_gt,
+                    params.from_trade_average_slip_lt,
+                    params.from_trade_average_slip_eq,
+                )
+                || !compare(
+                    row.from_trade_count,
+                    params.from_trade_count_gt,
+                    params.from_trade_count_lt,
+                    params.from_trade_count_eq,
+                )
+                || !compare(
+                    row.from_trade_fees,
+                    params.from_trade_fees_gt,
+                    params.from_trade_fees_lt,
+                    params.from_trade_fees_eq,
+                )
+                || !compare(
+                    row.from_trade_volume,
+                    params.from_trade_volume_gt,
+                    params.from_trade_volume_lt,
+                    params.from_trade_volume_eq,
+                )
+                || !compare(
+                    row.from_trade_volume_usd,
+                    params.from_trade_volume_usd_gt,
+                    params.from_trade_volume_usd_lt,
+                    params.from_trade_volume_usd_eq,
+                )
+                || !compare(
+                    row.synth_mint_average_slip,
+                    params.synth_mint_average_slip_gt,
+                    params.synth_mint_average_slip_lt,
+                    params.synth_mint_average_slip_eq,
+                )
+                || !compare(
+                    row.synth_mint_count,
+                    params.synth_mint_count_gt,
+                    params.synth_mint_count_lt,
+                    params.synth_mint_count_eq,
+                )
+                || !compare(
+                    row.synth_mint_fees,
+                    params.synth_mint_fees_gt,
+                    params.synth_mint_fees_lt,
+                    params.synth_mint_fees_eq,
+                )
+                || !compare(
+                    row.synth_mint_volume,
+                    params.synth_mint_volume_gt,
+                    params.synth_mint_volume_lt,
+                    params.synth_mint_volume_eq,
+                )
+                || !compare(
+                    row.synth_mint_volume_usd,
+                    params.synth_mint_volume_usd_gt,
+                    params.synth_mint_volume_usd_lt,
+                    params.synth_mint_volume_usd_eq,
+                )
+                || !compare(
+                    row.synth_redeem_average_slip,
+                    params.synth_redeem_average_slip_gt,
+                    params.synth_redeem_average_slip_lt,
+                    params.synth_redeem_average_slip_eq,
+                )
+                || !compare(
+                    row.synth_redeem_count,
+                    params.synth_redeem_count_gt,
+                    params.synth_redeem_count_lt,
+                    params.synth_redeem_count_eq,
+                )
+                || !compare(
+                    row.synth_redeem_fees,
+                    params.synth_redeem_fees_gt,
+                    params.synth_redeem_fees_lt,
+                    params.synth_redeem_fees_eq,
+                )
+                || !compare(
+                    row.synth_redeem_volume,
+                    params.synth_redeem_volume_gt,
+                    params.synth_redeem_volume_lt,
+                    params.synth_redeem_volume_eq,
+                )
+                || !compare(
+                    row.synth_redeem_volume_usd,
+                    params.synth_redeem_volume_usd_gt,
+                    params.synth_redeem_volume_usd_lt,
+                    params.synth_redeem_volume_usd_eq,
+                )
+                || !compare(
+                    row.to_asset_average_slip,
+                    params.to_asset_average_slip_gt,
+                    params.to_asset_average_slip_lt,
+                    params.to_asset_average_slip_eq,
+                )
+                || !compare(
+                    row.to_asset_count,
+                    params.to_asset_count_gt,
+                    params.to_asset_count_lt,
+                    params.to_asset_count_eq,
+                )
+                || !compare(
+                    row.to_asset_fees,
+                    params.to_asset_fees_gt,
+                    params.to_asset_fees_lt,
+                    params.to_asset_fees_eq,
+                )
+                || !compare(
+                    row.to_asset_volume,
+                    params.to_asset_volume_gt,
+                    params.to_asset_volume_lt,
+                    params.to_asset_volume_eq,
+                )
+                || !compare(
+                    row.to_asset_volume_usd,
+                    params.to_asset_volume_usd_gt,
+                    params.to_asset_volume_usd_lt,
+                    params.to_asset_volume_usd_eq,
+                )
+                || !compare(
+                    row.to_rune_average_slip,
+                    params.to_rune_average_slip_gt,
+                    params.to_rune_average_slip_lt,
+                    params.to_rune_average_slip_eq,
+                )
+                || !compare(
+                    row.to_rune_count,
+                    params.to_rune_count_gt,
+                    params.to_rune_count_lt,
+                    params.to_rune_count_eq,
+                )
+                || !compare(
+                    row.to_rune_fees,
+                    params.to_rune_fees_gt,
+                    params.to_rune_fees_lt,
+                    params.to_rune_fees_eq,
+                )
+                || !compare(
+                    row.to_rune_volume,
+                    params.to_rune_volume_gt,
+                    params.to_rune_volume_lt,
+                    params.to_rune_volume_eq,
+                )
+                || !compare(
+                    row.to_rune_volume_usd,
+                    params.to_rune_volume_usd_gt,
+                    params.to_rune_volume_usd_lt,
+                    params.to_rune_volume_usd_eq,
+                )
+                || !compare(
+                    row.to_trade_average_slip,
+                    params.to_trade_average_slip_gt,
+                    params.to_trade_average_slip_lt,
+                    params.to_trade_average_slip_eq,
+                )
+                || !compare(
+                    row.to_trade_count,
+                    params.to_trade_count_gt,
+                    params.to_trade_count_lt,
+                    params.to_trade_count_eq,
+                )
+                || !compare(
+                    row.to_trade_fees,
+                    params.to_trade_fees_gt,
+                    params.to_trade_fees_lt,
+                    params.to_trade_fees_eq,
+                )
+                || !compare(
+                    row.to_trade_volume,
+                    params.to_trade_volume_gt,
+                    params.to_trade_volume_lt,
+                    params.to_trade_volume_eq,
+                )
+                || !compare(
+                    row.to_trade_volume_usd,
+                    params.to_trade_volume_usd_gt,
+                    params.to_trade_volume_usd_lt,
+                    params.to_trade_volume_usd_eq,
+                )
+                || !compare(
+                    row.total_count,
+                    params.total_count_gt,
+                    params.total_count_lt,
+                    params.total_count_eq,
+                )
+                || !compare(
+                    row.total_fees,
+                    params.total_fees_gt,
+                    params.total_fees_lt,
+                    params.total_fees_eq,
+                )
+                || !compare(
+                    row.total_volume,
+                    params.total_volume_gt,
+                    params.total_volume_lt,
+                    params.total_volume_eq,
+                )
+            {
+                continue;
+            }
+            result.push(row.clone());
+        }
+
+        if let Some(sort_by) = &params.sort_by {
+            sort_rows(&mut result, sort_by, params.order.as_deref())?;
+        }
+
+        Ok(paginate_pages(
+            result,
+            params.count.map(i64::from),
+            params.limit.map(i64::from),
+            params.page.map(i64::from),
+        ))
+    }
+
+    async fn last_timestamp(&self) -> Result<Option<i64>, AppError> {
+        let rows = self.rows.read().unwrap_or_else(|e| e.into_inner());
+        Ok(rows.iter().map(|r| r.start_time.timestamp()).max())
+    }
+
+    async fn last_end_time(&self) -> Result<Option<chrono::DateTime<chrono::Utc>>, AppError> {
+        let rows = self.rows.read().unwrap_or_else(|e| e.into_inner());
+        Ok(rows.iter().map(|r| r.end_time).max())
+    }
+
+    async fn data_version(&self) -> Result<Option<DataVersion>, AppError> {
+        let rows = self.rows.read().unwrap_or_else(|e| e.into_inner());
+        Ok(memory::data_version(
+            rows.iter().map(|r| (r.end_time, r.id)),
+        ))
+    }
+
+    async fn find_in_range(&self, range: &SyncRange) -> Result<Vec<SwapHistory>, AppError> {
+        let rows = self.rows.read().unwrap_or_else(|e| e.into_inner());
+        let mut result: Vec<SwapHistory> = rows
+            .iter()
+            .filter(|r| range.contains(r.start_time))
+            .cloned()
+            .collect();
+        result.sort_by_key(|r| (r.start_time, r.id));
+        Ok(result)
+    }
+
+    async fn start_times(&self, range: &SyncRange) -> Result<HashSet<i64>, AppError> {
+        let rows = self.rows.read().unwrap_or_else(|e| e.into_inner());
+        Ok(rows
+            .iter()
+            .filter(|r| range.contains(r.start_time))
+            .map(|r| r.start_time.timestamp())
+            .collect())
+    }
+
+    async fn interval_breaks(
+        &self,
+        date_range: &Option<String>,
+    ) -> Result<IntervalBreaks, AppError> {
+        let rows = self.rows.read().unwrap_or_else(|e| e.into_inner());
+        let mut bounds = Vec::with_capacity(rows.len());
+        for row in rows.iter() {
+            if in_date_range(date_range, row.start_time, row.end_time)? {
+                bounds.push((row.start_time, row.end_time));
+            }
+        }
+        Ok(memory::interval_breaks(bounds))
+    }
+}
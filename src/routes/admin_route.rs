@@ -0,0 +1,239 @@
+use axum::{
+    debug_handler,
+    extract::{
+        rejection::{JsonRejection, PathRejection, QueryRejection},
+        Path, Query, Request, State,
+    },
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::Utc;
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+use uuid::Uuid;
+
+use crate::{
+    cronjobs::{dataset::Dataset, sync_jobs},
+    error::AppError,
+    model::{
+        api_key::Scope,
+        gap::GapParams,
+        reconciliation::{ReconcileParams, MAX_RECONCILE_WINDOW_HOURS},
+        sync_job::{BackfillRequest, SyncJobKind, TriggerSyncParams},
+        sync_run::SyncRange,
+    },
+    routes::api_key_route::check_api_key,
+    state::AppState,
+};
+
+/// Guards the admin routes with an `admin` API key or `Authorization: Bearer
+/// <ADMIN_TOKEN>`. Without either the admin API is disabled altogether.
+pub async fn require_admin(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    match check_api_key(&state, &mut request, Scope::Admin).await {
+        Err(response) => return response,
+        Ok(Some(_)) => return next.run(request).await,
+        Ok(None) => {}
+    }
+
+    let Some(token) = state.config.admin_token.as_deref() else {
+        return AppError::forbidden(
+            "Admin API is disabled, set ADMIN_TOKEN or use an admin API key",
+        )
+        .into_response();
+    };
+
+    let provided = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    if !provided.is_some_and(|provided| token_matches(provided, token)) {
+        return AppError::unauthorized("Missing or invalid admin token").into_response();
+    }
+
+    next.run(request).await
+}
+
+/// Compares the digests in constant time, so neither the content nor the length of
+/// the token leaks through the response time.
+fn token_matches(provided: &str, token: &str) -> bool {
+    Sha256::digest(provided.as_bytes())
+        .ct_eq(&Sha256::digest(token.as_bytes()))
+        .into()
+}
+
+fn parse_dataset(dataset: &str) -> Result<Dataset, AppError> {
+    dataset
+        .parse::<Dataset>()
+        .map_err(|e| AppError::not_found(e.message))
+}
+
+#[debug_handler]
+pub async fn get_sync_status(State(state): State<AppState>) -> impl IntoResponse {
+    let res = state.sync_runs.get_sync_status().await?;
+    Ok::<_, AppError>((StatusCode::OK, Json(res)))
+}
+
+#[debug_handler]
+pub async fn trigger_sync(
+    State(state): State<AppState>,
+    Path(dataset): Path<String>,
+    params: Result<Query<TriggerSyncParams>, QueryRejection>,
+) -> impl IntoResponse {
+    let dataset = parse_dataset(&dataset)?;
+    let Query(params) = params?;
+
+    let range = dataset.incremental_range(&state).await;
+    let job = sync_jobs::spawn(&state, dataset, params.pool, SyncJobKind::Sync, vec![range])?;
+    Ok::<_, AppError>((StatusCode::ACCEPTED, Json(job)))
+}
+
+#[debug_handler]
+pub async fn trigger_backfill(
+    State(state): State<AppState>,
+    Path(dataset): Path<String>,
+    request: Result<Json<BackfillRequest>, JsonRejection>,
+) -> impl IntoResponse {
+    let dataset = parse_dataset(&dataset)?;
+    let Json(request) = request?;
+
+    if request.from < 0 || request.from >= request.to {
+        return Err(AppError::invalid_field(
+            "from",
+            "from must be a unix timestamp before to",
+        ));
+    }
+    if request.from >= Utc::now().timestamp() {
+        return Err(AppError::invalid_field("from", "from must be in the past"));
+    }
+    let range = SyncRange {
+        from: request.from,
+        to: request.to.min(Utc::now().timestamp()),
+    };
+
+    let job = sync_jobs::spawn(
+        &state,
+        dataset,
+        request.pool,
+        SyncJobKind::Backfill,
+        vec![range],
+    )?;
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+#[debug_handler]
+pub async fn get_sync_jobs(State(state): State<AppState>) -> impl IntoResponse {
+    (StatusCode::OK, Json(sync_jobs::list(&state)))
+}
+
+#[debug_handler]
+pub async fn get_sync_job(
+    State(state): State<AppState>,
+    id: Result<Path<Uuid>, PathRejection>,
+) -> impl IntoResponse {
+    let Path(id) = id?;
+    let job = sync_jobs::get(&state, id)?;
+    Ok::<_, AppError>((StatusCode::OK, Json(job)))
+}
+
+#[debug_handler]
+pub async fn cancel_sync_job(
+    State(state): State<AppState>,
+    id: Result<Path<Uuid>, PathRejection>,
+) -> impl IntoResponse {
+    let Path(id) = id?;
+    let job = sync_jobs::cancel(&state, id)?;
+    Ok::<_, AppError>((StatusCode::ACCEPTED, Json(job)))
+}
+
+fn query_datasets(dataset: &Option<String>) -> Result<Vec<Dataset>, AppError> {
+    match dataset {
+        Some(dataset) => Ok(vec![dataset
+            .parse::<Dataset>()
+            .map_err(|e| AppError::invalid_field("dataset", e.message))?]),
+        None => Ok(Dataset::ALL.to_vec()),
+    }
+}
+
+#[debug_handler]
+pub async fn get_gaps(
+    State(state): State<AppState>,
+    params: Result<Query<GapParams>, QueryRejection>,
+) -> impl IntoResponse {
+    let Query(params) = params?;
+    let datasets = query_datasets(&params.dataset)?;
+    let mut res = Vec::with_capacity(datasets.len());
+    for dataset in datasets {
+        res.push(state.gaps.find_gaps(dataset, &params.date_range).await?);
+    }
+    Ok::<_, AppError>((StatusCode::OK, Json(res)))
+}
+
+/// Starts a repair job per dataset that has gaps, re-fetching only the missing ranges.
+/// `409 Conflict` without starting any of them while one of the datasets has a job running.
+#[debug_handler]
+pub async fn repair_gaps(
+    State(state): State<AppState>,
+    params: Result<Query<GapParams>, QueryRejection>,
+) -> impl IntoResponse {
+    let Query(params) = params?;
+    let datasets = query_datasets(&params.dataset)?;
+    let mut repairs = Vec::new();
+    for dataset in datasets {
+        let ranges = state
+            .gaps
+            .get_repair_ranges(dataset, &params.date_range)
+            .await?;
+        if !ranges.is_empty() {
+            repairs.push((dataset, ranges));
+        }
+    }
+    // Either every dataset's repair starts or, on a conflict, none of them
+    let jobs = sync_jobs::spawn_all(&state, SyncJobKind::Repair, repairs)?;
+    Ok::<_, AppError>((StatusCode::ACCEPTED, Json(jobs)))
+}
+
+/// Diffs a window of stored data against Midgard, rewriting divergent rows with `repair=true`.
+#[debug_handler]
+pub async fn reconcile(
+    State(state): State<AppState>,
+    params: Result<Query<ReconcileParams>, QueryRejection>,
+) -> impl IntoResponse {
+    let Query(params) = params?;
+    let datasets = query_datasets(&params.dataset)?;
+    let default = SyncRange::last_hours(24);
+    let range = SyncRange {
+        from: params.from.unwrap_or(default.from),
+        to: params.to.unwrap_or(default.to),
+    };
+    if range.from >= range.to {
+        return Err(AppError::invalid_field("from", "from must be before to"));
+    }
+    if range.to - range.from > MAX_RECONCILE_WINDOW_HOURS * 3600 {
+        return Err(AppError::invalid_field(
+            "to",
+            format!(
+                "the window can span at most {} hours",
+                MAX_RECONCILE_WINDOW_HOURS
+            ),
+        ));
+    }
+
+    let mut res = Vec::with_capacity(datasets.len());
+    for dataset in datasets {
+        res.push(
+            state
+                .reconciliation
+                .reconcile(&state, dataset, range, params.repair.unwrap_or(false))
+                .await?,
+        );
+    }
+    Ok::<_, AppError>((StatusCode::OK, Json(res)))
+}
@@ -0,0 +1,137 @@
+use axum::{
+    debug_handler,
+    extract::{
+        rejection::{JsonRejection, PathRejection, QueryRejection},
+        MatchedPath, Path, Query, Request, State,
+    },
+    http::{header, HeaderName, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use tracing::Span;
+
+use crate::{
+    error::AppError,
+    model::api_key::{ApiKey, CreateApiKey, Scope, UsageParams},
+    service::api_key_service::Throttled,
+    state::AppState,
+};
+
+/// Header carrying the API key of a request.
+pub const API_KEY_HEADER: HeaderName = HeaderName::from_static("x-api-key");
+
+impl IntoResponse for Throttled {
+    fn into_response(self) -> Response {
+        let mut response = self.error.into_response();
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, self.retry_after_secs.into());
+        response
+    }
+}
+
+/// Authenticates the `x-api-key` of the request, checks it allows `scope` and counts
+/// the request against its limits. `None` when the request has no key.
+pub async fn check_api_key(
+    state: &AppState,
+    request: &mut Request,
+    scope: Scope,
+) -> Result<Option<ApiKey>, Response> {
+    let Some(key) = request.headers().get(&API_KEY_HEADER) else {
+        return Ok(None);
+    };
+    let key = key
+        .to_str()
+        .map_err(|_| AppError::unauthorized("Invalid or revoked API key").into_response())?;
+
+    let api_key = state
+        .api_keys
+        .authenticate(key)
+        .await
+        .map_err(IntoResponse::into_response)?;
+    Span::current().record("api_key", api_key.name.as_str());
+    if !api_key.allows(scope) {
+        return Err(AppError::forbidden(format!(
+            "API key {} lacks the {} scope",
+            api_key.prefix, scope
+        ))
+        .into_response());
+    }
+    state
+        .api_keys
+        .admit(&api_key)
+        .map_err(IntoResponse::into_response)?;
+
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or(request.uri().path(), MatchedPath::as_str)
+        .to_string();
+    // Counted before the quota is checked, so concurrent requests can't all slip in
+    let used_today = state
+        .api_keys
+        .record(&api_key, &route)
+        .await
+        .map_err(IntoResponse::into_response)?;
+    state
+        .api_keys
+        .check_quota(&api_key, used_today)
+        .map_err(IntoResponse::into_response)?;
+
+    request.extensions_mut().insert(api_key.clone());
+    Ok(Some(api_key))
+}
+
+/// Guards the data routes with a `read` API key. Requests without a key are let
+/// through unless `REQUIRE_API_KEY` is set.
+pub async fn require_api_key(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    match check_api_key(&state, &mut request, Scope::Read).await {
+        Err(response) => response,
+        Ok(None) if state.config.auth.require_api_key => {
+            AppError::unauthorized(format!("Missing {} header", API_KEY_HEADER)).into_response()
+        }
+        Ok(_) => next.run(request).await,
+    }
+}
+
+#[debug_handler]
+pub async fn create_api_key(
+    State(state): State<AppState>,
+    request: Result<Json<CreateApiKey>, JsonRejection>,
+) -> impl IntoResponse {
+    let Json(request) = request?;
+    let res = state.api_keys.create(request).await?;
+    Ok::<_, AppError>((StatusCode::CREATED, Json(res)))
+}
+
+#[debug_handler]
+pub async fn get_api_keys(State(state): State<AppState>) -> impl IntoResponse {
+    let res = state.api_keys.list().await?;
+    Ok::<_, AppError>((StatusCode::OK, Json(res)))
+}
+
+#[debug_handler]
+pub async fn revoke_api_key(
+    State(state): State<AppState>,
+    id: Result<Path<i64>, PathRejection>,
+) -> impl IntoResponse {
+    let Path(id) = id?;
+    let res = state.api_keys.revoke(id).await?;
+    Ok::<_, AppError>((StatusCode::OK, Json(res)))
+}
+
+/// Requests per key, day and route, to see which keys hit which endpoints the most.
+#[debug_handler]
+pub async fn get_api_key_usage(
+    State(state): State<AppState>,
+    params: Result<Query<UsageParams>, QueryRejection>,
+) -> impl IntoResponse {
+    let Query(params) = params?;
+    let res = state.api_keys.usage(params).await?;
+    Ok::<_, AppError>((StatusCode::OK, Json(res)))
+}
@@ -0,0 +1,136 @@
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+
+use crate::{cronjobs::dataset::Dataset, state::AppState};
+
+const HTTP_DATE: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+/// Tables each GET route reads, so its validators change whenever one of them grows.
+fn route_datasets(route: &str) -> &'static [Dataset] {
+    match route {
+        "/depth-history" => &[Dataset::Depths],
+        "/depth-history/synths" => &[Dataset::Depths, Dataset::Swaps],
+        "/earning-history" | "/earning-history/bonding" => &[Dataset::Earnings],
+        "/swap-history" | "/swap-history/breakdown" | "/swap-history/slippage" => &[Dataset::Swaps],
+        "/runepool-history" | "/runepool-history/analytics" => &[Dataset::Runepool],
+        "/rune-price" => &[Dataset::Swaps, Dataset::Earnings, Dataset::Depths],
+        _ => &[],
+    }
+}
+
+/// Adds an `ETag` and `Last-Modified` to the history responses, derived from the
+/// data version of the tables behind the route and a hash of the query, and answers
+/// `304 Not Modified` when the client already has that version.
+pub async fn conditional_get(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let datasets = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or(&[][..], |route| route_datasets(route.as_str()));
+    if request.method() != Method::GET || datasets.is_empty() {
+        return next.run(request).await;
+    }
+
+    let mut versions = Vec::with_capacity(datasets.len());
+    for dataset in datasets {
+        match dataset.data_version(&state).await {
+            Ok(Some(version)) => versions.push(version),
+            // Nothing to validate against, or the handler is about to report the error
+            _ => return next.run(request).await,
+        }
+    }
+    let last_modified = versions
+        .iter()
+        .map(|version| version.last_end_time)
+        .max()
+        .unwrap_or_default();
+
+    let mut hasher = Sha256::new();
+    hasher.update(request.uri().to_string());
+    for version in &versions {
+        hasher.update(version.last_end_time.timestamp_micros().to_be_bytes());
+        hasher.update(version.last_id.to_be_bytes());
+        hasher.update(version.rows.to_be_bytes());
+    }
+    let hash: String = hasher.finalize()[..12]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    // Weak, the same data is served gzip, brotli, zstd or uncompressed
+    let etag = format!("W/\"{}\"", hash);
+
+    let validators = [
+        (header::ETAG, etag.clone()),
+        (
+            header::LAST_MODIFIED,
+            last_modified.format(HTTP_DATE).to_string(),
+        ),
+        (header::CACHE_CONTROL, "no-cache".to_string()),
+    ];
+    let freshness = freshness(request.headers(), &etag, last_modified);
+    if freshness == Freshness::Current {
+        return (StatusCode::NOT_MODIFIED, validators).into_response();
+    }
+
+    let mut response = next.run(request).await;
+    if response.status() != StatusCode::OK {
+        return response;
+    }
+    if freshness == Freshness::CurrentIfValid {
+        return (StatusCode::NOT_MODIFIED, validators).into_response();
+    }
+    for (name, value) in validators {
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            response.headers_mut().insert(name, value);
+        }
+    }
+    response
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Freshness {
+    Stale,
+    /// `If-None-Match` has this ETag, which only a `200` for the same query hands out.
+    Current,
+    /// `If-None-Match: *` or `If-Modified-Since` match, but neither is tied to the query,
+    /// so the handler still has to accept its params before answering `304`.
+    CurrentIfValid,
+}
+
+/// Whether the client's copy is current. `If-None-Match` wins over `If-Modified-Since`
+/// when both are sent.
+fn freshness(headers: &HeaderMap, etag: &str, last_modified: DateTime<Utc>) -> Freshness {
+    if let Some(if_none_match) = headers.get(header::IF_NONE_MATCH) {
+        let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+        let tags: Vec<&str> = if_none_match
+            .to_str()
+            .map(|tags| tags.split(',').map(str::trim).collect())
+            .unwrap_or_default();
+        return if tags.iter().any(|tag| opaque(tag) == opaque(etag)) {
+            Freshness::Current
+        } else if tags.contains(&"*") {
+            Freshness::CurrentIfValid
+        } else {
+            Freshness::Stale
+        };
+    }
+    let not_modified = headers
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|since| since.to_str().ok())
+        .and_then(|since| DateTime::parse_from_rfc2822(since).ok())
+        .is_some_and(|since| last_modified.timestamp() <= since.timestamp());
+    if not_modified {
+        Freshness::CurrentIfValid
+    } else {
+        Freshness::Stale
+    }
+}
@@ -1,21 +1,47 @@
-use axum::{debug_handler, extract::Query, http::StatusCode, response::IntoResponse, Json};
+use axum::{
+    debug_handler,
+    extract::{rejection::QueryRejection, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
 
 use crate::{
-    model::earning_history::QueryParams, service::earning_history_service::EarningHistoryService,
+    error::AppError,
+    model::earning_history::{BondingYieldParams, QueryParams},
+    state::AppState,
 };
 
 #[debug_handler]
-pub async fn get_all_earnings_history(params: Query<QueryParams>) -> impl IntoResponse {
-    let earning_history_service = match EarningHistoryService::new() {
-        Ok(service) => service,
-        Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
-    };
+pub async fn get_all_earnings_history(
+    State(state): State<AppState>,
+    params: Result<Query<QueryParams>, QueryRejection>,
+) -> impl IntoResponse {
+    let params = params?;
+    let res = state
+        .metrics
+        .time_query(
+            "earning_history",
+            "get_all_earnings_history",
+            state.earning_history.get_all_earnings_history(params),
+        )
+        .await?;
+    Ok::<_, AppError>((StatusCode::OK, Json(res)))
+}
 
-    match earning_history_service
-        .get_all_earnings_history(params)
-        .await
-    {
-        Ok(res) => Ok((StatusCode::OK, Json(res))),
-        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
-    }
+#[debug_handler]
+pub async fn get_bonding_yield(
+    State(state): State<AppState>,
+    params: Result<Query<BondingYieldParams>, QueryRejection>,
+) -> impl IntoResponse {
+    let params = params?;
+    let res = state
+        .metrics
+        .time_query(
+            "earning_history",
+            "get_bonding_yield",
+            state.earning_history.get_bonding_yield(&params),
+        )
+        .await?;
+    Ok::<_, AppError>((StatusCode::OK, Json(res)))
 }
@@ -0,0 +1,22 @@
+use axum::{debug_handler, extract::State, http::StatusCode, response::IntoResponse, Json};
+use serde_json::json;
+
+use crate::state::AppState;
+
+/// Liveness: answers as long as the process serves requests.
+#[debug_handler]
+pub async fn get_healthz() -> impl IntoResponse {
+    (StatusCode::OK, Json(json!({ "status": "ok" })))
+}
+
+/// Readiness: `503` with the failed checks while the service can't serve data.
+#[debug_handler]
+pub async fn get_readyz(State(state): State<AppState>) -> impl IntoResponse {
+    let readiness = state.health.readiness(&state).await;
+    let status = if readiness.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(readiness))
+}
@@ -0,0 +1,26 @@
+use axum::{
+    debug_handler,
+    extract::{rejection::JsonRejection, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+
+use crate::{error::AppError, model::lp_simulation::LpSimulationRequest, state::AppState};
+
+#[debug_handler]
+pub async fn simulate_lp_position(
+    State(state): State<AppState>,
+    request: Result<Json<LpSimulationRequest>, JsonRejection>,
+) -> impl IntoResponse {
+    let Json(request) = request?;
+    let res = state
+        .metrics
+        .time_query(
+            "lp_simulator",
+            "simulate",
+            state.lp_simulator.simulate(request),
+        )
+        .await?;
+    Ok::<_, AppError>((StatusCode::OK, Json(res)))
+}
@@ -0,0 +1,36 @@
+use axum::{
+    debug_handler,
+    extract::{MatchedPath, Request, State},
+    http::header,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use prometheus::TEXT_FORMAT;
+use std::time::Instant;
+
+use crate::{error::AppError, state::AppState};
+
+/// Counts every request and its latency under the route it matched, so ids in the
+/// path don't make a series each.
+pub async fn track_http(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let started = Instant::now();
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or("unmatched", MatchedPath::as_str)
+        .to_string();
+
+    let response = next.run(request).await;
+    state
+        .metrics
+        .http_request(&method, &route, response.status(), started);
+    response
+}
+
+#[debug_handler]
+pub async fn get_metrics(State(state): State<AppState>) -> impl IntoResponse {
+    state.metrics.refresh(&state).await;
+    let body = state.metrics.render()?;
+    Ok::<_, AppError>(([(header::CONTENT_TYPE, TEXT_FORMAT)], body))
+}
@@ -1,10 +1,133 @@
+pub mod admin_route;
+pub mod api_key_route;
+pub mod cache_route;
 pub mod earning_history_route;
+pub mod health_route;
+pub mod lp_simulator_route;
+pub mod metrics_route;
 pub mod price_history_route;
 pub mod rune_pool_route;
+pub mod rune_price_route;
+pub mod swap_analytics_route;
 pub mod swap_history_route;
+pub mod synth_analytics_route;
 
-use axum::Json;
-use crate::error::AppError;
+use crate::{config::cors, state::AppState};
+use admin_route::{
+    cancel_sync_job, get_gaps, get_sync_job, get_sync_jobs, get_sync_status, reconcile,
+    repair_gaps, require_admin, trigger_backfill, trigger_sync,
+};
+use api_key_route::{
+    create_api_key, get_api_key_usage, get_api_keys, require_api_key, revoke_api_key,
+};
+use axum::{
+    extract::{MatchedPath, Request},
+    http::HeaderName,
+    middleware,
+    routing::{delete, get, post},
+    Router,
+};
+use cache_route::conditional_get;
+use earning_history_route::{get_all_earnings_history, get_bonding_yield};
+use health_route::{get_healthz, get_readyz};
+use lp_simulator_route::simulate_lp_position;
+use metrics_route::{get_metrics, track_http};
+use price_history_route::get_price_depth_history;
+use rune_pool_route::{get_all_runepools, get_runepool_analytics};
+use rune_price_route::get_rune_price;
+use swap_analytics_route::{get_swap_fee_volume_breakdown, get_swap_slippage_analytics};
+use swap_history_route::get_all_swap_history;
+use synth_analytics_route::get_synth_analytics;
+use tower_http::{
+    compression::CompressionLayer,
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    trace::{DefaultOnResponse, TraceLayer},
+};
+use tracing::{info_span, Level, Span};
 
-pub type Result<T> = std::result::Result<T, AppError>;
-pub type JsonResponse<T> = Result<Json<T>>;
+/// Header carrying the id of a request, taken from the client when it sends one.
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Builds the whole app around `state`.
+pub fn router(state: AppState) -> Router {
+    let admin = Router::new()
+        .route("/admin/gaps", get(get_gaps))
+        .route("/admin/gaps/repair", post(repair_gaps))
+        .route("/admin/api-keys", get(get_api_keys).post(create_api_key))
+        .route("/admin/api-keys/usage", get(get_api_key_usage))
+        .route("/admin/api-keys/{id}", delete(revoke_api_key))
+        .route("/admin/reconcile", post(reconcile))
+        .route("/admin/sync/status", get(get_sync_status))
+        .route("/admin/sync/jobs", get(get_sync_jobs))
+        .route("/admin/sync/jobs/{id}", get(get_sync_job))
+        .route("/admin/sync/jobs/{id}/cancel", post(cancel_sync_job))
+        .route("/admin/sync/{dataset}", post(trigger_sync))
+        .route("/admin/sync/{dataset}/backfill", post(trigger_backfill))
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_admin));
+
+    let data = Router::new()
+        .route("/depth-history", get(get_price_depth_history))
+        .route("/depth-history/synths", get(get_synth_analytics))
+        .route("/earning-history", get(get_all_earnings_history))
+        .route("/earning-history/bonding", get(get_bonding_yield))
+        .route("/swap-history", get(get_all_swap_history))
+        .route(
+            "/swap-history/breakdown",
+            get(get_swap_fee_volume_breakdown),
+        )
+        .route("/swap-history/slippage", get(get_swap_slippage_analytics))
+        .route("/runepool-history", get(get_all_runepools))
+        .route("/runepool-history/analytics", get(get_runepool_analytics))
+        .route("/rune-price", get(get_rune_price))
+        .route("/lp-simulator", post(simulate_lp_position))
+        // Route layers run last to first, the key is checked before any caching
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            conditional_get,
+        ))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            require_api_key,
+        ));
+
+    Router::new()
+        .route("/metrics", get(get_metrics))
+        .route("/healthz", get(get_healthz))
+        .route("/readyz", get(get_readyz))
+        .merge(data)
+        .merge(admin)
+        .layer(middleware::from_fn_with_state(state.clone(), track_http))
+        // Outside the auth middleware, so preflights and rejections carry the CORS headers
+        .layer(cors::layer(&state.config.cors))
+        .layer(CompressionLayer::new())
+        // The last layer runs first, so the id is set before the span records it
+        .layer(PropagateRequestIdLayer::new(REQUEST_ID_HEADER))
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(request_span)
+                .on_response(DefaultOnResponse::new().level(Level::INFO)),
+        )
+        .layer(SetRequestIdLayer::new(REQUEST_ID_HEADER, MakeRequestUuid))
+        .with_state(state)
+}
+
+/// Span of one HTTP request, tagged with its route and request id.
+fn request_span(request: &Request) -> Span {
+    let request_id = request
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|id| id.to_str().ok())
+        .unwrap_or_default();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or(request.uri().path(), MatchedPath::as_str);
+    info_span!(
+        "http_request",
+        method = %request.method(),
+        route,
+        uri = %request.uri(),
+        request_id,
+        api_key = tracing::field::Empty,
+    )
+}
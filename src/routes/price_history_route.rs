@@ -1,18 +1,26 @@
-use axum::{debug_handler, extract::Query, http::StatusCode, response::IntoResponse, Json};
-
-use crate::{
-    model::price_history::PriceHistoryParams, service::price_history_service::PriceHistoryService,
+use axum::{
+    debug_handler,
+    extract::{rejection::QueryRejection, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
 };
 
-#[debug_handler]
-pub async fn get_price_depth_history(params: Query<PriceHistoryParams>) -> impl IntoResponse {
-    let price_history_service = match PriceHistoryService::new() {
-        Ok(service) => service,
-        Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
-    };
+use crate::{error::AppError, model::price_history::PriceHistoryParams, state::AppState};
 
-    match price_history_service.get_all_price_history(params).await {
-        Ok(res) => Ok((StatusCode::OK, Json(res))),
-        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
-    }
+#[debug_handler]
+pub async fn get_price_depth_history(
+    State(state): State<AppState>,
+    params: Result<Query<PriceHistoryParams>, QueryRejection>,
+) -> impl IntoResponse {
+    let params = params?;
+    let res = state
+        .metrics
+        .time_query(
+            "price_history",
+            "get_all_price_history",
+            state.price_history.get_all_price_history(params),
+        )
+        .await?;
+    Ok::<_, AppError>((StatusCode::OK, Json(res)))
 }
@@ -1,15 +1,44 @@
-use axum::{extract::Query, http::StatusCode, response::IntoResponse, Json};
+use axum::{
+    extract::{rejection::QueryRejection, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
 
-use crate::{model::rune_pool::QueryParams, service::run_pool_service::RunePoolService};
+use crate::{
+    error::AppError,
+    model::rune_pool::{QueryParams, RunepoolAnalyticsParams},
+    state::AppState,
+};
 
-pub async fn get_all_runepools(params: Query<QueryParams>) -> impl IntoResponse {
-    let rune_service = match RunePoolService::new() {
-        Ok(service) => service,
-        Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
-    };
+pub async fn get_all_runepools(
+    State(state): State<AppState>,
+    params: Result<Query<QueryParams>, QueryRejection>,
+) -> impl IntoResponse {
+    let params = params?;
+    let res = state
+        .metrics
+        .time_query(
+            "rune_pool",
+            "get_all_runepools",
+            state.rune_pool.get_all_runepools(params),
+        )
+        .await?;
+    Ok::<_, AppError>((StatusCode::OK, Json(res)))
+}
 
-    match rune_service.get_all_runepools(params).await {
-        Ok(res) => Ok((StatusCode::OK, Json(res))),
-        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
-    }
+pub async fn get_runepool_analytics(
+    State(state): State<AppState>,
+    params: Result<Query<RunepoolAnalyticsParams>, QueryRejection>,
+) -> impl IntoResponse {
+    let params = params?;
+    let res = state
+        .metrics
+        .time_query(
+            "rune_pool",
+            "get_runepool_analytics",
+            state.rune_pool.get_runepool_analytics(&params),
+        )
+        .await?;
+    Ok::<_, AppError>((StatusCode::OK, Json(res)))
 }
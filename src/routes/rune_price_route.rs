@@ -0,0 +1,26 @@
+use axum::{
+    debug_handler,
+    extract::{rejection::QueryRejection, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+
+use crate::{error::AppError, model::rune_price::RunePriceParams, state::AppState};
+
+#[debug_handler]
+pub async fn get_rune_price(
+    State(state): State<AppState>,
+    params: Result<Query<RunePriceParams>, QueryRejection>,
+) -> impl IntoResponse {
+    let params = params?;
+    let res = state
+        .metrics
+        .time_query(
+            "rune_price",
+            "get_rune_price",
+            state.rune_price.get_rune_price(&params),
+        )
+        .await?;
+    Ok::<_, AppError>((StatusCode::OK, Json(res)))
+}
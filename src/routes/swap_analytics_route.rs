@@ -0,0 +1,43 @@
+use axum::{
+    debug_handler,
+    extract::{rejection::QueryRejection, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+
+use crate::{error::AppError, model::swap_analytics::SwapAnalyticsParams, state::AppState};
+
+#[debug_handler]
+pub async fn get_swap_fee_volume_breakdown(
+    State(state): State<AppState>,
+    params: Result<Query<SwapAnalyticsParams>, QueryRejection>,
+) -> impl IntoResponse {
+    let params = params?;
+    let res = state
+        .metrics
+        .time_query(
+            "swap_analytics",
+            "get_fee_volume_breakdown",
+            state.swap_analytics.get_fee_volume_breakdown(&params),
+        )
+        .await?;
+    Ok::<_, AppError>((StatusCode::OK, Json(res)))
+}
+
+#[debug_handler]
+pub async fn get_swap_slippage_analytics(
+    State(state): State<AppState>,
+    params: Result<Query<SwapAnalyticsParams>, QueryRejection>,
+) -> impl IntoResponse {
+    let params = params?;
+    let res = state
+        .metrics
+        .time_query(
+            "swap_analytics",
+            "get_slippage_analytics",
+            state.swap_analytics.get_slippage_analytics(&params),
+        )
+        .await?;
+    Ok::<_, AppError>((StatusCode::OK, Json(res)))
+}
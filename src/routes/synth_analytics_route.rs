@@ -0,0 +1,26 @@
+use axum::{
+    debug_handler,
+    extract::{rejection::QueryRejection, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+
+use crate::{error::AppError, model::synth_analytics::SynthAnalyticsParams, state::AppState};
+
+#[debug_handler]
+pub async fn get_synth_analytics(
+    State(state): State<AppState>,
+    params: Result<Query<SynthAnalyticsParams>, QueryRejection>,
+) -> impl IntoResponse {
+    let params = params?;
+    let res = state
+        .metrics
+        .time_query(
+            "synth_analytics",
+            "get_synth_analytics",
+            state.synth_analytics.get_synth_analytics(&params),
+        )
+        .await?;
+    Ok::<_, AppError>((StatusCode::OK, Json(res)))
+}
@@ -0,0 +1,159 @@
+use crate::{
+    config::app::AuthConfig,
+    error::AppError,
+    model::api_key::{ApiKey, ApiKeyUsage, CreateApiKey, CreatedApiKey, UsageParams},
+    repository::api_key_repository::ApiKeyRepository,
+    utils::validation::Validate,
+};
+use chrono::{Days, Utc};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+use tracing::instrument;
+use uuid::Uuid;
+
+/// Characters of a key kept in the clear, enough to tell keys apart in listings.
+const PREFIX_LEN: usize = 10;
+
+/// A request over its key's limits, with how long until it would be let through.
+#[derive(Debug)]
+pub struct Throttled {
+    pub error: AppError,
+    pub retry_after_secs: u64,
+}
+
+/// Token bucket of one key, holding up to a minute's worth of requests.
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+#[derive(Clone)]
+pub struct ApiKeyService {
+    repository: Arc<dyn ApiKeyRepository>,
+    config: AuthConfig,
+    // Rate limits are per replica, quotas are counted in the database
+    buckets: Arc<Mutex<HashMap<i64, Bucket>>>,
+}
+
+impl ApiKeyService {
+    pub fn new(repository: Arc<dyn ApiKeyRepository>, config: AuthConfig) -> Self {
+        Self {
+            repository,
+            config,
+            buckets: Arc::default(),
+        }
+    }
+
+    /// Creates a key and returns it in the clear, it can't be read back afterwards.
+    #[instrument(skip_all)]
+    pub async fn create(&self, request: CreateApiKey) -> Result<CreatedApiKey, AppError> {
+        request.validate()?;
+        let key = format!("mgk_{}", Uuid::new_v4().simple());
+        let api_key = self
+            .repository
+            .create(&request, &key[..PREFIX_LEN], &hash_key(&key))
+            .await?;
+        Ok(CreatedApiKey { key, api_key })
+    }
+
+    pub async fn list(&self) -> Result<Vec<ApiKey>, AppError> {
+        self.repository.list().await
+    }
+
+    pub async fn revoke(&self, id: i64) -> Result<ApiKey, AppError> {
+        let api_key = self
+            .repository
+            .revoke(id)
+            .await?
+            .ok_or_else(|| AppError::not_found(format!("API key {} not found", id)))?;
+        self.buckets
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&id);
+        Ok(api_key)
+    }
+
+    pub async fn usage(&self, params: UsageParams) -> Result<Vec<ApiKeyUsage>, AppError> {
+        params.validate()?;
+        let days = params.days.unwrap_or(7);
+        let since = Utc::now().date_naive() - Days::new(days as u64 - 1);
+        self.repository.usage(since, params.key_id).await
+    }
+
+    /// The active key `key` belongs to.
+    pub async fn authenticate(&self, key: &str) -> Result<ApiKey, AppError> {
+        self.repository
+            .find_active(&hash_key(key))
+            .await?
+            .ok_or_else(|| AppError::unauthorized("Invalid or revoked API key"))
+    }
+
+    /// Takes a request out of the key's rate limit.
+    pub fn admit(&self, api_key: &ApiKey) -> Result<(), Throttled> {
+        let per_minute = api_key
+            .rate_limit_per_minute
+            .map_or(self.config.rate_limit_per_minute as f64, |limit| {
+                limit as f64
+            });
+        let per_second = per_minute / 60.0;
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        let bucket = buckets.entry(api_key.id).or_insert_with(|| Bucket {
+            tokens: per_minute,
+            refilled_at: Instant::now(),
+        });
+
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * per_second).min(per_minute);
+        bucket.refilled_at = now;
+
+        if bucket.tokens < 1.0 {
+            return Err(Throttled {
+                error: AppError::rate_limited(format!(
+                    "Rate limit of {} requests per minute exceeded",
+                    per_minute
+                )),
+                retry_after_secs: ((1.0 - bucket.tokens) / per_second).ceil() as u64,
+            });
+        }
+        bucket.tokens -= 1.0;
+        Ok(())
+    }
+
+    /// Counts a request of the key to `route` towards today's usage, returning the
+    /// requests it made today including this one.
+    pub async fn record(&self, api_key: &ApiKey, route: &str) -> Result<i64, AppError> {
+        self.repository
+            .record_usage(api_key.id, Utc::now().date_naive(), route)
+            .await
+    }
+
+    /// Rejects the request that took the key's `used_today` over its daily quota.
+    pub fn check_quota(&self, api_key: &ApiKey, used_today: i64) -> Result<(), Throttled> {
+        let quota = api_key.daily_quota.or(self.config.daily_quota);
+        if let Some(quota) = quota.filter(|quota| used_today > *quota) {
+            let now = Utc::now();
+            let midnight = (now.date_naive() + Days::new(1))
+                .and_hms_opt(0, 0, 0)
+                .unwrap_or_default()
+                .and_utc();
+            return Err(Throttled {
+                error: AppError::rate_limited(format!("Daily quota of {} requests used up", quota)),
+                retry_after_secs: (midnight - now).num_seconds().max(1) as u64,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Keys are random, so a plain SHA-256 is enough to keep them out of the database.
+fn hash_key(key: &str) -> String {
+    Sha256::digest(key.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
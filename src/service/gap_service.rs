@@ -0,0 +1,90 @@
+use crate::{
+    cronjobs::dataset::Dataset,
+    error::AppError,
+    model::{
+        gap::{DatasetGaps, IntervalGap, IntervalOverlap},
+        sync_run::SyncRange,
+    },
+    repository::Repositories,
+};
+use tracing::instrument;
+
+// Every history table is synced with Midgard's 5min interval
+const INTERVAL_SECONDS: i64 = 300;
+
+#[derive(Clone)]
+pub struct GapService {
+    repositories: Repositories,
+}
+
+impl GapService {
+    pub fn new(repositories: Repositories) -> Self {
+        Self { repositories }
+    }
+
+    /// Walks the dataset's intervals in order and reports every place where an interval
+    /// doesn't start exactly where the previous one ended.
+    #[instrument(skip_all)]
+    pub async fn find_gaps(
+        &self,
+        dataset: Dataset,
+        date_range: &Option<String>,
+    ) -> Result<DatasetGaps, AppError> {
+        let breaks = self
+            .repositories
+            .interval_breaks(dataset, date_range)
+            .await?;
+
+        let mut gaps = DatasetGaps {
+            dataset: dataset.to_string(),
+            pool: dataset.pool().map(str::to_string),
+            intervals: breaks.intervals,
+            first_start_time: breaks.first_start_time,
+            last_end_time: breaks.last_end_time,
+            missing_intervals: 0,
+            gaps: Vec::new(),
+            overlaps: Vec::new(),
+        };
+
+        for interval in breaks.breaks {
+            if interval.start_time > interval.previous_end_time {
+                let missing_seconds =
+                    (interval.start_time - interval.previous_end_time).num_seconds();
+                let missing_intervals = (missing_seconds + INTERVAL_SECONDS - 1) / INTERVAL_SECONDS;
+                gaps.missing_intervals += missing_intervals;
+                gaps.gaps.push(IntervalGap {
+                    from: interval.previous_end_time,
+                    to: interval.start_time,
+                    missing_intervals,
+                });
+            } else {
+                gaps.overlaps.push(IntervalOverlap {
+                    start_time: interval.start_time,
+                    end_time: interval.end_time,
+                    previous_end_time: interval.previous_end_time,
+                    duplicate: interval.start_time == interval.previous_start_time,
+                });
+            }
+        }
+
+        Ok(gaps)
+    }
+
+    /// Ranges a backfill has to re-fetch to fill the dataset's gaps.
+    pub async fn get_repair_ranges(
+        &self,
+        dataset: Dataset,
+        date_range: &Option<String>,
+    ) -> Result<Vec<SyncRange>, AppError> {
+        Ok(self
+            .find_gaps(dataset, date_range)
+            .await?
+            .gaps
+            .iter()
+            .map(|gap| SyncRange {
+                from: gap.from.timestamp(),
+                to: gap.to.timestamp(),
+            })
+            .collect())
+    }
+}
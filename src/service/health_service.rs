@@ -0,0 +1,82 @@
+use crate::{
+    config::database::pending_migrations,
+    cronjobs::dataset::Dataset,
+    model::health::{HealthCheck, Readiness},
+    state::AppState,
+};
+use chrono::Utc;
+use sqlx::PgPool;
+use tracing::instrument;
+
+#[derive(Clone)]
+pub struct HealthService {
+    pool: PgPool,
+    max_data_age_secs: Option<u64>,
+}
+
+impl HealthService {
+    pub fn new(pool: PgPool, max_data_age_secs: Option<u64>) -> Self {
+        Self {
+            pool,
+            max_data_age_secs,
+        }
+    }
+
+    /// Runs every readiness check. The data freshness is only checked when a maximum
+    /// age is configured.
+    #[instrument(skip_all)]
+    pub async fn readiness(&self, state: &AppState) -> Readiness {
+        let mut checks = Vec::new();
+
+        let database = sqlx::query("SELECT 1").execute(&self.pool).await;
+        match database {
+            Ok(_) => {
+                checks.push(HealthCheck::ok("database", None));
+                checks.push(self.check_migrations().await);
+            }
+            Err(e) => {
+                checks.push(HealthCheck::failed("database", e.to_string()));
+                checks.push(HealthCheck::skipped(
+                    "migrations",
+                    "the database is unreachable",
+                ));
+            }
+        }
+
+        if let Some(max_age) = self.max_data_age_secs {
+            for dataset in Dataset::ALL {
+                checks.push(check_freshness(state, dataset, max_age).await);
+            }
+        }
+
+        Readiness::new(checks)
+    }
+
+    async fn check_migrations(&self) -> HealthCheck {
+        match pending_migrations(&self.pool).await {
+            Ok(pending) if pending.is_empty() => HealthCheck::ok("migrations", None),
+            Ok(pending) => {
+                HealthCheck::failed("migrations", format!("pending migrations: {:?}", pending))
+            }
+            Err(e) => HealthCheck::failed("migrations", e.message),
+        }
+    }
+}
+
+async fn check_freshness(state: &AppState, dataset: Dataset, max_age: u64) -> HealthCheck {
+    let name = format!("freshness:{}", dataset);
+    let Some(last) = dataset.last_synced(state).await else {
+        return HealthCheck::failed(name, "no data synced yet");
+    };
+
+    let age = Utc::now().timestamp() - last;
+    let message = format!(
+        "newest interval is {}s old, at most {}s allowed",
+        age, max_age
+    );
+    if age > max_age as i64 {
+        HealthCheck::failed(name, message)
+    } else {
+        HealthCheck::ok(name, Some(message))
+    }
+}
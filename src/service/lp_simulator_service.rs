@@ -0,0 +1,185 @@
+use crate::{
+    client::proxy::DEPTH_POOL,
+    error::AppError,
+    model::lp_simulation::{LpSimulationPoint, LpSimulationRequest, LpSimulationResponse},
+    utils::interval::interval_trunc,
+};
+use chrono::{DateTime, Utc};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use sqlx::{PgPool, Postgres, QueryBuilder, Row};
+use tracing::instrument;
+
+// Depths and deposit amounts are in 1e8 base units
+const BASE_UNITS: f64 = 100_000_000.0;
+
+struct DepthSnapshot {
+    time: DateTime<Utc>,
+    asset_depth: f64,
+    rune_depth: f64,
+    asset_price: f64,
+    asset_price_usd: f64,
+    units: f64,
+}
+
+impl DepthSnapshot {
+    fn from_row(row: &sqlx::postgres::PgRow, time_column: &str) -> Self {
+        Self {
+            time: row.get(time_column),
+            asset_depth: row.get::<i64, _>("asset_depth") as f64,
+            rune_depth: row.get::<i64, _>("rune_depth") as f64,
+            asset_price: row.get::<Decimal, _>("asset_price").to_f64().unwrap_or(0.0),
+            asset_price_usd: row
+                .get::<Decimal, _>("asset_price_usd")
+                .to_f64()
+                .unwrap_or(0.0),
+            units: row.get::<i64, _>("units") as f64,
+        }
+    }
+
+    fn rune_price_usd(&self) -> f64 {
+        if self.asset_price > 0.0 {
+            self.asset_price_usd / self.asset_price
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Replays depth snapshots bucketed in SQL, so it needs Postgres.
+#[derive(Clone)]
+pub struct LpSimulatorService {
+    pool: PgPool,
+}
+
+impl LpSimulatorService {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Replays a hypothetical LP deposit against the stored pool depths and
+    /// reports how the position compares with simply holding the deposit.
+    #[instrument(skip_all)]
+    pub async fn simulate(
+        &self,
+        request: LpSimulationRequest,
+    ) -> Result<LpSimulationResponse, AppError> {
+        let pool = request.pool.unwrap_or_else(|| DEPTH_POOL.to_string());
+        if pool != DEPTH_POOL {
+            return Err(AppError::invalid_field(
+                "pool",
+                format!("Depth history is only available for pool {}", DEPTH_POOL),
+            ));
+        }
+
+        let rune_amount = request.rune_amount.unwrap_or(0);
+        let asset_amount = request.asset_amount.unwrap_or(0);
+        if rune_amount < 0 || asset_amount < 0 || (rune_amount == 0 && asset_amount == 0) {
+            return Err(AppError::invalid_field(
+                "rune_amount",
+                "Deposit needs a positive rune_amount and/or asset_amount",
+            ));
+        }
+
+        let interval = request.interval.unwrap_or_else(|| "day".to_string());
+
+        // The interval that contains the deposit is the entry snapshot
+        let entry_row = sqlx::query(
+            "SELECT start_time, asset_depth, rune_depth, asset_price, asset_price_usd, units \
+             FROM depth_price_history WHERE end_time > $1 ORDER BY start_time ASC LIMIT 1",
+        )
+        .bind(request.deposit_time)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AppError::from(e).context("Failed to fetch entry depth"))?
+        .ok_or_else(|| AppError::not_found("No depth history found after the deposit time"))?;
+        let entry = DepthSnapshot::from_row(&entry_row, "start_time");
+
+        if entry.asset_depth <= 0.0 || entry.rune_depth <= 0.0 || entry.units <= 0.0 {
+            return Err(AppError::unprocessable(
+                "Pool was empty at the deposit time",
+            ));
+        }
+
+        // Last snapshot of every bucket from the entry onwards
+        let mut qb = QueryBuilder::<Postgres>::new("SELECT DISTINCT ON (bucket) ");
+        qb.push(interval_trunc(&interval))
+            .push(
+                " AS bucket, asset_depth, rune_depth, asset_price, asset_price_usd, units \
+                 FROM depth_price_history WHERE start_time >= ",
+            )
+            .push_bind(entry.time)
+            .push(" ORDER BY bucket, start_time DESC");
+
+        let rows = qb
+            .build()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to fetch depth history"))?;
+
+        let r = rune_amount as f64;
+        let a = asset_amount as f64;
+        let lp_units = liquidity_units(entry.units, entry.rune_depth, entry.asset_depth, r, a);
+
+        // Constant product of the position at entry, used to separate fees from price moves
+        let entry_share = lp_units / entry.units;
+        let entry_k = (entry_share * entry.rune_depth) * (entry_share * entry.asset_depth);
+
+        let points = rows
+            .iter()
+            .map(|row| DepthSnapshot::from_row(row, "bucket"))
+            .filter(|snapshot| snapshot.units > 0.0)
+            .map(|snapshot| {
+                let share = lp_units / snapshot.units;
+                let position_rune = share * snapshot.rune_depth;
+                let position_asset = share * snapshot.asset_depth;
+                let position_value_rune = position_rune + position_asset * snapshot.asset_price;
+                let hodl_value_rune = r + a * snapshot.asset_price;
+                let no_fee_value_rune = 2.0 * (entry_k * snapshot.asset_price).sqrt();
+                let fee_income_rune = position_value_rune - no_fee_value_rune;
+                let rune_price_usd = snapshot.rune_price_usd() / BASE_UNITS;
+
+                LpSimulationPoint {
+                    time: snapshot.time,
+                    pool_share: share,
+                    position_rune,
+                    position_asset,
+                    position_value_rune,
+                    position_value_usd: position_value_rune * rune_price_usd,
+                    hodl_value_rune,
+                    hodl_value_usd: hodl_value_rune * rune_price_usd,
+                    impermanent_loss: if hodl_value_rune > 0.0 {
+                        no_fee_value_rune / hodl_value_rune - 1.0
+                    } else {
+                        0.0
+                    },
+                    fee_income_rune,
+                    fee_income_usd: fee_income_rune * rune_price_usd,
+                }
+            })
+            .collect();
+
+        Ok(LpSimulationResponse {
+            pool,
+            deposit_time: request.deposit_time,
+            entry_time: entry.time,
+            interval,
+            rune_amount,
+            asset_amount,
+            lp_units,
+            points,
+        })
+    }
+}
+
+/// THORChain add-liquidity formula including the slip adjustment for asymmetric deposits.
+/// The first deposit into an empty pool gets one unit per rune base unit, as on THORChain.
+pub fn liquidity_units(pool_units: f64, rune_depth: f64, asset_depth: f64, r: f64, a: f64) -> f64 {
+    if pool_units <= 0.0 || rune_depth <= 0.0 || asset_depth <= 0.0 {
+        return r;
+    }
+    let units = pool_units * (rune_depth * a + r * asset_depth) / (2.0 * rune_depth * asset_depth);
+    let slip_adjustment =
+        1.0 - ((rune_depth * a - r * asset_depth) / ((r + rune_depth) * (a + asset_depth))).abs();
+    units * slip_adjustment
+}
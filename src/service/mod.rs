@@ -1,4 +1,13 @@
+pub mod api_key_service;
 pub mod earning_history_service;
+pub mod gap_service;
+pub mod health_service;
+pub mod lp_simulator_service;
 pub mod price_history_service;
+pub mod reconciliation_service;
 pub mod run_pool_service;
+pub mod rune_price_service;
+pub mod swap_analytics_service;
 pub mod swap_history_service;
+pub mod sync_run_service;
+pub mod synth_analytics_service;
@@ -0,0 +1,321 @@
+use crate::{
+    client::proxy,
+    cronjobs::{dataset::Dataset, sync_lock::SyncLock},
+    error::AppError,
+    model::{
+        earning_history::EarningHistory,
+        price_history::PriceHistory,
+        reconciliation::{DatasetReconciliation, FieldMismatch, IntervalMismatch},
+        rune_pool::Runepool,
+        swap_history::SwapHistory,
+        sync_run::{SyncProgress, SyncRange},
+    },
+    repository::Repositories,
+    state::AppState,
+};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{btree_map::Entry, BTreeMap};
+use tracing::{instrument, warn};
+
+// Surrogate keys differ between the two sides by nature
+const IGNORED_FIELDS: [&str; 2] = ["id", "earnings_history_id"];
+
+#[derive(Clone)]
+pub struct ReconciliationService {
+    repositories: Repositories,
+}
+
+impl ReconciliationService {
+    pub fn new(repositories: Repositories) -> Self {
+        Self { repositories }
+    }
+
+    /// Re-fetches the range from Midgard and diffs it field by field against the stored
+    /// rows. With `repair` the missing, mismatching and duplicated intervals are rewritten
+    /// and the unexpected ones deleted.
+    #[instrument(skip_all, fields(dataset = %dataset))]
+    pub async fn reconcile(
+        &self,
+        state: &AppState,
+        dataset: Dataset,
+        range: SyncRange,
+        repair: bool,
+    ) -> Result<DatasetReconciliation, AppError> {
+        if !repair {
+            return self.compare(state, dataset, range, false).await;
+        }
+
+        // Keep syncs of the dataset from writing while rows are being replaced
+        let lock = SyncLock::try_acquire(&state.config.database.url, dataset).await?;
+        let result = self.compare(state, dataset, range, true).await;
+        if let Err(e) = lock.release().await {
+            warn!(dataset = %dataset, error = %e.message, "Failed to release the sync lock");
+        }
+        result
+    }
+
+    async fn compare(
+        &self,
+        state: &AppState,
+        dataset: Dataset,
+        range: SyncRange,
+        repair: bool,
+    ) -> Result<DatasetReconciliation, AppError> {
+        match dataset {
+            Dataset::Depths => {
+                let upstream = proxy::fetch_price_history(state, &range, &SyncProgress::default())
+                    .await?
+                    .into_iter()
+                    .map(PriceHistory::try_from)
+                    .collect::<Result<_, _>>()?;
+                let stored = self
+                    .repositories
+                    .price_history
+                    .find_in_range(&range)
+                    .await?;
+                let (mut report, divergent) =
+                    diff(dataset, range, upstream, stored, |r| r.start_time);
+                if repair {
+                    let repo = &self.repositories.price_history;
+                    if !divergent.is_empty() {
+                        report.repaired = repo.replace_intervals(&divergent).await?.len();
+                    }
+                    if !report.unexpected.is_empty() {
+                        repo.delete_intervals(&report.unexpected).await?;
+                        report.removed = report.unexpected.len();
+                    }
+                }
+                Ok(report)
+            }
+            Dataset::Earnings => {
+                let upstream =
+                    proxy::fetch_earning_history(state, &range, &SyncProgress::default())
+                        .await?
+                        .into_iter()
+                        .map(EarningHistory::try_from)
+                        .collect::<Result<_, _>>()?;
+                let stored = self
+                    .repositories
+                    .earning_history
+                    .find_in_range(&range)
+                    .await?;
+                let (mut report, divergent) =
+                    diff(dataset, range, upstream, stored, |r| r.start_time);
+                if repair {
+                    let repo = &self.repositories.earning_history;
+                    if !divergent.is_empty() {
+                        report.repaired = repo.replace_intervals(&divergent).await?.len();
+                    }
+                    if !report.unexpected.is_empty() {
+                        repo.delete_intervals(&report.unexpected).await?;
+                        report.removed = report.unexpected.len();
+                    }
+                }
+                Ok(report)
+            }
+            Dataset::Swaps => {
+                let upstream = proxy::fetch_swap_history(state, &range, &SyncProgress::default())
+                    .await?
+                    .into_iter()
+                    .map(SwapHistory::try_from)
+                    .collect::<Result<_, _>>()?;
+                let stored = self.repositories.swap_history.find_in_range(&range).await?;
+                let (mut report, divergent) =
+                    diff(dataset, range, upstream, stored, |r| r.start_time);
+                if repair {
+                    let repo = &self.repositories.swap_history;
+                    if !divergent.is_empty() {
+                        report.repaired = repo.replace_intervals(&divergent).await?.len();
+                    }
+                    if !report.unexpected.is_empty() {
+                        repo.delete_intervals(&report.unexpected).await?;
+                        report.removed = report.unexpected.len();
+                    }
+                }
+                Ok(report)
+            }
+            Dataset::Runepool => {
+                let upstream =
+                    proxy::fetch_runepool_history(state, &range, &SyncProgress::default())
+                        .await?
+                        .into_iter()
+                        .map(Runepool::try_from)
+                        .collect::<Result<_, _>>()?;
+                let stored = self.repositories.rune_pool.find_in_range(&range).await?;
+                let (mut report, divergent) =
+                    diff(dataset, range, upstream, stored, |r| r.start_time);
+                if repair {
+                    let repo = &self.repositories.rune_pool;
+                    if !divergent.is_empty() {
+                        report.repaired = repo.replace_intervals(&divergent).await?.len();
+                    }
+                    if !report.unexpected.is_empty() {
+                        repo.delete_intervals(&report.unexpected).await?;
+                        report.removed = report.unexpected.len();
+                    }
+                }
+                Ok(report)
+            }
+        }
+    }
+}
+
+/// Compares both sides interval by interval and returns the report together with the
+/// upstream rows that have to be written to make the stored data match.
+fn diff<T: Serialize>(
+    dataset: Dataset,
+    range: SyncRange,
+    upstream: Vec<T>,
+    stored: Vec<T>,
+    start_time: impl Fn(&T) -> DateTime<Utc>,
+) -> (DatasetReconciliation, Vec<T>) {
+    // The last Midgard page usually runs past the end of the range
+    let upstream: Vec<T> = upstream
+        .into_iter()
+        .filter(|row| {
+            let start = start_time(row).timestamp();
+            start >= range.from && start < range.to
+        })
+        .collect();
+
+    let mut report = DatasetReconciliation {
+        dataset: dataset.to_string(),
+        pool: dataset.pool().map(str::to_string),
+        range,
+        upstream_intervals: upstream.len(),
+        stored_intervals: stored.len(),
+        matched: 0,
+        missing: Vec::new(),
+        unexpected: Vec::new(),
+        duplicates: Vec::new(),
+        mismatches: Vec::new(),
+        repaired: 0,
+        removed: 0,
+    };
+
+    let mut stored_by_start: BTreeMap<DateTime<Utc>, T> = BTreeMap::new();
+    for row in stored {
+        let start = start_time(&row);
+        match stored_by_start.entry(start) {
+            Entry::Vacant(entry) => {
+                entry.insert(row);
+            }
+            // Stored rows come sorted by start time, so copies are next to each other
+            Entry::Occupied(_) if report.duplicates.last() != Some(&start) => {
+                report.duplicates.push(start);
+            }
+            Entry::Occupied(_) => {}
+        }
+    }
+
+    let mut divergent = Vec::new();
+    for row in upstream {
+        let start = start_time(&row);
+        let Some(stored) = stored_by_start.remove(&start) else {
+            report.missing.push(start);
+            divergent.push(row);
+            continue;
+        };
+
+        let fields = diff_fields(&flatten(&stored), &flatten(&row));
+        let duplicated = report.duplicates.contains(&start);
+        if fields.is_empty() && !duplicated {
+            report.matched += 1;
+            continue;
+        }
+        if !fields.is_empty() {
+            report.mismatches.push(IntervalMismatch {
+                start_time: start,
+                fields,
+            });
+        }
+        // Rewriting the interval also collapses its duplicates into one row
+        divergent.push(row);
+    }
+    report.unexpected = stored_by_start.into_keys().collect();
+
+    (report, divergent)
+}
+
+fn diff_fields(
+    stored: &BTreeMap<String, Value>,
+    upstream: &BTreeMap<String, Value>,
+) -> Vec<FieldMismatch> {
+    let mut fields: Vec<&String> = stored.keys().chain(upstream.keys()).collect();
+    fields.sort();
+    fields.dedup();
+
+    fields
+        .into_iter()
+        .filter_map(|field| {
+            let stored = stored.get(field).cloned().unwrap_or(Value::Null);
+            let upstream = upstream.get(field).cloned().unwrap_or(Value::Null);
+            (!values_match(&stored, &upstream)).then(|| FieldMismatch {
+                field: field.clone(),
+                stored,
+                upstream,
+            })
+        })
+        .collect()
+}
+
+/// Decimals are serialized as strings, so `"1.50"` and `"1.5"` are the same value.
+fn values_match(a: &Value, b: &Value) -> bool {
+    if a == b {
+        return true;
+    }
+    match (a, b) {
+        (Value::String(a), Value::String(b)) => {
+            matches!((a.parse::<Decimal>(), b.parse::<Decimal>()), (Ok(a), Ok(b)) if a == b)
+        }
+        _ => false,
+    }
+}
+
+/// Flattens a model into `field -> value`, with nested pools keyed by their pool name,
+/// e.g. `pools.BTC.BTC.earnings`.
+fn flatten<T: Serialize>(model: &T) -> BTreeMap<String, Value> {
+    let mut fields = BTreeMap::new();
+    flatten_into(
+        "",
+        serde_json::to_value(model).unwrap_or(Value::Null),
+        &mut fields,
+    );
+    fields
+}
+
+fn flatten_into(prefix: &str, value: Value, fields: &mut BTreeMap<String, Value>) {
+    let key = |name: &str| {
+        if prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}.{}", prefix, name)
+        }
+    };
+
+    match value {
+        Value::Object(map) => {
+            for (name, value) in map {
+                if !IGNORED_FIELDS.contains(&name.as_str()) {
+                    flatten_into(&key(&name), value, fields);
+                }
+            }
+        }
+        Value::Array(items) => {
+            for (idx, item) in items.into_iter().enumerate() {
+                let name = item
+                    .get("pool")
+                    .and_then(Value::as_str)
+                    .map(str::to_string)
+                    .unwrap_or_else(|| idx.to_string());
+                flatten_into(&key(&name), item, fields);
+            }
+        }
+        value => {
+            fields.insert(prefix.to_string(), value);
+        }
+    }
+}
@@ -1,218 +1,118 @@
 use crate::{
-    config::database::get_pool,
     error::AppError,
-    model::rune_pool::{QueryParams, Runepool},
+    model::{
+        data_version::DataVersion,
+        rune_pool::{QueryParams, Runepool, RunepoolAnalyticsBucket, RunepoolAnalyticsParams},
+        rune_price::RunePriceParams,
+    },
+    repository::run_pool_repository::RunePoolRepository,
+    service::rune_price_service::RunePriceService,
+    utils::{
+        interval::interval_trunc,
+        query::{push_date_range, relative_change},
+        validation::Validate,
+    },
 };
 use axum::extract::Query;
-use sqlx::{Execute, PgPool, Postgres, QueryBuilder, Row};
-
-pub struct RunePoolService<'a> {
-    pool: &'a PgPool,
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, QueryBuilder, Row};
+use std::{collections::HashMap, sync::Arc};
+use tracing::instrument;
+
+#[derive(Clone)]
+pub struct RunePoolService {
+    // The RUNEPool analytics aggregates in SQL and only runs against Postgres
+    pool: PgPool,
+    repository: Arc<dyn RunePoolRepository>,
+    rune_price_service: RunePriceService,
 }
 
-impl<'a> RunePoolService<'a> {
-    pub fn new() -> Result<Self, AppError> {
-        println!("📊 Initializing RunePoolService");
-        Ok(Self {
-            pool: get_pool().map_err(|e| {
-                AppError::new(format!("Failed to initialize database pool: {:#?}", e))
-            })?,
-        })
+impl RunePoolService {
+    pub fn new(pool: PgPool, repository: Arc<dyn RunePoolRepository>) -> Self {
+        Self {
+            rune_price_service: RunePriceService::new(pool.clone()),
+            pool,
+            repository,
+        }
     }
 
-    pub async fn get_last_update_timestamp(&self) -> Result<i64, AppError> {
-        let record = sqlx::query(
-            "SELECT start_time FROM rune_pool_history ORDER BY start_time DESC LIMIT 1",
-        )
-        .fetch_one(self.pool)
-        .await
-        .map_err(|e| AppError::new(format!("Failed to get last timestamp: {}", e)))?;
+    pub async fn get_last_update_timestamp(&self) -> Result<Option<i64>, AppError> {
+        self.repository.last_timestamp().await
+    }
 
-        Ok(record
-            .get::<chrono::DateTime<chrono::Utc>, _>("start_time")
-            .timestamp())
+    pub async fn get_data_version(&self) -> Result<Option<DataVersion>, AppError> {
+        self.repository.data_version().await
     }
 
     pub async fn get_all_runepools(
         &self,
         params: Query<QueryParams>,
     ) -> Result<Vec<Runepool>, AppError> {
-        println!("🔍 Fetching rune pools with params: {:?}", params);
-        let mut qb = QueryBuilder::<Postgres>::new("SELECT * FROM rune_pool_history WHERE true");
-
-        // Interval filter
-        if let Some(interval) = &params.interval {
-            let interval_trunc = match interval.as_str() {
-                "5min" => "date_trunc('minute', start_time) + INTERVAL '5 minutes' * (EXTRACT(MINUTE FROM start_time)::int / 5)",
-                "hour" => "date_trunc('hour', start_time)",
-                "day" => "date_trunc('day', start_time)",
-                "week" => "date_trunc('week', start_time)",
-                "month" => "date_trunc('month', start_time)",
-                "quarter" => "date_trunc('quarter', start_time)",
-                "year" => "date_trunc('year', start_time)",
-                _ => "date_trunc('hour', start_time)", // Default to hourly
-            };
-
-            qb.push(" AND start_time IN (");
-            qb.push("SELECT DISTINCT ON (")
-                .push(interval_trunc)
-                .push(") start_time FROM rune_pool_history WHERE true");
-            qb.push(")");
-        }
-
-        // Date range filter
-        if let Some(date_range) = &params.date_range {
-            let dates: Vec<&str> = date_range.split(',').collect();
-            if dates.len() == 2 {
-                qb.push(" AND start_time >= ")
-                    .push("TO_TIMESTAMP(")
-                    .push_bind(dates[0])
-                    .push(", 'YYYY-MM-DD')")
-                    .push(" AND end_time <= ")
-                    .push("TO_TIMESTAMP(")
-                    .push_bind(dates[1])
-                    .push(", 'YYYY-MM-DD')");
-            }
-        }
-
-        // Units filters
-        if let Some(units_gt) = params.units_gt {
-            qb.push(" AND units > ").push_bind(units_gt);
-        }
-        if let Some(units_lt) = params.units_lt {
-            qb.push(" AND units < ").push_bind(units_lt);
-        }
-        if let Some(units_eq) = params.units_eq {
-            qb.push(" AND units = ").push_bind(units_eq);
-        }
-
-        // Sorting
-        if let Some(sort_by) = &params.sort_by {
-            qb.push(" ORDER BY ").push(sort_by);
-
-            if let Some(order) = &params.order {
-                match order.to_lowercase().as_str() {
-                    "asc" => qb.push(" ASC"),
-                    "desc" => qb.push(" DESC"),
-                    _ => qb.push(" ASC"), // default to ascending
-                };
-            }
-        }
-
-        // Pagination and limits
-        if let Some(count) = params.count {
-            qb.push(" LIMIT ").push_bind(count);
-        } else if let Some(limit) = params.limit {
-            qb.push(" LIMIT ").push_bind(limit);
-
-            if let Some(page) = params.page {
-                let offset = (page as i64).saturating_sub(1) * limit as i64;
-                qb.push(" OFFSET ").push_bind(offset);
-            }
-        }
-
-        let query = qb.build_query_as::<Runepool>();
-        println!("🔎 Executing SQL Query: {}", query.sql());
-        let result = query
-            .fetch_all(self.pool)
-            .await
-            .map_err(|e| AppError::new(format!("Failed to fetch rune pools: {}", e)))?;
-
-        println!("✅ Found {} records", result.len());
-        Ok(result)
+        params.validate()?;
+        self.repository.query(&params).await
     }
 
-    pub async fn save(&self, rune_pool: &Runepool) -> Result<i32, AppError> {
-        println!("💾 Saving single rune pool record");
-        let result = sqlx::query(
-            r#"
-                INSERT INTO rune_pool_history (
-                    start_time, end_time, count, units
-                )
-                VALUES ($1, $2, $3, $4)
-                RETURNING id
-            "#,
-        )
-        .bind(&rune_pool.start_time)
-        .bind(&rune_pool.end_time)
-        .bind(&rune_pool.count)
-        .bind(&rune_pool.units)
-        .fetch_one(self.pool)
-        .await
-        .map_err(|e| AppError::new(format!("Failed to save rune pool: {}", e)))?;
-
-        let id = result.get::<i32, _>("id");
-        println!("✅ Saved record with ID: {}", id);
-        Ok(id)
-    }
-
-    pub async fn save_batch(&self, rune_pools: &[Runepool]) -> Result<Vec<i32>, AppError> {
-        if rune_pools.is_empty() {
-            return Err(AppError::new("No rune pools provided for batch save"));
-        }
-
-        println!("📦 Batch saving {} rune pool records", rune_pools.len());
-
-        let mut tx = self
-            .pool
-            .begin()
-            .await
-            .map_err(|e| AppError::new(format!("Failed to start transaction: {}", e)))?;
-
-        let copy = String::from(
-            "COPY rune_pool_history (start_time, end_time, count, units) \
-             FROM STDIN WITH (FORMAT text, DELIMITER '\t')",
-        );
-
-        let mut writer = tx
-            .copy_in_raw(&copy)
+    /// Member and unit growth between the closing snapshots of consecutive buckets, next
+    /// to the reconciled RUNE price. Midgard's RUNEPool history has units but no depth,
+    /// so deposits and PnL can't be valued in RUNE and aren't reported.
+    #[instrument(skip_all)]
+    pub async fn get_runepool_analytics(
+        &self,
+        params: &RunepoolAnalyticsParams,
+    ) -> Result<Vec<RunepoolAnalyticsBucket>, AppError> {
+        params.validate()?;
+        let interval = params.interval.as_deref().unwrap_or("day");
+        let mut qb = QueryBuilder::<Postgres>::new("SELECT DISTINCT ON (bucket) ");
+        qb.push(interval_trunc(interval))
+            .push(" AS bucket, count, units FROM rune_pool_history WHERE true");
+        push_date_range(&mut qb, &params.date_range);
+        qb.push(" ORDER BY bucket, start_time DESC");
+
+        let query = qb.build();
+        let rows = query
+            .fetch_all(&self.pool)
             .await
-            .map_err(|e| AppError::new(format!("Failed to initialize batch write: {}", e)))?;
-
-        // Process in chunks of 5000 records
-        for (chunk_idx, chunk) in rune_pools.chunks(5000).enumerate() {
-            println!(
-                "Processing chunk {} of {}",
-                chunk_idx + 1,
-                (rune_pools.len() + 4999) / 5000
-            );
-            let mut batch_data = String::with_capacity(chunk.len() * 256);
-
-            for rune_pool in chunk {
-                batch_data.push_str(&format!(
-                    "{}\t{}\t{}\t{}\n",
-                    rune_pool.start_time.format("%Y-%m-%d %H:%M:%S UTC"),
-                    rune_pool.end_time.format("%Y-%m-%d %H:%M:%S UTC"),
-                    rune_pool.count,
-                    rune_pool.units
-                ));
-            }
-
-            writer
-                .send(batch_data.as_bytes())
-                .await
-                .map_err(|e| AppError::new(format!("Failed to write batch data: {}", e)))?;
+            .map_err(|e| AppError::from(e).context("Failed to fetch runepool analytics"))?;
+
+        let rune_prices: HashMap<DateTime<Utc>, f64> = self
+            .rune_price_service
+            .get_rune_price(&RunePriceParams {
+                interval: Some(interval.to_string()),
+                date_range: params.date_range.clone(),
+                threshold: None,
+            })
+            .await?
+            .into_iter()
+            .map(|point| (point.start_time, point.close))
+            .collect();
+
+        let mut buckets: Vec<RunepoolAnalyticsBucket> = Vec::with_capacity(rows.len());
+        for row in rows {
+            let start_time: DateTime<Utc> = row.get("bucket");
+            let count: i64 = row.get("count");
+            let units: i64 = row.get("units");
+            let previous = buckets.last();
+
+            buckets.push(RunepoolAnalyticsBucket {
+                start_time,
+                count,
+                units,
+                member_change: previous.map_or(0, |p| count - p.count),
+                member_growth: relative_change(previous.map(|p| p.count as f64), count as f64),
+                units_change: previous.map_or(0, |p| units - p.units),
+                average_units_per_member: if count > 0 {
+                    units as f64 / count as f64
+                } else {
+                    0.0
+                },
+                rune_price_usd: rune_prices.get(&start_time).copied(),
+            });
         }
 
-        writer
-            .finish()
-            .await
-            .map_err(|e| AppError::new(format!("Failed to finish batch write: {}", e)))?;
-
-        let ids = sqlx::query_as::<_, (i32,)>(
-            "SELECT id FROM rune_pool_history ORDER BY id DESC LIMIT $1",
-        )
-        .bind(rune_pools.len() as i32)
-        .fetch_all(&mut *tx)
-        .await
-        .map_err(|e| AppError::new(format!("Failed to retrieve inserted IDs: {}", e)))?;
-
-        tx.commit()
-            .await
-            .map_err(|e| AppError::new(format!("Failed to commit transaction: {}", e)))?;
+        Ok(buckets)
+    }
 
-        let ids = ids.into_iter().map(|(id,)| id).collect();
-        println!("🎉 Successfully saved {} records", rune_pools.len());
-        Ok(ids)
+    pub async fn save_batch(&self, rune_pools: &[Runepool]) -> Result<Vec<i32>, AppError> {
+        self.repository.save_batch(rune_pools).await
     }
 }
@@ -0,0 +1,137 @@
+use crate::{
+    error::AppError,
+    model::rune_price::{RunePriceParams, RunePricePoint, RunePriceSource, RunePriceSourceCounts},
+    utils::{interval::interval_trunc, query::push_date_range, validation::Validate},
+};
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, QueryBuilder, Row};
+use tracing::instrument;
+
+const DEFAULT_DISAGREEMENT_THRESHOLD: f64 = 0.01;
+
+/// Reconciles the RUNE price of every stored source in SQL, so it needs Postgres.
+#[derive(Clone)]
+pub struct RunePriceService {
+    pool: PgPool,
+}
+
+impl RunePriceService {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Builds a RUNE/USD series from `swap_history`, `earnings_history` and the price
+    /// implied by `depth_price_history`, preferring the sources in that order.
+    #[instrument(skip_all)]
+    pub async fn get_rune_price(
+        &self,
+        params: &RunePriceParams,
+    ) -> Result<Vec<RunePricePoint>, AppError> {
+        params.validate()?;
+        let threshold = params.threshold.unwrap_or(DEFAULT_DISAGREEMENT_THRESHOLD);
+
+        let mut qb = QueryBuilder::<Postgres>::new(
+            "WITH s AS (SELECT start_time, rune_price_usd AS price FROM swap_history \
+             WHERE rune_price_usd > 0",
+        );
+        push_date_range(&mut qb, &params.date_range);
+        qb.push(
+            "), e AS (SELECT start_time, rune_price_usd AS price FROM earnings_history \
+             WHERE rune_price_usd > 0",
+        );
+        push_date_range(&mut qb, &params.date_range);
+        qb.push(
+            "), d AS (SELECT start_time, asset_price_usd / asset_price AS price \
+             FROM depth_price_history WHERE asset_price > 0",
+        );
+        push_date_range(&mut qb, &params.date_range);
+        qb.push(
+            "), prices AS (SELECT COALESCE(s.start_time, e.start_time, d.start_time) AS start_time, \
+             s.price::FLOAT8 AS swaps_price, e.price::FLOAT8 AS earnings_price, \
+             d.price::FLOAT8 AS depths_price \
+             FROM s FULL JOIN e ON e.start_time = s.start_time \
+             FULL JOIN d ON d.start_time = COALESCE(s.start_time, e.start_time)) \
+             SELECT ",
+        )
+        .push(interval_trunc(params.interval.as_deref().unwrap_or("day")))
+        .push(" AS bucket, swaps_price, earnings_price, depths_price FROM prices ORDER BY start_time");
+
+        let query = qb.build();
+        let rows = query
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to fetch rune price"))?;
+
+        let mut points: Vec<RunePricePoint> = Vec::new();
+        for row in rows {
+            let bucket: DateTime<Utc> = row.get("bucket");
+            let candidates = [
+                (
+                    RunePriceSource::Swaps,
+                    row.get::<Option<f64>, _>("swaps_price"),
+                ),
+                (
+                    RunePriceSource::Earnings,
+                    row.get::<Option<f64>, _>("earnings_price"),
+                ),
+                (
+                    RunePriceSource::Depths,
+                    row.get::<Option<f64>, _>("depths_price"),
+                ),
+            ];
+            let available: Vec<(RunePriceSource, f64)> = candidates
+                .iter()
+                .filter_map(|(source, price)| price.map(|p| (*source, p)))
+                .collect();
+            let Some(&(source, price)) = available.first() else {
+                continue;
+            };
+            let divergence = divergence(&available);
+
+            if points.last().map(|p| p.start_time) != Some(bucket) {
+                points.push(RunePricePoint {
+                    start_time: bucket,
+                    open: price,
+                    high: price,
+                    low: price,
+                    close: price,
+                    source,
+                    source_counts: RunePriceSourceCounts::default(),
+                    max_divergence: None,
+                    disagreement_count: 0,
+                    disagreement: false,
+                });
+            }
+
+            let point = points.last_mut().expect("bucket was just pushed");
+            point.high = point.high.max(price);
+            point.low = point.low.min(price);
+            point.close = price;
+            point.source = source;
+            match source {
+                RunePriceSource::Swaps => point.source_counts.swaps += 1,
+                RunePriceSource::Earnings => point.source_counts.earnings += 1,
+                RunePriceSource::Depths => point.source_counts.depths += 1,
+            }
+            if let Some(divergence) = divergence {
+                point.max_divergence = Some(point.max_divergence.unwrap_or(0.0).max(divergence));
+                if divergence > threshold {
+                    point.disagreement_count += 1;
+                    point.disagreement = true;
+                }
+            }
+        }
+
+        Ok(points)
+    }
+}
+
+/// Relative spread between the highest and lowest price reported for one interval.
+fn divergence(prices: &[(RunePriceSource, f64)]) -> Option<f64> {
+    if prices.len() < 2 {
+        return None;
+    }
+    let high = prices.iter().map(|(_, p)| *p).fold(f64::MIN, f64::max);
+    let low = prices.iter().map(|(_, p)| *p).fold(f64::MAX, f64::min);
+    (low > 0.0).then(|| (high - low) / low)
+}
@@ -0,0 +1,176 @@
+use crate::{
+    error::AppError,
+    model::swap_analytics::{
+        CategorySlippage, SlippageAnalytics, SlippageBucket, SwapAnalyticsParams,
+        SwapBreakdownBucket, SwapCategoryBreakdown, SWAP_CATEGORIES,
+    },
+    utils::{
+        interval::interval_trunc,
+        query::{push_date_range, ratio, relative_change},
+        validation::Validate,
+    },
+};
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use sqlx::{PgPool, Postgres, QueryBuilder, Row};
+use tracing::instrument;
+
+// Swap fees and volumes are reported by Midgard in 1e8 RUNE base units
+const RUNE_BASE_UNITS: f64 = 100_000_000.0;
+
+/// Fee, volume and slippage analytics aggregated in SQL over `swap_history`, so
+/// unlike the history endpoints they need Postgres.
+#[derive(Clone)]
+pub struct SwapAnalyticsService {
+    pool: PgPool,
+}
+
+impl SwapAnalyticsService {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    #[instrument(skip_all)]
+    pub async fn get_fee_volume_breakdown(
+        &self,
+        params: &SwapAnalyticsParams,
+    ) -> Result<Vec<SwapBreakdownBucket>, AppError> {
+        params.validate()?;
+        let interval = params.interval.as_deref().unwrap_or("day");
+        let mut qb = QueryBuilder::<Postgres>::new("SELECT ");
+        qb.push(interval_trunc(interval)).push(" AS bucket");
+
+        for category in SWAP_CATEGORIES {
+            qb.push(format!(
+                ", SUM({c}_count)::BIGINT AS {c}_count, SUM({c}_fees)::BIGINT AS {c}_fees, \
+                 SUM({c}_volume)::BIGINT AS {c}_volume, \
+                 SUM({c}_fees * rune_price_usd) AS {c}_fees_usd",
+                c = category
+            ));
+        }
+        qb.push(
+            ", SUM(total_count)::BIGINT AS total_count, SUM(total_fees)::BIGINT AS total_fees, \
+             SUM(total_volume)::BIGINT AS total_volume, \
+             SUM(total_fees * rune_price_usd) AS total_fees_usd \
+             FROM swap_history WHERE true",
+        );
+        push_date_range(&mut qb, &params.date_range);
+        qb.push(" GROUP BY bucket ORDER BY bucket");
+
+        let query = qb.build();
+        let rows = query
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to fetch swap breakdown"))?;
+
+        let mut buckets: Vec<SwapBreakdownBucket> = Vec::with_capacity(rows.len());
+        for row in rows {
+            let total_count: i64 = row.get("total_count");
+            let total_fees: i64 = row.get("total_fees");
+            let total_volume: i64 = row.get("total_volume");
+            let fee_revenue_usd = usd(row.get("total_fees_usd"));
+
+            let categories = SWAP_CATEGORIES
+                .iter()
+                .map(|category| {
+                    let fees: i64 = row.get(format!("{}_fees", category).as_str());
+                    let volume: i64 = row.get(format!("{}_volume", category).as_str());
+                    SwapCategoryBreakdown {
+                        category: category.to_string(),
+                        count: row.get(format!("{}_count", category).as_str()),
+                        fees,
+                        volume,
+                        fee_share: ratio(fees, total_fees),
+                        volume_share: ratio(volume, total_volume),
+                        effective_fee_rate: ratio(fees, volume),
+                        fee_revenue_usd: usd(row.get(format!("{}_fees_usd", category).as_str())),
+                    }
+                })
+                .collect();
+
+            let previous = buckets.last();
+            buckets.push(SwapBreakdownBucket {
+                start_time: row.get("bucket"),
+                total_count,
+                total_fees,
+                total_volume,
+                effective_fee_rate: ratio(total_fees, total_volume),
+                fee_revenue_usd,
+                fees_change: relative_change(
+                    previous.map(|p| p.total_fees as f64),
+                    total_fees as f64,
+                ),
+                volume_change: relative_change(
+                    previous.map(|p| p.total_volume as f64),
+                    total_volume as f64,
+                ),
+                fee_revenue_usd_change: relative_change(
+                    previous.map(|p| p.fee_revenue_usd),
+                    fee_revenue_usd,
+                ),
+                categories,
+            });
+        }
+
+        Ok(buckets)
+    }
+
+    #[instrument(skip_all)]
+    pub async fn get_slippage_analytics(
+        &self,
+        params: &SwapAnalyticsParams,
+    ) -> Result<SlippageAnalytics, AppError> {
+        params.validate()?;
+        let interval = params.interval.as_deref().unwrap_or("day");
+        let mut qb = QueryBuilder::<Postgres>::new("SELECT ");
+        qb.push(interval_trunc(interval)).push(
+            " AS bucket, SUM(total_count)::BIGINT AS total_count, \
+             (SUM(average_slip * total_count) / NULLIF(SUM(total_count), 0))::FLOAT8 AS average_slip",
+        );
+
+        for category in SWAP_CATEGORIES {
+            qb.push(format!(
+                ", SUM({c}_count)::BIGINT AS {c}_count, \
+                 (SUM({c}_average_slip * {c}_count) / NULLIF(SUM({c}_count), 0))::FLOAT8 AS {c}_slip, \
+                 percentile_cont(0.5) WITHIN GROUP (ORDER BY {c}_average_slip::FLOAT8) FILTER (WHERE {c}_count > 0) AS {c}_p50, \
+                 percentile_cont(0.9) WITHIN GROUP (ORDER BY {c}_average_slip::FLOAT8) FILTER (WHERE {c}_count > 0) AS {c}_p90, \
+                 percentile_cont(0.99) WITHIN GROUP (ORDER BY {c}_average_slip::FLOAT8) FILTER (WHERE {c}_count > 0) AS {c}_p99",
+                c = category
+            ));
+        }
+        qb.push(" FROM swap_history WHERE true");
+        push_date_range(&mut qb, &params.date_range);
+        qb.push(" GROUP BY bucket ORDER BY bucket");
+
+        let query = qb.build();
+        let rows = query
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to fetch slippage analytics"))?;
+
+        let buckets = rows
+            .iter()
+            .map(|row| SlippageBucket {
+                start_time: row.get("bucket"),
+                total_count: row.get("total_count"),
+                weighted_average_slip: row.get("average_slip"),
+                categories: SWAP_CATEGORIES
+                    .iter()
+                    .map(|category| CategorySlippage {
+                        category: category.to_string(),
+                        count: row.get(format!("{}_count", category).as_str()),
+                        weighted_average_slip: row.get(format!("{}_slip", category).as_str()),
+                        p50_slip: row.get(format!("{}_p50", category).as_str()),
+                        p90_slip: row.get(format!("{}_p90", category).as_str()),
+                        p99_slip: row.get(format!("{}_p99", category).as_str()),
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        Ok(SlippageAnalytics { buckets })
+    }
+}
+
+fn usd(fees_times_price: Option<Decimal>) -> f64 {
+    fees_times_price.and_then(|v| v.to_f64()).unwrap_or(0.0) / RUNE_BASE_UNITS
+}
@@ -0,0 +1,144 @@
+use crate::{
+    cronjobs::dataset::Dataset,
+    error::AppError,
+    model::sync_run::{DatasetSyncStatus, SyncRange, SyncReport, SyncRun},
+    repository::Repositories,
+};
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Row};
+use std::collections::HashSet;
+
+pub const STATUS_RUNNING: &str = "running";
+pub const STATUS_SUCCEEDED: &str = "succeeded";
+pub const STATUS_FAILED: &str = "failed";
+pub const STATUS_CANCELLED: &str = "cancelled";
+
+#[derive(Clone)]
+pub struct SyncRunService {
+    pool: PgPool,
+    repositories: Repositories,
+}
+
+impl SyncRunService {
+    pub fn new(pool: PgPool, repositories: Repositories) -> Self {
+        Self { pool, repositories }
+    }
+
+    pub async fn start(
+        &self,
+        dataset: Dataset,
+        provider: &str,
+        range: &SyncRange,
+    ) -> Result<i32, AppError> {
+        let record = sqlx::query(
+            r#"
+            INSERT INTO sync_runs (dataset, pool, provider, status, started_at, range_from, range_to)
+            VALUES ($1, $2, $3, $4, NOW(), $5, $6)
+            RETURNING id
+            "#,
+        )
+        .bind(dataset.as_str())
+        .bind(dataset.pool())
+        .bind(provider)
+        .bind(STATUS_RUNNING)
+        .bind(DateTime::from_timestamp(range.from, 0))
+        .bind(DateTime::from_timestamp(range.to, 0))
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| AppError::from(e).context("Failed to record sync run"))?;
+
+        Ok(record.get::<i32, _>("id"))
+    }
+
+    pub async fn finish(
+        &self,
+        run_id: i32,
+        status: &str,
+        report: &SyncReport,
+        error: Option<&AppError>,
+    ) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            UPDATE sync_runs
+            SET status = $2, finished_at = NOW(), pages_fetched = $3,
+                rows_inserted = $4, rows_skipped = $5, error_message = $6
+            WHERE id = $1
+            "#,
+        )
+        .bind(run_id)
+        .bind(status)
+        .bind(report.pages_fetched)
+        .bind(report.rows_inserted)
+        .bind(report.rows_skipped)
+        .bind(error.map(|e| e.message.clone()))
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AppError::from(e).context("Failed to update sync run"))?;
+
+        Ok(())
+    }
+
+    /// Fails the dataset's runs still marked `running`, left behind by a process that
+    /// died mid-sync. Only safe while holding the dataset's sync lock, otherwise the run
+    /// of another instance could be closed under it.
+    pub async fn fail_interrupted(&self, dataset: Dataset) -> Result<u64, AppError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE sync_runs
+            SET status = $2, finished_at = NOW(),
+                error_message = 'Interrupted before it finished, the process stopped mid-sync'
+            WHERE dataset = $1 AND status = $3
+            "#,
+        )
+        .bind(dataset.as_str())
+        .bind(STATUS_FAILED)
+        .bind(STATUS_RUNNING)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AppError::from(e).context("Failed to close interrupted sync runs"))?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Unix `start_time`s of the dataset's rows that fall inside the range.
+    pub async fn get_synced_start_times(
+        &self,
+        dataset: Dataset,
+        range: &SyncRange,
+    ) -> Result<HashSet<i64>, AppError> {
+        self.repositories.start_times(dataset, range).await
+    }
+
+    pub async fn get_latest_run(&self, dataset: Dataset) -> Result<Option<SyncRun>, AppError> {
+        sqlx::query_as::<_, SyncRun>(
+            "SELECT * FROM sync_runs WHERE dataset = $1 ORDER BY started_at DESC LIMIT 1",
+        )
+        .bind(dataset.as_str())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AppError::from(e).context("Failed to fetch latest sync run"))
+    }
+
+    pub async fn get_sync_status(&self) -> Result<Vec<DatasetSyncStatus>, AppError> {
+        let now = Utc::now();
+        let mut statuses = Vec::with_capacity(Dataset::ALL.len());
+
+        for dataset in Dataset::ALL {
+            let newest_end_time = self
+                .repositories
+                .last_end_time(dataset)
+                .await
+                .map_err(|e| e.context("Failed to fetch data freshness"))?;
+
+            statuses.push(DatasetSyncStatus {
+                dataset: dataset.to_string(),
+                pool: dataset.pool().map(str::to_string),
+                latest_run: self.get_latest_run(dataset).await?,
+                newest_end_time,
+                lag_seconds: newest_end_time.map(|end_time| (now - end_time).num_seconds()),
+            });
+        }
+
+        Ok(statuses)
+    }
+}
@@ -0,0 +1,116 @@
+use crate::{
+    client::proxy::DEPTH_POOL,
+    error::AppError,
+    model::synth_analytics::{
+        SynthAnalytics, SynthAnalyticsBucket, SynthAnalyticsParams, SynthNetworkFlows,
+    },
+    utils::{
+        interval::interval_trunc,
+        query::{push_date_range, ratio},
+        validation::Validate,
+    },
+};
+use sqlx::{PgPool, Postgres, QueryBuilder, Row};
+use tracing::instrument;
+
+const DEFAULT_MAX_SYNTH_PER_POOL_DEPTH: i64 = 3500;
+
+/// Synth analytics joined and aggregated in SQL over the depth and swap tables, so
+/// unlike the history endpoints they need Postgres.
+#[derive(Clone)]
+pub struct SynthAnalyticsService {
+    pool: PgPool,
+}
+
+impl SynthAnalyticsService {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    #[instrument(skip_all)]
+    pub async fn get_synth_analytics(
+        &self,
+        params: &SynthAnalyticsParams,
+    ) -> Result<SynthAnalytics, AppError> {
+        params.validate()?;
+        let pool = params
+            .pool
+            .clone()
+            .unwrap_or_else(|| DEPTH_POOL.to_string());
+        let max_synth_per_pool_depth = params
+            .max_synth_per_pool_depth
+            .unwrap_or(DEFAULT_MAX_SYNTH_PER_POOL_DEPTH);
+
+        let bucket = interval_trunc(params.interval.as_deref().unwrap_or("day"));
+
+        // Last depth snapshot of the pool in each bucket, next to the network-wide synth
+        // flows of the bucket
+        let mut qb = QueryBuilder::<Postgres>::new("WITH depth AS (SELECT DISTINCT ON (bucket) ");
+        qb.push(bucket).push(
+            " AS bucket, asset_depth, units, synth_units, synth_supply \
+             FROM depth_price_history WHERE true",
+        );
+        push_date_range(&mut qb, &params.date_range);
+        qb.push(" ORDER BY bucket, start_time DESC), swaps AS (SELECT ")
+            .push(bucket)
+            .push(
+                " AS bucket, SUM(synth_mint_volume)::BIGINT AS synth_mint_volume, \
+                 SUM(synth_redeem_volume)::BIGINT AS synth_redeem_volume \
+                 FROM swap_history WHERE true",
+            );
+        push_date_range(&mut qb, &params.date_range);
+        qb.push(
+            " GROUP BY bucket) \
+             SELECT d.bucket, d.asset_depth, d.units, d.synth_units, d.synth_supply, \
+             COALESCE(s.synth_mint_volume, 0) AS synth_mint_volume, \
+             COALESCE(s.synth_redeem_volume, 0) AS synth_redeem_volume \
+             FROM depth d LEFT JOIN swaps s ON s.bucket = d.bucket ORDER BY d.bucket",
+        );
+
+        let query = qb.build();
+        let rows = query
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to fetch synth analytics"))?;
+
+        let buckets = rows
+            .iter()
+            .map(|row| {
+                let asset_depth: i64 = row.get("asset_depth");
+                let units: i64 = row.get("units");
+                let synth_units: i64 = row.get("synth_units");
+                let synth_supply: i64 = row.get("synth_supply");
+                let synth_mint_volume: i64 = row.get("synth_mint_volume");
+                let synth_redeem_volume: i64 = row.get("synth_redeem_volume");
+
+                // The cap is expressed against both sides of the pool, i.e. twice the asset depth
+                let synth_cap =
+                    (asset_depth as i128 * 2 * max_synth_per_pool_depth as i128 / 10_000) as i64;
+                let synth_cap_headroom = synth_cap - synth_supply;
+
+                SynthAnalyticsBucket {
+                    start_time: row.get("bucket"),
+                    asset_depth,
+                    units,
+                    synth_units,
+                    synth_supply,
+                    synth_utilisation: ratio(synth_units, units),
+                    synth_cap,
+                    synth_cap_headroom,
+                    synth_cap_headroom_ratio: ratio(synth_cap_headroom, synth_cap),
+                    network: SynthNetworkFlows {
+                        synth_mint_volume,
+                        synth_redeem_volume,
+                        net_mint_volume: synth_mint_volume - synth_redeem_volume,
+                    },
+                }
+            })
+            .collect();
+
+        Ok(SynthAnalytics {
+            pool,
+            max_synth_per_pool_depth,
+            buckets,
+        })
+    }
+}
@@ -0,0 +1,76 @@
+use std::future::Future;
+use tokio::task::JoinHandle;
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+use tracing::{info, warn};
+
+/// Tells the background tasks that the app is stopping and lets `main` wait for them.
+/// The syncs stop paging once it is requested and save what they fetched, so nothing
+/// is cut off mid-COPY.
+#[derive(Clone, Default)]
+pub struct Shutdown {
+    token: CancellationToken,
+    tasks: TaskTracker,
+}
+
+impl Shutdown {
+    /// Spawns a task that [`Shutdown::drain`] waits for.
+    pub fn spawn<F>(&self, task: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.tasks.spawn(task)
+    }
+
+    pub fn trigger(&self) {
+        self.token.cancel();
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// Resolves once a shutdown is requested.
+    pub async fn requested(&self) {
+        self.token.cancelled().await
+    }
+
+    /// Waits for every task spawned through [`Shutdown::spawn`] to finish.
+    pub async fn drain(&self) {
+        self.tasks.close();
+        self.tasks.wait().await
+    }
+}
+
+/// Triggers `shutdown` on SIGINT or SIGTERM.
+pub async fn listen_for_signals(shutdown: Shutdown) {
+    let interrupt = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!(error = %e, "Failed to listen for SIGINT");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(e) => {
+                warn!(error = %e, "Failed to listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = interrupt => info!("Received SIGINT, shutting down"),
+        _ = terminate => info!("Received SIGTERM, shutting down"),
+        _ = shutdown.requested() => return,
+    }
+    shutdown.trigger();
+}
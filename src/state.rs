@@ -0,0 +1,91 @@
+use crate::{
+    config::app::AppConfig,
+    cronjobs::sync_jobs::SyncJobs,
+    error::AppError,
+    metrics::Metrics,
+    repository::Repositories,
+    service::{
+        api_key_service::ApiKeyService, earning_history_service::EarningHistoryService,
+        gap_service::GapService, health_service::HealthService,
+        lp_simulator_service::LpSimulatorService, price_history_service::PriceHistoryService,
+        reconciliation_service::ReconciliationService, run_pool_service::RunePoolService,
+        rune_price_service::RunePriceService, swap_analytics_service::SwapAnalyticsService,
+        swap_history_service::SwapHistoryService, sync_run_service::SyncRunService,
+        synth_analytics_service::SynthAnalyticsService,
+    },
+    shutdown::Shutdown,
+};
+use sqlx::PgPool;
+use std::{sync::Arc, time::Duration};
+
+/// Shared by every handler through axum's `State` and handed to the sync jobs, so the
+/// whole app can be built from any config and pool, e.g. in tests.
+#[derive(Clone)]
+pub struct AppState {
+    pub config: Arc<AppConfig>,
+    pub pool: PgPool,
+    pub http: reqwest::Client,
+    pub metrics: Metrics,
+    pub shutdown: Shutdown,
+    pub sync_jobs: Arc<SyncJobs>,
+    pub price_history: PriceHistoryService,
+    pub earning_history: EarningHistoryService,
+    pub swap_history: SwapHistoryService,
+    pub rune_pool: RunePoolService,
+    pub rune_price: RunePriceService,
+    pub swap_analytics: SwapAnalyticsService,
+    pub synth_analytics: SynthAnalyticsService,
+    pub lp_simulator: LpSimulatorService,
+    pub sync_runs: SyncRunService,
+    pub gaps: GapService,
+    pub reconciliation: ReconciliationService,
+    pub health: HealthService,
+    pub api_keys: ApiKeyService,
+}
+
+impl AppState {
+    pub fn new(config: AppConfig, pool: PgPool) -> Result<Self, AppError> {
+        let repositories = Repositories::postgres(pool.clone());
+        Self::with_repositories(config, pool, repositories)
+    }
+
+    /// Like [`AppState::new`] but with the history tables stored in `repositories`,
+    /// e.g. [`Repositories::in_memory`] to run the history endpoints without a database.
+    pub fn with_repositories(
+        config: AppConfig,
+        pool: PgPool,
+        repositories: Repositories,
+    ) -> Result<Self, AppError> {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.midgard.timeout_secs))
+            .build()
+            .map_err(|e| AppError::new(format!("Failed to build HTTP client: {}", e)))?;
+
+        let history = repositories.clone();
+        Ok(Self {
+            price_history: PriceHistoryService::new(repositories.price_history),
+            earning_history: EarningHistoryService::new(
+                pool.clone(),
+                repositories.earning_history,
+                config.total_bond,
+            ),
+            swap_history: SwapHistoryService::new(repositories.swap_history),
+            rune_pool: RunePoolService::new(pool.clone(), repositories.rune_pool),
+            rune_price: RunePriceService::new(pool.clone()),
+            swap_analytics: SwapAnalyticsService::new(pool.clone()),
+            synth_analytics: SynthAnalyticsService::new(pool.clone()),
+            lp_simulator: LpSimulatorService::new(pool.clone()),
+            sync_runs: SyncRunService::new(pool.clone(), history.clone()),
+            gaps: GapService::new(history.clone()),
+            reconciliation: ReconciliationService::new(history),
+            health: HealthService::new(pool.clone(), config.health.max_data_age_secs),
+            api_keys: ApiKeyService::new(repositories.api_keys, config.auth.clone()),
+            config: Arc::new(config),
+            pool,
+            http,
+            metrics: Metrics::new()?,
+            shutdown: Shutdown::default(),
+            sync_jobs: Arc::new(SyncJobs::default()),
+        })
+    }
+}
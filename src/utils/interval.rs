@@ -0,0 +1,14 @@
+/// Maps an `interval` query value to the `date_trunc` expression used to bucket
+/// `start_time`. Unknown values fall back to hourly buckets like the history endpoints.
+pub fn interval_trunc(interval: &str) -> &'static str {
+    match interval {
+        "5min" => "date_trunc('hour', start_time) + INTERVAL '5 minutes' * (EXTRACT(MINUTE FROM start_time)::int / 5)",
+        "hour" => "date_trunc('hour', start_time)",
+        "day" => "date_trunc('day', start_time)",
+        "week" => "date_trunc('week', start_time)",
+        "month" => "date_trunc('month', start_time)",
+        "quarter" => "date_trunc('quarter', start_time)",
+        "year" => "date_trunc('year', start_time)",
+        _ => "date_trunc('hour', start_time)",
+    }
+}
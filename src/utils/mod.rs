@@ -1 +1,4 @@
-pub mod conversions;
\ No newline at end of file
+pub mod interval;
+pub mod query;
+pub mod upstream;
+pub mod validation;
@@ -0,0 +1,56 @@
+use sqlx::{Postgres, QueryBuilder};
+
+/// Applies the `YYYY-MM-DD,YYYY-MM-DD` date range filter used across the history endpoints.
+pub fn push_date_range(qb: &mut QueryBuilder<'_, Postgres>, date_range: &Option<String>) {
+    if let Some(date_range) = date_range {
+        let dates: Vec<String> = date_range
+            .split(',')
+            .map(|d| d.trim().to_string())
+            .collect();
+        if dates.len() == 2 {
+            qb.push(" AND start_time >= TO_TIMESTAMP(")
+                .push_bind(dates[0].clone())
+                .push(", 'YYYY-MM-DD') AND end_time <= TO_TIMESTAMP(")
+                .push_bind(dates[1].clone())
+                .push(", 'YYYY-MM-DD')");
+        }
+    }
+}
+
+/// The `_gt`/`_lt`/`_eq` filters of a single column.
+pub fn add_comparison_filters<'q, T>(
+    qb: &mut QueryBuilder<'q, Postgres>,
+    field: &str,
+    gt: Option<T>,
+    lt: Option<T>,
+    eq: Option<T>,
+) where
+    T: sqlx::Encode<'q, Postgres> + sqlx::Type<Postgres> + std::fmt::Display + Send + 'q,
+{
+    if let Some(val) = gt {
+        qb.push(format!(" AND {} > ", field)).push_bind(val);
+    }
+    if let Some(val) = lt {
+        qb.push(format!(" AND {} < ", field)).push_bind(val);
+    }
+    if let Some(val) = eq {
+        qb.push(format!(" AND {} = ", field)).push_bind(val);
+    }
+}
+
+/// Relative change between two consecutive buckets, `None` when there is no baseline.
+pub fn relative_change(previous: Option<f64>, current: f64) -> Option<f64> {
+    match previous {
+        Some(previous) if previous != 0.0 => Some((current - previous) / previous),
+        _ => None,
+    }
+}
+
+/// Share of `part` in `total`, 0 for an empty total.
+pub fn ratio(part: i64, total: i64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f64 / total as f64
+    }
+}
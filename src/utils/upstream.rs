@@ -0,0 +1,22 @@
+//! Midgard encodes numbers and timestamps as strings, a value that doesn't parse
+//! fails the sync instead of being stored as something else.
+
+use crate::error::AppError;
+use chrono::{DateTime, TimeZone, Utc};
+use std::str::FromStr;
+
+/// `value` of the Midgard field `field` as a number.
+pub fn number<T: FromStr>(field: &str, value: &str) -> Result<T, AppError> {
+    value.parse().map_err(|_| invalid(field, value))
+}
+
+/// `value` of the Midgard field `field` as a unix timestamp in seconds.
+pub fn timestamp(field: &str, value: &str) -> Result<DateTime<Utc>, AppError> {
+    Utc.timestamp_opt(number(field, value)?, 0)
+        .single()
+        .ok_or_else(|| invalid(field, value))
+}
+
+fn invalid(field: &str, value: &str) -> AppError {
+    AppError::upstream(format!("Invalid {} in Midgard response: {}", field, value))
+}
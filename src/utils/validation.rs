@@ -0,0 +1,127 @@
+use crate::error::{AppError, FieldError};
+use chrono::NaiveDate;
+
+/// Intervals the history endpoints bucket by.
+pub const INTERVALS: [&str; 7] = ["5min", "hour", "day", "week", "month", "quarter", "year"];
+
+/// Most rows `count` or `limit` can ask for, the page size of Midgard.
+pub const MAX_PAGE_SIZE: i64 = 400;
+
+/// Query params that are checked before they reach a repository.
+pub trait Validate {
+    fn validate(&self) -> Result<(), AppError>;
+}
+
+/// Collects every problem with a request, so one response lists all of them.
+#[derive(Debug, Default)]
+pub struct Validator {
+    errors: Vec<FieldError>,
+}
+
+impl Validator {
+    pub fn check(&mut self, valid: bool, field: &str, message: impl FnOnce() -> String) {
+        if !valid {
+            self.errors.push(FieldError {
+                field: field.to_string(),
+                message: message(),
+            });
+        }
+    }
+
+    pub fn interval(&mut self, interval: &Option<String>) {
+        if let Some(interval) = interval {
+            self.check(INTERVALS.contains(&interval.as_str()), "interval", || {
+                format!(
+                    "unknown interval \"{}\", expected one of {}",
+                    interval,
+                    INTERVALS.join(", ")
+                )
+            });
+        }
+    }
+
+    pub fn order(&mut self, order: &Option<String>) {
+        if let Some(order) = order {
+            let known = order.eq_ignore_ascii_case("asc") || order.eq_ignore_ascii_case("desc");
+            self.check(known, "order", || {
+                format!("unknown order \"{}\", expected asc or desc", order)
+            });
+        }
+    }
+
+    /// `sort_by` ends up in `ORDER BY`, so only the columns of the table are allowed.
+    pub fn sort_by(&mut self, sort_by: &Option<String>, columns: &[&str]) {
+        if let Some(sort_by) = sort_by {
+            self.check(columns.contains(&sort_by.as_str()), "sort_by", || {
+                format!("column \"{}\" does not exist", sort_by)
+            });
+        }
+    }
+
+    /// `YYYY-MM-DD,YYYY-MM-DD` with the first day not after the second.
+    pub fn date_range(&mut self, date_range: &Option<String>) {
+        let Some(date_range) = date_range else {
+            return;
+        };
+        let dates: Vec<&str> = date_range.split(',').map(str::trim).collect();
+        let [from, to] = dates[..] else {
+            self.check(false, "date_range", || {
+                format!("expected YYYY-MM-DD,YYYY-MM-DD, got \"{}\"", date_range)
+            });
+            return;
+        };
+
+        let mut parse = |date: &str| {
+            let parsed = NaiveDate::parse_from_str(date, "%Y-%m-%d");
+            self.check(parsed.is_ok(), "date_range", || {
+                format!("invalid date \"{}\", expected YYYY-MM-DD", date)
+            });
+            parsed.ok()
+        };
+        if let (Some(from), Some(to)) = (parse(from), parse(to)) {
+            self.check(from <= to, "date_range", || {
+                format!("{} is after {}", from, to)
+            });
+        }
+    }
+
+    /// `count` and `limit`, between 1 and [`MAX_PAGE_SIZE`].
+    pub fn page_size(&mut self, field: &str, size: Option<i32>) {
+        if let Some(size) = size {
+            self.check((1..=MAX_PAGE_SIZE).contains(&(size as i64)), field, || {
+                format!("must be between 1 and {}, got {}", MAX_PAGE_SIZE, size)
+            });
+        }
+    }
+
+    pub fn page(&mut self, page: Option<i32>, first: i32) {
+        if let Some(page) = page {
+            self.check(page >= first, "page", || {
+                format!("must be at least {}, got {}", first, page)
+            });
+        }
+    }
+
+    /// `count` replaces `limit` and `page`, so it can't be combined with them.
+    pub fn count_or_pages(&mut self, count: Option<i32>, limit: Option<i32>, page: Option<i32>) {
+        if count.is_some() {
+            self.check(limit.is_none(), "count", || {
+                "can't be combined with limit".to_string()
+            });
+            self.check(page.is_none(), "count", || {
+                "can't be combined with page".to_string()
+            });
+        }
+    }
+
+    pub fn finish(self) -> Result<(), AppError> {
+        if self.errors.is_empty() {
+            return Ok(());
+        }
+        let mut fields: Vec<&str> = self.errors.iter().map(|e| e.field.as_str()).collect();
+        fields.dedup();
+        let mut error = AppError::validation(format!("Invalid {}", fields.join(", ")));
+        error.details = self.errors;
+        Err(error)
+    }
+}
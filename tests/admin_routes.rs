@@ -0,0 +1,307 @@
+mod common;
+
+use axum::http::{header, Method, StatusCode};
+use common::{
+    interval, midgard::MockMidgard, midgard_config, send, test_config, test_state, START,
+};
+use midguard_api::{
+    config::app::AppConfig,
+    model::{price_history::PriceHistory, rune_pool::Runepool},
+    routes,
+};
+
+fn config_with_token(token: Option<&str>) -> AppConfig {
+    AppConfig {
+        admin_token: token.map(str::to_string),
+        ..test_config()
+    }
+}
+
+#[tokio::test]
+async fn admin_routes_are_disabled_without_a_token() {
+    let app = routes::router(test_state(config_with_token(None)));
+
+    let (status, _, body) = send(
+        app,
+        Method::GET,
+        "/admin/sync/jobs",
+        &[(header::AUTHORIZATION, "Bearer secret")],
+        None,
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["statusCode"], 403);
+    assert_eq!(body["code"], "forbidden");
+}
+
+#[tokio::test]
+async fn admin_routes_reject_a_wrong_token() {
+    let state = test_state(config_with_token(Some("secret")));
+
+    let (status, _, _) = send(
+        routes::router(state.clone()),
+        Method::GET,
+        "/admin/sync/jobs",
+        &[],
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _, _) = send(
+        routes::router(state),
+        Method::GET,
+        "/admin/sync/jobs",
+        &[(header::AUTHORIZATION, "Bearer wrong")],
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn admin_routes_accept_the_configured_token() {
+    let state = test_state(config_with_token(Some("secret")));
+
+    let (status, _, body) = send(
+        routes::router(state),
+        Method::GET,
+        "/admin/sync/jobs",
+        &[(header::AUTHORIZATION, "Bearer secret")],
+        None,
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, serde_json::json!([]));
+}
+
+#[tokio::test]
+async fn unknown_sync_jobs_are_not_found() {
+    let state = test_state(config_with_token(Some("secret")));
+
+    let (status, _, _) = send(
+        routes::router(state),
+        Method::POST,
+        "/admin/sync/jobs/00000000-0000-0000-0000-000000000000/cancel",
+        &[(header::AUTHORIZATION, "Bearer secret")],
+        None,
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn unknown_datasets_are_not_found() {
+    let state = test_state(config_with_token(Some("secret")));
+
+    let (status, _, body) = send(
+        routes::router(state),
+        Method::POST,
+        "/admin/sync/bonds",
+        &[(header::AUTHORIZATION, "Bearer secret")],
+        None,
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["code"], "not_found");
+    assert_eq!(body["message"], "Unknown dataset: bonds");
+}
+
+#[tokio::test]
+async fn gaps_and_overlaps_are_reported_per_dataset() {
+    let state = test_state(config_with_token(Some("secret")));
+    // Intervals 3 and 4 are missing and interval 6 was saved twice
+    let rows: Vec<PriceHistory> = [0, 1, 2, 5, 6, 6]
+        .into_iter()
+        .map(|n| PriceHistory {
+            start_time: interval(n),
+            end_time: interval(n + 1),
+            ..Default::default()
+        })
+        .collect();
+    state.price_history.save_batch(&rows).await.unwrap();
+
+    let (status, _, body) = send(
+        routes::router(state),
+        Method::GET,
+        "/admin/gaps?dataset=depths",
+        &[(header::AUTHORIZATION, "Bearer secret")],
+        None,
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::OK);
+    let depths = &body[0];
+    assert_eq!(depths["intervals"], 6);
+    assert_eq!(depths["missing_intervals"], 2);
+    assert_eq!(depths["gaps"][0]["from"], "2024-11-01T00:15:00Z");
+    assert_eq!(depths["gaps"][0]["to"], "2024-11-01T00:25:00Z");
+    assert_eq!(depths["overlaps"][0]["start_time"], "2024-11-01T00:30:00Z");
+    assert_eq!(depths["overlaps"][0]["duplicate"], true);
+}
+
+#[tokio::test]
+async fn reconcile_reports_duplicated_and_unexpected_intervals() {
+    let midgard = MockMidgard::start().await;
+    let state = test_state(AppConfig {
+        admin_token: Some("secret".to_string()),
+        ..midgard_config(&midgard.url)
+    });
+    // The first recorded interval saved twice, and one Midgard doesn't have
+    let first = Runepool {
+        start_time: interval(0),
+        end_time: interval(1),
+        count: 1031,
+        units: 4823000000000,
+        ..Default::default()
+    };
+    let extra = Runepool {
+        start_time: interval(30),
+        end_time: interval(31),
+        ..Default::default()
+    };
+    state
+        .rune_pool
+        .save_batch(&[first.clone(), first, extra])
+        .await
+        .unwrap();
+
+    let (status, _, body) = send(
+        routes::router(state),
+        Method::POST,
+        &format!(
+            "/admin/reconcile?dataset=runepool&from={}&to={}",
+            START,
+            START + 31 * 300
+        ),
+        &[(header::AUTHORIZATION, "Bearer secret")],
+        None,
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::OK);
+    let runepool = &body[0];
+    assert_eq!(runepool["matched"], 0);
+    assert_eq!(runepool["duplicates"][0], "2024-11-01T00:00:00Z");
+    assert_eq!(runepool["mismatches"], serde_json::json!([]));
+    assert_eq!(runepool["unexpected"][0], "2024-11-01T02:30:00Z");
+    assert_eq!(runepool["missing"].as_array().unwrap().len(), 23);
+}
+
+#[tokio::test]
+async fn reconcile_windows_are_capped() {
+    let state = test_state(config_with_token(Some("secret")));
+
+    let (status, _, body) = send(
+        routes::router(state),
+        Method::POST,
+        &format!(
+            "/admin/reconcile?from={}&to={}",
+            START,
+            START + 8 * 24 * 3600
+        ),
+        &[(header::AUTHORIZATION, "Bearer secret")],
+        None,
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["details"][0]["field"], "to");
+}
+
+#[tokio::test]
+async fn jobs_stopped_by_a_shutdown_are_interrupted() {
+    let state = test_state(config_with_token(Some("secret")));
+    state.shutdown.trigger();
+
+    let (status, _, job) = send(
+        routes::router(state.clone()),
+        Method::POST,
+        "/admin/sync/swaps/backfill",
+        &[(header::AUTHORIZATION, "Bearer secret")],
+        Some(serde_json::json!({ "from": START, "to": START + 3600 })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    state.shutdown.drain().await;
+
+    let (_, _, job) = send(
+        routes::router(state),
+        Method::GET,
+        &format!("/admin/sync/jobs/{}", job["id"].as_str().unwrap()),
+        &[(header::AUTHORIZATION, "Bearer secret")],
+        None,
+    )
+    .await;
+    assert_eq!(job["status"], "interrupted");
+    assert_eq!(job["progress"], 0.0);
+}
+
+#[tokio::test]
+async fn gap_repairs_start_nothing_while_a_dataset_is_busy() {
+    // Accepts the sync lock connections but never answers, so started jobs keep running
+    let database = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = database.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut held = Vec::new();
+        while let Ok((socket, _)) = database.accept().await {
+            held.push(socket);
+        }
+    });
+    let mut config = config_with_token(Some("secret"));
+    config.database.url = format!("postgres://postgres@{}/midguard", address);
+    let state = test_state(config);
+
+    // Both datasets are missing interval 1
+    for n in [0, 2] {
+        let row = PriceHistory {
+            start_time: interval(n),
+            end_time: interval(n + 1),
+            ..Default::default()
+        };
+        state.price_history.save_batch(&[row]).await.unwrap();
+        let row = Runepool {
+            start_time: interval(n),
+            end_time: interval(n + 1),
+            ..Default::default()
+        };
+        state.rune_pool.save_batch(&[row]).await.unwrap();
+    }
+
+    let (status, _, _) = send(
+        routes::router(state.clone()),
+        Method::POST,
+        "/admin/sync/runepool/backfill",
+        &[(header::AUTHORIZATION, "Bearer secret")],
+        Some(serde_json::json!({ "from": START, "to": START + 3600 })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+
+    let (status, _, body) = send(
+        routes::router(state.clone()),
+        Method::POST,
+        "/admin/gaps/repair",
+        &[(header::AUTHORIZATION, "Bearer secret")],
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["code"], "conflict");
+
+    let (_, _, jobs) = send(
+        routes::router(state),
+        Method::GET,
+        "/admin/sync/jobs",
+        &[(header::AUTHORIZATION, "Bearer secret")],
+        None,
+    )
+    .await;
+    let jobs = jobs.as_array().unwrap();
+    assert_eq!(jobs.len(), 1, "{:?}", jobs);
+    assert_eq!(jobs[0]["dataset"], "runepool");
+}
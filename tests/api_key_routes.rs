@@ -0,0 +1,232 @@
+mod common;
+
+use axum::http::{header, HeaderMap, Method, StatusCode};
+use common::{get, send, test_config, test_state};
+use midguard_api::{
+    config::app::AppConfig,
+    routes::{self, api_key_route::API_KEY_HEADER},
+    state::AppState,
+};
+use serde_json::{json, Value};
+
+const ADMIN_TOKEN: &str = "secret";
+
+fn config(require_api_key: bool) -> AppConfig {
+    let mut config = test_config();
+    config.admin_token = Some(ADMIN_TOKEN.to_string());
+    config.auth.require_api_key = require_api_key;
+    config
+}
+
+/// Sends a request with the API key, or with the admin token without one.
+async fn request(
+    state: &AppState,
+    method: Method,
+    uri: &str,
+    api_key: Option<&str>,
+    body: Option<Value>,
+) -> (StatusCode, HeaderMap, Value) {
+    let admin = format!("Bearer {}", ADMIN_TOKEN);
+    let auth = match api_key {
+        Some(key) => (API_KEY_HEADER, key),
+        None => (header::AUTHORIZATION, admin.as_str()),
+    };
+    send(routes::router(state.clone()), method, uri, &[auth], body).await
+}
+
+/// Creates a key through the admin API with the admin token, returning the key itself.
+async fn create_key(state: &AppState, body: Value) -> String {
+    let (status, _, created) =
+        request(state, Method::POST, "/admin/api-keys", None, Some(body)).await;
+    assert_eq!(status, StatusCode::CREATED);
+    created["key"].as_str().expect("the new key").to_string()
+}
+
+#[tokio::test]
+async fn created_keys_are_shown_once_and_stored_hashed() {
+    let state = test_state(config(false));
+
+    let key = create_key(&state, json!({ "name": "dashboard" })).await;
+    assert!(key.starts_with("mgk_"));
+
+    let (status, _, keys) = request(&state, Method::GET, "/admin/api-keys", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(keys[0]["name"], "dashboard");
+    assert_eq!(keys[0]["scopes"], json!(["read"]));
+    assert_eq!(keys[0]["prefix"], &key[..10]);
+    assert!(keys[0].get("key").is_none());
+}
+
+#[tokio::test]
+async fn invalid_keys_are_rejected_with_every_problem() {
+    let state = test_state(config(false));
+
+    let (status, _, body) = request(
+        &state,
+        Method::POST,
+        "/admin/api-keys",
+        None,
+        Some(json!({ "name": " ", "scopes": [], "daily_quota": 0 })),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["message"], "Invalid name, scopes, daily_quota");
+}
+
+#[tokio::test]
+async fn data_routes_require_a_key_when_configured() {
+    let state = test_state(config(true));
+
+    let (status, _, body) = request(
+        &state,
+        Method::GET,
+        "/depth-history",
+        Some("mgk_nope"),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(body["code"], "unauthorized");
+
+    let (status, _) = get(&state, "/depth-history").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // Probes and scrapes don't need a key
+    let (status, _) = get(&state, "/healthz").await;
+    assert_eq!(status, StatusCode::OK);
+
+    let key = create_key(&state, json!({ "name": "dashboard" })).await;
+    let (status, _, _) = request(&state, Method::GET, "/depth-history", Some(&key), None).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn scopes_decide_who_reaches_the_admin_routes() {
+    let mut config = config(false);
+    config.admin_token = None;
+    let state = test_state(config);
+    let read = state
+        .api_keys
+        .create(serde_json::from_value(json!({ "name": "reader" })).unwrap())
+        .await
+        .unwrap()
+        .key;
+    let admin = state
+        .api_keys
+        .create(serde_json::from_value(json!({ "name": "ops", "scopes": ["admin"] })).unwrap())
+        .await
+        .unwrap()
+        .key;
+
+    let (status, _, _) = request(&state, Method::GET, "/admin/sync/jobs", Some(&read), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, _, _) = request(&state, Method::GET, "/admin/sync/jobs", Some(&admin), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Admin keys can read the data too
+    let (status, _, _) = request(&state, Method::GET, "/swap-history", Some(&admin), None).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn keys_over_their_rate_limit_are_told_when_to_retry() {
+    let state = test_state(config(false));
+    let key = create_key(
+        &state,
+        json!({ "name": "poller", "rate_limit_per_minute": 2 }),
+    )
+    .await;
+
+    for _ in 0..2 {
+        let (status, _, _) = request(&state, Method::GET, "/swap-history", Some(&key), None).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    let (status, headers, body) =
+        request(&state, Method::GET, "/swap-history", Some(&key), None).await;
+
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(body["code"], "rate_limited");
+    let retry_after: u64 = headers[header::RETRY_AFTER]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!((1..=30).contains(&retry_after));
+}
+
+#[tokio::test]
+async fn keys_over_their_daily_quota_are_rejected() {
+    let state = test_state(config(false));
+    let key = create_key(&state, json!({ "name": "trial", "daily_quota": 1 })).await;
+
+    let (status, _, _) = request(&state, Method::GET, "/swap-history", Some(&key), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _, body) = request(&state, Method::GET, "/swap-history", Some(&key), None).await;
+
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(body["message"], "Daily quota of 1 requests used up");
+}
+
+#[tokio::test]
+async fn concurrent_requests_cannot_overshoot_the_quota() {
+    let state = test_state(config(false));
+    let key = create_key(&state, json!({ "name": "burst", "daily_quota": 3 })).await;
+
+    let requests: Vec<_> = (0..10)
+        .map(|_| {
+            let (state, key) = (state.clone(), key.clone());
+            tokio::spawn(async move {
+                request(&state, Method::GET, "/swap-history", Some(&key), None)
+                    .await
+                    .0
+            })
+        })
+        .collect();
+    let mut admitted = 0;
+    for request in requests {
+        if request.await.unwrap() == StatusCode::OK {
+            admitted += 1;
+        }
+    }
+
+    assert_eq!(admitted, 3);
+}
+
+#[tokio::test]
+async fn usage_is_counted_per_key_and_route() {
+    let state = test_state(config(false));
+    let key = create_key(&state, json!({ "name": "dashboard" })).await;
+
+    for uri in ["/swap-history", "/swap-history?limit=10", "/depth-history"] {
+        request(&state, Method::GET, uri, Some(&key), None).await;
+    }
+    let (status, _, usage) =
+        request(&state, Method::GET, "/admin/api-keys/usage", None, None).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(usage.as_array().unwrap().len(), 2);
+    assert_eq!(usage[0]["name"], "dashboard");
+    assert_eq!(usage[0]["route"], "/swap-history");
+    assert_eq!(usage[0]["requests"], 2);
+    assert_eq!(usage[1]["route"], "/depth-history");
+    assert_eq!(usage[1]["requests"], 1);
+}
+
+#[tokio::test]
+async fn revoked_keys_stop_working() {
+    let state = test_state(config(false));
+    let key = create_key(&state, json!({ "name": "leaked" })).await;
+
+    let (status, _, revoked) =
+        request(&state, Method::DELETE, "/admin/api-keys/1", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(revoked["revoked_at"].is_string());
+
+    let (status, _, _) = request(&state, Method::GET, "/swap-history", Some(&key), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _, _) = request(&state, Method::DELETE, "/admin/api-keys/9", None, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
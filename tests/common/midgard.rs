@@ -0,0 +1,179 @@
+//! A local stand-in for Midgard's `/v2/history` endpoints, replaying the recorded
+//! responses in `tests/fixtures/midgard` page by page, with scripted failures.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use midguard_api::cronjobs::dataset::Dataset;
+use serde_json::{json, Value};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+};
+use tokio::net::TcpListener;
+
+/// Midgard caps a page at 400 intervals, the mock at fewer so the fixtures span pages.
+pub const PAGE_SIZE: usize = 10;
+
+/// What the mock answers to one request of a dataset instead of the recorded page.
+#[derive(Debug, Clone, Copy)]
+pub enum Scripted {
+    /// The recorded page, to let a failure hit a later page.
+    Page,
+    /// An error status with a plain text body.
+    Status(StatusCode),
+    /// A page without intervals, as Midgard returns past its newest data.
+    EmptyPage,
+    /// The recorded page with `field` of every interval replaced by a non-number.
+    Malformed(&'static str),
+}
+
+struct MockState {
+    fixtures: HashMap<Dataset, Vec<Value>>,
+    script: Mutex<HashMap<Dataset, VecDeque<Scripted>>>,
+    requests: Mutex<Vec<(Dataset, i64)>>,
+}
+
+pub struct MockMidgard {
+    pub url: String,
+    state: Arc<MockState>,
+}
+
+impl MockMidgard {
+    /// Serves the recorded fixtures on a free local port until the test ends.
+    pub async fn start() -> Self {
+        let fixtures = Dataset::ALL
+            .into_iter()
+            .map(|dataset| (dataset, fixture(dataset)))
+            .collect();
+        let state = Arc::new(MockState {
+            fixtures,
+            script: Mutex::default(),
+            requests: Mutex::default(),
+        });
+
+        let app = Router::new()
+            .route("/v2/history/depths/{pool}", get(depths))
+            .route("/v2/history/{dataset}", get(history))
+            .with_state(state.clone());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        Self { url, state }
+    }
+
+    /// Queues responses for the next requests of `dataset`, after which the
+    /// recorded pages are served again.
+    pub fn script(&self, dataset: Dataset, responses: impl IntoIterator<Item = Scripted>) {
+        self.state
+            .script
+            .lock()
+            .unwrap()
+            .entry(dataset)
+            .or_default()
+            .extend(responses);
+    }
+
+    /// `from` of every request of `dataset` so far, in order.
+    pub fn requested_from(&self, dataset: Dataset) -> Vec<i64> {
+        self.state
+            .requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(requested, _)| *requested == dataset)
+            .map(|(_, from)| *from)
+            .collect()
+    }
+
+    /// Number of recorded intervals of `dataset`.
+    pub fn interval_count(&self, dataset: Dataset) -> usize {
+        self.state.fixtures[&dataset].len()
+    }
+}
+
+fn fixture(dataset: Dataset) -> Vec<Value> {
+    let recorded = match dataset {
+        Dataset::Depths => include_str!("../fixtures/midgard/depths.json"),
+        Dataset::Earnings => include_str!("../fixtures/midgard/earnings.json"),
+        Dataset::Swaps => include_str!("../fixtures/midgard/swaps.json"),
+        Dataset::Runepool => include_str!("../fixtures/midgard/runepool.json"),
+    };
+    let mut response: Value = serde_json::from_str(recorded).expect("valid fixture");
+    match response["intervals"].take() {
+        Value::Array(intervals) => intervals,
+        _ => panic!("{} fixture has no intervals", dataset),
+    }
+}
+
+async fn depths(
+    State(state): State<Arc<MockState>>,
+    Path(_pool): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Response {
+    respond(&state, Dataset::Depths, &query)
+}
+
+async fn history(
+    State(state): State<Arc<MockState>>,
+    Path(dataset): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Response {
+    match dataset.parse::<Dataset>() {
+        Ok(dataset) if dataset != Dataset::Depths => respond(&state, dataset, &query),
+        _ => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+fn respond(state: &MockState, dataset: Dataset, query: &HashMap<String, String>) -> Response {
+    let param = |name: &str| query.get(name).and_then(|value| value.parse::<i64>().ok());
+    let from = param("from").unwrap_or(0);
+    let count = param("count").map_or(PAGE_SIZE, |count| (count as usize).min(PAGE_SIZE));
+    state.requests.lock().unwrap().push((dataset, from));
+
+    let scripted = state
+        .script
+        .lock()
+        .unwrap()
+        .get_mut(&dataset)
+        .and_then(VecDeque::pop_front)
+        .unwrap_or(Scripted::Page);
+
+    let mut intervals: Vec<Value> = state.fixtures[&dataset]
+        .iter()
+        .filter(|interval| start_time(interval) >= from)
+        .take(count)
+        .cloned()
+        .collect();
+    match scripted {
+        Scripted::Page => {}
+        Scripted::Status(status) => return (status, "scripted failure").into_response(),
+        Scripted::EmptyPage => intervals.clear(),
+        Scripted::Malformed(field) => {
+            for interval in &mut intervals {
+                interval[field] = json!("12,5e");
+            }
+        }
+    }
+
+    let meta = match (intervals.first(), intervals.last()) {
+        (Some(first), Some(last)) => json!({
+            "startTime": first["startTime"],
+            "endTime": last["endTime"],
+        }),
+        _ => json!({}),
+    };
+    Json(json!({ "intervals": intervals, "meta": meta })).into_response()
+}
+
+fn start_time(interval: &Value) -> i64 {
+    interval["startTime"]
+        .as_str()
+        .and_then(|start| start.parse().ok())
+        .unwrap_or_default()
+}
@@ -0,0 +1,118 @@
+//! Shared setup of the integration tests: an app state backed by the in-memory
+//! repositories and helpers to send requests through the router.
+// Not every test file uses every helper
+#![allow(dead_code)]
+
+pub mod midgard;
+
+use axum::{
+    body::{to_bytes, Body},
+    http::{header, HeaderMap, HeaderName, Method, Request, StatusCode},
+    Router,
+};
+use chrono::{DateTime, TimeZone, Utc};
+use midguard_api::{
+    config::app::{AppConfig, DatabaseConfig},
+    repository::Repositories,
+    routes,
+    state::AppState,
+};
+use serde_json::Value;
+use sqlx::postgres::PgPoolOptions;
+use std::time::Duration;
+use tower::ServiceExt;
+
+// 2024-11-01 00:00:00 UTC, where syncs start by default
+pub const START: i64 = 1730419200;
+
+pub fn test_config() -> AppConfig {
+    AppConfig {
+        database: DatabaseConfig {
+            // Never connected to, everything the tests touch is in memory
+            url: "postgres://localhost/midguard_test".to_string(),
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+/// Test config syncing from the mock Midgard at `url`, retrying without a real backoff.
+pub fn midgard_config(url: &str) -> AppConfig {
+    let mut config = test_config();
+    config.midgard.url = url.to_string();
+    config.midgard.retry_backoff_ms = 1;
+    config
+}
+
+/// State with in-memory history tables and a pool that is only connected on first use.
+pub fn test_state(config: AppConfig) -> AppState {
+    test_state_with(config, Repositories::in_memory())
+}
+
+/// [`test_state`] over `repositories`, to also write them the way only the syncs do.
+pub fn test_state_with(config: AppConfig, repositories: Repositories) -> AppState {
+    let pool = PgPoolOptions::new()
+        .acquire_timeout(Duration::from_secs(config.database.acquire_timeout_secs))
+        .connect_lazy(&config.database.url)
+        .expect("valid database url");
+    AppState::with_repositories(config, pool, repositories)
+        .expect("state builds from the test config")
+}
+
+/// Start of the 5 minute interval `n` intervals after [`START`].
+pub fn interval(n: i64) -> DateTime<Utc> {
+    Utc.timestamp_opt(START + n * 300, 0).unwrap()
+}
+
+/// Sends a request with `headers` and, when given, a JSON `body` through `app`. Bodies
+/// that aren't JSON come back as a string.
+pub async fn send(
+    app: Router,
+    method: Method,
+    uri: &str,
+    headers: &[(HeaderName, &str)],
+    body: Option<Value>,
+) -> (StatusCode, HeaderMap, Value) {
+    let mut request = Request::builder().method(method).uri(uri);
+    for (name, value) in headers {
+        request = request.header(name, *value);
+    }
+    let body = match body {
+        Some(body) => {
+            request = request.header(header::CONTENT_TYPE, "application/json");
+            Body::from(body.to_string())
+        }
+        None => Body::empty(),
+    };
+    let response = app
+        .oneshot(request.body(body).unwrap())
+        .await
+        .expect("router never fails");
+
+    let status = response.status();
+    let headers = response.headers().clone();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let json = serde_json::from_slice(&body)
+        .unwrap_or(Value::String(String::from_utf8_lossy(&body).into_owned()));
+    (status, headers, json)
+}
+
+pub async fn get(state: &AppState, uri: &str) -> (StatusCode, Value) {
+    let (status, _, body) = get_with(state, uri, &[]).await;
+    (status, body)
+}
+
+pub async fn get_with(
+    state: &AppState,
+    uri: &str,
+    headers: &[(HeaderName, &str)],
+) -> (StatusCode, HeaderMap, Value) {
+    send(
+        routes::router(state.clone()),
+        Method::GET,
+        uri,
+        headers,
+        None,
+    )
+    .await
+}
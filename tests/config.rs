@@ -0,0 +1,65 @@
+use midguard_api::{config::app::AppConfig, cronjobs::dataset::Dataset};
+
+fn parse(toml: &str) -> AppConfig {
+    let mut config: AppConfig = toml::from_str(toml).unwrap();
+    config.database.url = "postgres://localhost/midguard_test".to_string();
+    config
+}
+
+#[test]
+fn scheduler_section_is_read_from_the_config_file() {
+    let config = parse(
+        r#"
+        [scheduler]
+        run_on_startup = false
+
+        [scheduler.jobs.swaps]
+        schedule = "0 */30 * * * *"
+
+        [scheduler.jobs.runepool]
+        enabled = false
+
+        [scheduler.reconcile]
+        schedule = "0 0 3 * * *"
+        repair = true
+        "#,
+    );
+    config.validate().unwrap();
+
+    let scheduler = &config.scheduler;
+    assert!(!scheduler.run_on_startup);
+    assert_eq!(
+        scheduler.job(Dataset::Swaps).schedule.to_string(),
+        "0 */30 * * * *"
+    );
+    assert!(!scheduler.job(Dataset::Runepool).enabled);
+    assert!(scheduler.job(Dataset::Depths).enabled);
+    assert_eq!(
+        scheduler.job(Dataset::Depths).schedule.to_string(),
+        "0 0 */23 * * *"
+    );
+
+    let reconcile = scheduler.reconcile.as_ref().unwrap();
+    assert_eq!(reconcile.window_hours, 24);
+    assert!(reconcile.repair);
+}
+
+#[test]
+fn reconcile_window_must_be_positive() {
+    for hours in [0, -1] {
+        let config = parse(&format!(
+            r#"
+            [scheduler.reconcile]
+            schedule = "0 0 3 * * *"
+            window_hours = {}
+            "#,
+            hours
+        ));
+        let err = config.validate().unwrap_err();
+        assert!(
+            err.message.contains("RECONCILE_WINDOW_HOURS"),
+            "{}",
+            err.message
+        );
+    }
+}
@@ -0,0 +1,85 @@
+mod common;
+
+use axum::http::{header, HeaderMap, Method, StatusCode};
+use common::{send, test_config, test_state};
+use midguard_api::{
+    config::app::{AppConfig, CorsPreset},
+    routes,
+};
+
+const DASHBOARD: &str = "https://dashboard.example.com";
+
+fn config_with_origins(origins: &[&str]) -> AppConfig {
+    let mut config = test_config();
+    config.cors.allowed_origins = origins.iter().map(|o| o.to_string()).collect();
+    config
+}
+
+/// Sends the preflight a browser makes before a `GET` with an API key from `origin`.
+async fn preflight(config: AppConfig, origin: &str) -> (StatusCode, HeaderMap) {
+    let (status, headers, _) = send(
+        routes::router(test_state(config)),
+        Method::OPTIONS,
+        "/swap-history",
+        &[
+            (header::ORIGIN, origin),
+            (header::ACCESS_CONTROL_REQUEST_METHOD, "GET"),
+            (header::ACCESS_CONTROL_REQUEST_HEADERS, "x-api-key"),
+        ],
+        None,
+    )
+    .await;
+    (status, headers)
+}
+
+#[tokio::test]
+async fn other_origins_are_refused_by_default() {
+    let (_, headers) = preflight(test_config(), DASHBOARD).await;
+
+    assert!(headers.get(header::ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+}
+
+#[tokio::test]
+async fn configured_origins_get_their_preflights_answered() {
+    let config = config_with_origins(&[DASHBOARD]);
+
+    let (status, headers) = preflight(config.clone(), DASHBOARD).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], DASHBOARD);
+    assert_eq!(
+        headers[header::ACCESS_CONTROL_ALLOW_METHODS],
+        "GET,POST,DELETE"
+    );
+    assert!(headers[header::ACCESS_CONTROL_ALLOW_HEADERS]
+        .to_str()
+        .unwrap()
+        .contains("x-api-key"));
+    assert_eq!(headers[header::ACCESS_CONTROL_MAX_AGE], "3600");
+
+    let (_, headers) = preflight(config, "https://elsewhere.example.com").await;
+    assert!(headers.get(header::ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+}
+
+#[tokio::test]
+async fn the_permissive_preset_allows_any_origin() {
+    let mut config = test_config();
+    config.cors.preset = CorsPreset::Permissive;
+
+    let (status, headers) = preflight(config, "http://localhost:5173").await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+}
+
+#[tokio::test]
+async fn invalid_origins_fail_the_config() {
+    for origins in [
+        &["dashboard.example.com"][..],
+        &["https://a.example/"],
+        &["*", DASHBOARD],
+    ] {
+        let error = config_with_origins(origins).validate().unwrap_err();
+        assert!(error.message.contains("CORS"), "{}", error.message);
+    }
+    config_with_origins(&["*"]).validate().unwrap();
+}
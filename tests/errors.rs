@@ -0,0 +1,97 @@
+use axum::{body::to_bytes, http::StatusCode, response::IntoResponse};
+use midguard_api::error::AppError;
+use serde_json::Value;
+use sqlx::error::{DatabaseError, ErrorKind};
+use std::{borrow::Cow, error::Error, fmt};
+
+/// A Postgres error with just a SQLSTATE, as the driver would report it.
+#[derive(Debug)]
+struct PgError(&'static str);
+
+impl fmt::Display for PgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "error {}", self.0)
+    }
+}
+
+impl Error for PgError {}
+
+impl DatabaseError for PgError {
+    fn message(&self) -> &str {
+        self.0
+    }
+
+    fn code(&self) -> Option<Cow<'_, str>> {
+        Some(Cow::Borrowed(self.0))
+    }
+
+    fn as_error(&self) -> &(dyn Error + Send + Sync + 'static) {
+        self
+    }
+
+    fn as_error_mut(&mut self) -> &mut (dyn Error + Send + Sync + 'static) {
+        self
+    }
+
+    fn into_error(self: Box<Self>) -> Box<dyn Error + Send + Sync + 'static> {
+        self
+    }
+
+    fn kind(&self) -> ErrorKind {
+        match self.0 {
+            "23505" => ErrorKind::UniqueViolation,
+            "23503" => ErrorKind::ForeignKeyViolation,
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+fn status(code: &'static str) -> StatusCode {
+    AppError::from(sqlx::Error::Database(Box::new(PgError(code)))).status()
+}
+
+#[test]
+fn database_errors_are_only_unavailable_when_the_database_is() {
+    assert_eq!(status("23505"), StatusCode::CONFLICT);
+    assert_eq!(status("22007"), StatusCode::BAD_REQUEST);
+    assert_eq!(status("42703"), StatusCode::BAD_REQUEST);
+    assert_eq!(status("23503"), StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(status("42601"), StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(status("08006"), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(status("53300"), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(status("57P01"), StatusCode::SERVICE_UNAVAILABLE);
+
+    for err in [sqlx::Error::PoolTimedOut, sqlx::Error::PoolClosed] {
+        assert_eq!(
+            AppError::from(err).status(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+    }
+}
+
+#[tokio::test]
+async fn server_errors_do_not_leak_the_driver_message() {
+    let cases = [
+        (
+            sqlx::Error::Database(Box::new(PgError("42601"))),
+            "Internal server error",
+        ),
+        (
+            sqlx::Error::PoolTimedOut,
+            "The database is unavailable, try again later",
+        ),
+    ];
+    for (err, message) in cases {
+        let response = AppError::from(err).into_response();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["message"], message);
+    }
+
+    let response =
+        AppError::from(sqlx::Error::Database(Box::new(PgError("23505")))).into_response();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["code"], "conflict");
+    assert_ne!(body["message"], "Internal server error");
+}
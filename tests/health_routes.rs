@@ -0,0 +1,65 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{get, interval, test_config, test_state};
+use midguard_api::{config::app::AppConfig, model::price_history::PriceHistory};
+
+// Nothing listens on port 1, so the database checks fail right away
+fn unreachable_database() -> AppConfig {
+    let mut config = test_config();
+    config.database.url = "postgres://127.0.0.1:1/midguard_test".to_string();
+    config.database.acquire_timeout_secs = 1;
+    config
+}
+
+#[tokio::test]
+async fn healthz_answers_without_a_database() {
+    let state = test_state(unreachable_database());
+
+    let (status, body) = get(&state, "/healthz").await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["status"], "ok");
+}
+
+#[tokio::test]
+async fn readyz_fails_while_the_database_is_unreachable() {
+    let state = test_state(unreachable_database());
+
+    let (status, body) = get(&state, "/readyz").await;
+
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["ready"], false);
+    assert_eq!(body["checks"][0]["name"], "database");
+    assert_eq!(body["checks"][0]["status"], "failed");
+    assert_eq!(body["checks"][1]["name"], "migrations");
+    assert_eq!(body["checks"][1]["status"], "skipped");
+}
+
+#[tokio::test]
+async fn readyz_checks_freshness_when_configured() {
+    let mut config = unreachable_database();
+    config.health.max_data_age_secs = Some(3600);
+    let state = test_state(config);
+    // November 2024 is far more than an hour ago
+    let row = PriceHistory {
+        start_time: interval(0),
+        end_time: interval(1),
+        ..Default::default()
+    };
+    state.price_history.save_batch(&[row]).await.unwrap();
+
+    let (_, body) = get(&state, "/readyz").await;
+
+    let checks = body["checks"].as_array().unwrap();
+    let depths = checks
+        .iter()
+        .find(|check| check["name"] == "freshness:depths")
+        .expect("a freshness check per dataset");
+    assert_eq!(depths["status"], "failed");
+    let swaps = checks
+        .iter()
+        .find(|check| check["name"] == "freshness:swaps")
+        .unwrap();
+    assert_eq!(swaps["message"], "no data synced yet");
+}
@@ -0,0 +1,583 @@
+mod common;
+
+use axum::http::{header, StatusCode};
+use chrono::{DateTime, Utc};
+use common::{get, get_with, interval, test_config, test_state, test_state_with, START};
+use midguard_api::{
+    cronjobs::dataset::Dataset,
+    model::{
+        earning_history::EarningHistory, earning_history_pool::EarningHistoryPool,
+        price_history::PriceHistory, rune_pool::Runepool, swap_history::SwapHistory,
+        sync_run::SyncRange,
+    },
+    repository::{memory::bucket_start, Repositories},
+    routes::REQUEST_ID_HEADER,
+    state::AppState,
+    utils::{interval::interval_trunc, validation::INTERVALS},
+};
+use serde_json::Value;
+use sqlx::PgPool;
+
+async fn seed_prices(state: &AppState, depths: &[(i64, i64)]) {
+    let rows: Vec<PriceHistory> = depths
+        .iter()
+        .map(|&(n, asset_depth)| PriceHistory {
+            start_time: interval(n),
+            end_time: interval(n + 1),
+            asset_depth,
+            ..Default::default()
+        })
+        .collect();
+    state.price_history.save_batch(&rows).await.unwrap();
+}
+
+fn field(rows: &Value, name: &str) -> Vec<Value> {
+    rows.as_array()
+        .expect("a list of rows")
+        .iter()
+        .map(|row| row[name].clone())
+        .collect()
+}
+
+#[tokio::test]
+async fn depth_history_returns_saved_rows() {
+    let state = test_state(test_config());
+    seed_prices(&state, &[(0, 100), (1, 200), (2, 300)]).await;
+
+    let (status, body) = get(&state, "/depth-history").await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(field(&body, "id"), [1, 2, 3]);
+    assert_eq!(body[0]["startTime"], "2024-11-01T00:00:00Z");
+    assert_eq!(body[0]["endTime"], "2024-11-01T00:05:00Z");
+    assert_eq!(body[2]["assetDepth"], 300);
+}
+
+#[tokio::test]
+async fn depth_history_filters_by_asset_depth() {
+    let state = test_state(test_config());
+    seed_prices(&state, &[(0, 100), (1, 200), (2, 300)]).await;
+
+    let (_, body) = get(&state, "/depth-history?asset_depth_gt=150").await;
+    assert_eq!(field(&body, "assetDepth"), [200, 300]);
+
+    let (_, body) = get(
+        &state,
+        "/depth-history?asset_depth_gt=150&asset_depth_lt=250",
+    )
+    .await;
+    assert_eq!(field(&body, "assetDepth"), [200]);
+
+    let (_, body) = get(&state, "/depth-history?asset_depth_eq=100").await;
+    assert_eq!(field(&body, "assetDepth"), [100]);
+}
+
+#[tokio::test]
+async fn depth_history_filters_by_date_range() {
+    let state = test_state(test_config());
+    // The last interval of Nov 1st ends at midnight, the first of Nov 2nd after it
+    seed_prices(&state, &[(0, 100), (287, 200), (288, 300)]).await;
+
+    let (status, body) = get(&state, "/depth-history?date_range=2024-11-01,2024-11-02").await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(field(&body, "assetDepth"), [100, 200]);
+}
+
+#[tokio::test]
+async fn depth_history_sorts_and_paginates() {
+    let state = test_state(test_config());
+    seed_prices(&state, &[(0, 500), (1, 100), (2, 400), (3, 200), (4, 300)]).await;
+
+    let (status, body) = get(
+        &state,
+        "/depth-history?sort_by=asset_depth&order=desc&limit=2&page=2",
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(field(&body, "assetDepth"), [300, 200]);
+
+    let (_, body) = get(&state, "/depth-history?sort_by=asset_depth&count=2").await;
+    assert_eq!(field(&body, "assetDepth"), [100, 200]);
+
+    let (_, body) = get(
+        &state,
+        "/depth-history?sort_by=start_time&order=desc&limit=2",
+    )
+    .await;
+    assert_eq!(field(&body, "assetDepth"), [300, 200]);
+}
+
+#[tokio::test]
+async fn depth_history_interval_keeps_one_row_per_bucket() {
+    let state = test_state(test_config());
+    let depths: Vec<(i64, i64)> = (0..30).map(|n| (n, n)).collect();
+    seed_prices(&state, &depths).await;
+
+    let (_, body) = get(&state, "/depth-history?interval=hour").await;
+
+    assert_eq!(
+        field(&body, "startTime"),
+        [
+            "2024-11-01T00:00:00Z",
+            "2024-11-01T01:00:00Z",
+            "2024-11-01T02:00:00Z"
+        ]
+    );
+}
+
+/// The `date_trunc` expressions of the Postgres repositories against the buckets of
+/// the in-memory ones. The SQL side needs a database, it runs when
+/// `TEST_DATABASE_URL` is set.
+#[tokio::test]
+async fn interval_buckets_agree_between_backends() {
+    let bucket = |time: &str, interval| bucket_start(time.parse().unwrap(), interval);
+    assert_eq!(
+        bucket("2024-11-01T00:17:42Z", "5min"),
+        "2024-11-01T00:15:00Z".parse::<DateTime<Utc>>().unwrap()
+    );
+    assert_eq!(
+        bucket("2024-11-01T00:59:59Z", "5min"),
+        "2024-11-01T00:55:00Z".parse::<DateTime<Utc>>().unwrap()
+    );
+
+    let Ok(url) = std::env::var("TEST_DATABASE_URL") else {
+        return;
+    };
+    let pool = PgPool::connect(&url).await.expect("test database");
+    let times = [
+        "2024-11-01T00:00:00Z",
+        "2024-11-01T00:17:42Z",
+        "2024-11-01T00:59:59Z",
+        "2024-11-03T23:55:00Z",
+        "2024-12-31T12:30:00Z",
+    ];
+    for interval in INTERVALS {
+        let sql = format!(
+            "SELECT {} FROM (SELECT $1::timestamptz AS start_time) t",
+            interval_trunc(interval)
+        );
+        for time in times {
+            let stored: DateTime<Utc> = sqlx::query_scalar(&sql)
+                .bind(time.parse::<DateTime<Utc>>().unwrap())
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+            assert_eq!(
+                stored,
+                bucket(time, interval),
+                "{} bucket of {}",
+                interval,
+                time
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn swap_history_sorts_and_paginates() {
+    let state = test_state(test_config());
+    let rows: Vec<SwapHistory> = [5, 1, 4, 2, 3]
+        .into_iter()
+        .enumerate()
+        .map(|(n, total_count)| SwapHistory {
+            start_time: interval(n as i64),
+            end_time: interval(n as i64 + 1),
+            total_count,
+            ..Default::default()
+        })
+        .collect();
+    state.swap_history.save_batch(&rows).await.unwrap();
+
+    let (status, body) = get(
+        &state,
+        "/swap-history?sort_by=total_count&order=desc&limit=2&page=2",
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(field(&body, "total_count"), [3, 2]);
+
+    let (_, body) = get(&state, "/swap-history?sort_by=total_count&count=2").await;
+    assert_eq!(field(&body, "total_count"), [1, 2]);
+
+    let (_, body) = get(&state, "/swap-history?total_count_gt=3").await;
+    assert_eq!(field(&body, "total_count"), [5, 4]);
+}
+
+#[tokio::test]
+async fn swap_history_rejects_unknown_sort_column() {
+    let state = test_state(test_config());
+    state
+        .swap_history
+        .save_batch(&[SwapHistory::default()])
+        .await
+        .unwrap();
+
+    let (status, body) = get(&state, "/swap-history?sort_by=not_a_column").await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "validation_error");
+    assert_eq!(body["details"][0]["field"], "sort_by");
+}
+
+#[tokio::test]
+async fn malformed_query_parameters_are_json_validation_errors() {
+    let state = test_state(test_config());
+    seed_prices(&state, &[(0, 100)]).await;
+
+    let (status, body) = get(&state, "/swap-history?limit=ten").await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["statusCode"], 400);
+    assert_eq!(body["code"], "validation_error");
+    assert_eq!(body["details"][0]["field"], "limit");
+
+    let (status, body) = get(&state, "/depth-history?date_range=2024-13-01,2024-11-02").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["details"][0]["field"], "date_range");
+}
+
+#[tokio::test]
+async fn invalid_query_parameters_are_reported_together() {
+    let state = test_state(test_config());
+
+    let (status, body) = get(
+        &state,
+        "/earning-history?interval=fortnight&order=up&count=500&limit=5&date_range=2024-11-02,2024-11-01",
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "validation_error");
+    assert_eq!(
+        field(&body["details"], "field"),
+        ["interval", "order", "date_range", "count", "count"]
+    );
+
+    // Wider than the old `i8`, within the page size
+    let (status, _) = get(&state, "/earning-history?count=200").await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (_, body) = get(&state, "/runepool-history?limit=0&page=0").await;
+    assert_eq!(field(&body["details"], "field"), ["limit", "page"]);
+}
+
+/// The analytics are Postgres-only, so a 400 here means nothing was queried.
+#[tokio::test]
+async fn analytics_parameters_are_validated_before_querying() {
+    let state = test_state(test_config());
+    let query = "interval=fortnight&date_range=2024-11-02,2024-11-01";
+
+    for route in [
+        "/swap-history/breakdown",
+        "/swap-history/slippage",
+        "/depth-history/synths",
+        "/runepool-history/analytics",
+        "/earning-history/bonding",
+        "/rune-price",
+    ] {
+        let (status, body) = get(&state, &format!("{}?{}", route, query)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", route);
+        assert_eq!(
+            field(&body["details"], "field"),
+            ["interval", "date_range"],
+            "{}",
+            route
+        );
+    }
+
+    let (_, body) = get(
+        &state,
+        "/depth-history/synths?pool=ETH.ETH&max_synth_per_pool_depth=20000&date_range=2024-11-01",
+    )
+    .await;
+    assert_eq!(
+        field(&body["details"], "field"),
+        ["pool", "date_range", "max_synth_per_pool_depth"]
+    );
+
+    let (_, body) = get(&state, "/rune-price?threshold=-1").await;
+    assert_eq!(field(&body["details"], "field"), ["threshold"]);
+}
+
+#[tokio::test]
+async fn runepool_history_filters_and_limits() {
+    let state = test_state(test_config());
+    let rows: Vec<Runepool> = (0..4)
+        .map(|n| Runepool {
+            start_time: interval(n),
+            end_time: interval(n + 1),
+            count: 10 + n,
+            units: 1000 * (n + 1),
+            ..Default::default()
+        })
+        .collect();
+    state.rune_pool.save_batch(&rows).await.unwrap();
+
+    let (_, body) = get(&state, "/runepool-history?count=2").await;
+    assert_eq!(field(&body, "count"), [10, 11]);
+
+    let (_, body) = get(
+        &state,
+        "/runepool-history?units_gt=2000&sort_by=units&order=desc",
+    )
+    .await;
+    assert_eq!(field(&body, "units"), [4000, 3000]);
+}
+
+/// Reconciliation repairs go through `replace_intervals`, which swaps out only the
+/// intervals it is given.
+#[tokio::test]
+async fn replaced_intervals_swap_out_the_stored_rows() {
+    let repositories = Repositories::in_memory();
+    let row = |n, units| Runepool {
+        start_time: interval(n),
+        end_time: interval(n + 1),
+        units,
+        ..Default::default()
+    };
+    let saved = repositories
+        .rune_pool
+        .save_batch(&[row(0, 100), row(1, 200), row(2, 300)])
+        .await
+        .unwrap();
+
+    let replaced = repositories
+        .rune_pool
+        .replace_intervals(&[row(1, 250)])
+        .await
+        .unwrap();
+    assert!(replaced[0] > *saved.iter().max().unwrap());
+
+    let range = SyncRange {
+        from: interval(0).timestamp(),
+        to: interval(3).timestamp(),
+    };
+    let stored = repositories.rune_pool.find_in_range(&range).await.unwrap();
+    let units: Vec<i64> = stored.iter().map(|r| r.units).collect();
+    assert_eq!(units, [100, 250, 300]);
+    assert_eq!(stored[1].id, Some(replaced[0]));
+}
+
+#[tokio::test]
+async fn earning_history_filters_nested_pools() {
+    let state = test_state(test_config());
+    let pool = |name: &str, earnings: i64| EarningHistoryPool {
+        pool: name.to_string(),
+        earnings,
+        ..Default::default()
+    };
+    let rows: Vec<EarningHistory> = (0..2)
+        .map(|n| EarningHistory {
+            start_time: interval(n),
+            end_time: interval(n + 1),
+            earnings: 100 * (n + 1),
+            pools: Some(vec![pool("BTC.BTC", 10), pool("ETH.ETH", 20)]),
+            ..Default::default()
+        })
+        .collect();
+    state.earning_history.save_batch(&rows).await.unwrap();
+
+    let (status, body) = get(&state, "/earning-history?pool_eq=BTC.BTC").await;
+    assert_eq!(status, StatusCode::OK);
+    for row in body.as_array().unwrap() {
+        assert_eq!(field(&row["pools"], "pool"), ["BTC.BTC"]);
+        assert_eq!(row["pools"][0]["earnings_history_id"], row["id"]);
+    }
+
+    let (_, body) = get(&state, "/earning-history?earnings_gt=100").await;
+    assert_eq!(field(&body, "earnings"), [200]);
+    assert_eq!(field(&body[0]["pools"], "pool"), ["BTC.BTC", "ETH.ETH"]);
+}
+
+#[tokio::test]
+async fn last_synced_follows_the_newest_row() {
+    let state = test_state(test_config());
+    assert_eq!(Dataset::Runepool.last_synced(&state).await, None);
+
+    let rows: Vec<Runepool> = [2, 0, 1]
+        .into_iter()
+        .map(|n| Runepool {
+            start_time: interval(n),
+            end_time: interval(n + 1),
+            ..Default::default()
+        })
+        .collect();
+    state.rune_pool.save_batch(&rows).await.unwrap();
+
+    assert_eq!(
+        Dataset::Runepool.last_synced(&state).await,
+        Some(START + 900)
+    );
+}
+
+#[tokio::test]
+async fn responses_carry_a_request_id() {
+    let state = test_state(test_config());
+    let (_, headers, _) = get_with(
+        &state,
+        "/runepool-history",
+        &[(REQUEST_ID_HEADER, "from-the-client")],
+    )
+    .await;
+    assert_eq!(headers[REQUEST_ID_HEADER], "from-the-client");
+
+    let (_, headers, _) = get_with(&state, "/runepool-history", &[]).await;
+    let generated = headers[REQUEST_ID_HEADER].to_str().unwrap();
+    assert!(uuid::Uuid::parse_str(generated).is_ok(), "{}", generated);
+}
+
+#[tokio::test]
+async fn metrics_count_requests_by_route_and_track_freshness() {
+    let state = test_state(test_config());
+    seed_prices(&state, &[(0, 100)]).await;
+    get(&state, "/depth-history").await;
+    get(&state, "/depth-history?interval=fortnight").await;
+
+    let (status, body) = get(&state, "/metrics").await;
+
+    assert_eq!(status, StatusCode::OK);
+    let metrics = body.as_str().expect("text metrics");
+    for line in [
+        r#"http_requests_total{method="GET",route="/depth-history",status="200"} 1"#,
+        r#"http_requests_total{method="GET",route="/depth-history",status="400"} 1"#,
+        r#"db_query_duration_seconds_count{operation="get_all_price_history",service="price_history"} 2"#,
+        r#"sync_last_interval_timestamp_seconds{dataset="depths",pool="BTC.BTC"} 1730419500"#,
+    ] {
+        assert!(metrics.contains(line), "missing {} in\n{}", line, metrics);
+    }
+}
+
+#[tokio::test]
+async fn history_responses_are_revalidated_by_etag() {
+    let repositories = Repositories::in_memory();
+    let state = test_state_with(test_config(), repositories.clone());
+    seed_prices(&state, &[(0, 100), (1, 200)]).await;
+
+    let (status, headers, _) = get_with(&state, "/depth-history", &[]).await;
+    assert_eq!(status, StatusCode::OK);
+    let etag = headers[header::ETAG].to_str().unwrap().to_string();
+    assert!(etag.starts_with("W/\""), "{}", etag);
+    assert_eq!(
+        headers[header::LAST_MODIFIED],
+        "Fri, 01 Nov 2024 00:10:00 GMT"
+    );
+
+    let (status, headers, body) =
+        get_with(&state, "/depth-history", &[(header::IF_NONE_MATCH, &etag)]).await;
+    assert_eq!(status, StatusCode::NOT_MODIFIED);
+    assert_eq!(headers[header::ETAG], etag.as_str());
+    assert_eq!(body, "");
+
+    // Another query is another resource
+    let (status, _, _) = get_with(
+        &state,
+        "/depth-history?asset_depth_gt=150",
+        &[(header::IF_NONE_MATCH, &etag)],
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // A newly synced interval invalidates the cached copy
+    seed_prices(&state, &[(2, 300)]).await;
+    let (status, headers, _) =
+        get_with(&state, "/depth-history", &[(header::IF_NONE_MATCH, &etag)]).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_ne!(headers[header::ETAG], etag.as_str());
+
+    // So does a stored interval rewritten in place, e.g. by a reconcile repair
+    let etag = headers[header::ETAG].to_str().unwrap().to_string();
+    let rewritten = PriceHistory {
+        start_time: interval(1),
+        end_time: interval(2),
+        asset_depth: 250,
+        ..Default::default()
+    };
+    repositories
+        .price_history
+        .replace_intervals(&[rewritten])
+        .await
+        .unwrap();
+    let (status, headers, _) =
+        get_with(&state, "/depth-history", &[(header::IF_NONE_MATCH, &etag)]).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_ne!(headers[header::ETAG], etag.as_str());
+}
+
+#[tokio::test]
+async fn revalidation_does_not_skip_parameter_validation() {
+    let state = test_state(test_config());
+    seed_prices(&state, &[(0, 100), (1, 200)]).await;
+
+    let (status, _, _) = get_with(&state, "/depth-history", &[(header::IF_NONE_MATCH, "*")]).await;
+    assert_eq!(status, StatusCode::NOT_MODIFIED);
+
+    for (name, value) in [
+        (header::IF_NONE_MATCH, "*"),
+        (header::IF_MODIFIED_SINCE, "Fri, 01 Nov 2024 00:10:00 GMT"),
+    ] {
+        let (status, _, body) = get_with(
+            &state,
+            "/depth-history?interval=fortnight",
+            &[(name, value)],
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], "validation_error");
+    }
+}
+
+#[tokio::test]
+async fn history_responses_are_revalidated_by_date() {
+    let state = test_state(test_config());
+    seed_prices(&state, &[(0, 100), (1, 200)]).await;
+
+    let (status, _, _) = get_with(
+        &state,
+        "/depth-history",
+        &[(header::IF_MODIFIED_SINCE, "Fri, 01 Nov 2024 00:10:00 GMT")],
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_MODIFIED);
+
+    let (status, _, _) = get_with(
+        &state,
+        "/depth-history",
+        &[(header::IF_MODIFIED_SINCE, "Fri, 01 Nov 2024 00:05:00 GMT")],
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn empty_tables_are_not_cached() {
+    let state = test_state(test_config());
+
+    let (status, headers, _) = get_with(&state, "/swap-history", &[]).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert!(headers.get(header::ETAG).is_none());
+}
+
+#[tokio::test]
+async fn responses_are_compressed_as_negotiated() {
+    let state = test_state(test_config());
+    seed_prices(&state, &[(0, 100), (1, 200), (2, 300)]).await;
+
+    for encoding in ["gzip", "br", "zstd"] {
+        let (_, headers, _) = get_with(
+            &state,
+            "/depth-history",
+            &[(header::ACCEPT_ENCODING, encoding)],
+        )
+        .await;
+        assert_eq!(headers[header::CONTENT_ENCODING], encoding);
+        assert!(headers
+            .get_all(header::VARY)
+            .iter()
+            .any(|vary| vary == "accept-encoding"));
+    }
+
+    let (_, headers, _) = get_with(&state, "/depth-history", &[]).await;
+    assert!(headers.get(header::CONTENT_ENCODING).is_none());
+}
@@ -0,0 +1,36 @@
+use midguard_api::service::lp_simulator_service::liquidity_units;
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!(
+        (actual - expected).abs() < 1e-9,
+        "expected {}, got {}",
+        expected,
+        actual
+    );
+}
+
+#[test]
+fn symmetric_deposits_get_their_share_of_the_units() {
+    // 10% of both sides of the pool
+    assert_close(liquidity_units(1000.0, 1000.0, 500.0, 100.0, 50.0), 100.0);
+}
+
+#[test]
+fn single_sided_deposits_pay_the_slip() {
+    // Half the units of a symmetric deposit of the same value, less the slip of 100 / 1100
+    assert_close(
+        liquidity_units(1000.0, 1000.0, 1000.0, 100.0, 0.0),
+        50.0 * (1.0 - 100.0 / 1100.0),
+    );
+    assert_close(
+        liquidity_units(1000.0, 1000.0, 1000.0, 0.0, 100.0),
+        50.0 * (1.0 - 100.0 / 1100.0),
+    );
+}
+
+#[test]
+fn deposits_into_an_empty_pool_get_a_unit_per_rune() {
+    let units = liquidity_units(0.0, 0.0, 0.0, 100.0, 50.0);
+    assert!(units.is_finite());
+    assert_close(units, 100.0);
+}
@@ -0,0 +1,324 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{
+    get, interval, midgard::MockMidgard, midgard::Scripted, midgard_config, test_state, START,
+};
+use midguard_api::{
+    client::proxy,
+    cronjobs::dataset::Dataset,
+    error::ErrorKind,
+    model::{
+        earning_history::EarningHistory,
+        price_history::PriceHistory,
+        rune_pool::Runepool,
+        swap_history::SwapHistory,
+        sync_run::{SyncProgress, SyncRange},
+    },
+    state::AppState,
+};
+
+// The 24 recorded intervals, 2 hours from START
+const RANGE: SyncRange = SyncRange {
+    from: START,
+    to: START + 24 * 300,
+};
+
+async fn setup() -> (MockMidgard, AppState) {
+    let midgard = MockMidgard::start().await;
+    let state = test_state(midgard_config(&midgard.url));
+    (midgard, state)
+}
+
+#[tokio::test]
+async fn fetches_every_dataset_page_by_page() {
+    let (midgard, state) = setup().await;
+    let pages = vec![START, START + 3000, START + 6000];
+
+    let progress = SyncProgress::default();
+    let depths = proxy::fetch_price_history(&state, &RANGE, &progress)
+        .await
+        .unwrap();
+    assert_eq!(depths.len(), midgard.interval_count(Dataset::Depths));
+    assert_eq!(midgard.requested_from(Dataset::Depths), pages);
+    assert_eq!(progress.report().pages_fetched, 3);
+    assert_eq!(progress.cursor(), RANGE.to);
+
+    let progress = SyncProgress::default();
+    let earnings = proxy::fetch_earning_history(&state, &RANGE, &progress)
+        .await
+        .unwrap();
+    assert_eq!(earnings.len(), midgard.interval_count(Dataset::Earnings));
+    assert_eq!(earnings[0].pools.len(), 3);
+    assert_eq!(midgard.requested_from(Dataset::Earnings), pages);
+
+    let swaps = proxy::fetch_swap_history(&state, &RANGE, &progress)
+        .await
+        .unwrap();
+    assert_eq!(swaps.len(), midgard.interval_count(Dataset::Swaps));
+    assert_eq!(midgard.requested_from(Dataset::Swaps), pages);
+
+    let runepool = proxy::fetch_runepool_history(&state, &RANGE, &progress)
+        .await
+        .unwrap();
+    assert_eq!(runepool.len(), midgard.interval_count(Dataset::Runepool));
+    assert_eq!(midgard.requested_from(Dataset::Runepool), pages);
+}
+
+#[tokio::test]
+async fn stops_at_the_end_of_the_range() {
+    let (midgard, state) = setup().await;
+    let range = SyncRange {
+        from: START + 600,
+        to: START + 1200,
+    };
+
+    let swaps = proxy::fetch_swap_history(&state, &range, &SyncProgress::default())
+        .await
+        .unwrap();
+
+    // Midgard returns full pages, the sync drops what is past the range before saving
+    assert_eq!(swaps[0].start_time, (START + 600).to_string());
+    assert_eq!(midgard.requested_from(Dataset::Swaps), [START + 600]);
+}
+
+#[tokio::test]
+async fn replays_the_fixtures_into_the_history_endpoints() {
+    let (_midgard, state) = setup().await;
+    let progress = SyncProgress::default();
+
+    let depths = proxy::fetch_price_history(&state, &RANGE, &progress)
+        .await
+        .unwrap();
+    let rows: Vec<PriceHistory> = depths
+        .into_iter()
+        .map(PriceHistory::try_from)
+        .collect::<Result<_, _>>()
+        .unwrap();
+    state.price_history.save_batch(&rows).await.unwrap();
+
+    let earnings = proxy::fetch_earning_history(&state, &RANGE, &progress)
+        .await
+        .unwrap();
+    let rows: Vec<EarningHistory> = earnings
+        .into_iter()
+        .map(EarningHistory::try_from)
+        .collect::<Result<_, _>>()
+        .unwrap();
+    state.earning_history.save_batch(&rows).await.unwrap();
+
+    let swaps = proxy::fetch_swap_history(&state, &RANGE, &progress)
+        .await
+        .unwrap();
+    let rows: Vec<SwapHistory> = swaps
+        .into_iter()
+        .map(SwapHistory::try_from)
+        .collect::<Result<_, _>>()
+        .unwrap();
+    state.swap_history.save_batch(&rows).await.unwrap();
+
+    let runepool = proxy::fetch_runepool_history(&state, &RANGE, &progress)
+        .await
+        .unwrap();
+    let rows: Vec<Runepool> = runepool
+        .into_iter()
+        .map(Runepool::try_from)
+        .collect::<Result<_, _>>()
+        .unwrap();
+    state.rune_pool.save_batch(&rows).await.unwrap();
+
+    let (_, body) = get(&state, "/depth-history?interval=hour").await;
+    assert_eq!(body.as_array().unwrap().len(), 2);
+    assert_eq!(body[0]["assetDepth"], 79708789352_i64);
+
+    let (_, body) = get(&state, "/earning-history?count=1&pool_eq=ETH.ETH").await;
+    assert_eq!(body[0]["pools"][0]["pool"], "ETH.ETH");
+
+    let (_, body) = get(&state, "/swap-history?count=100").await;
+    assert_eq!(body.as_array().unwrap().len(), 24);
+
+    for dataset in Dataset::ALL {
+        assert_eq!(dataset.last_synced(&state).await, Some(RANGE.to));
+    }
+}
+
+#[tokio::test]
+async fn retries_rate_limits_and_server_errors() {
+    let (midgard, state) = setup().await;
+    midgard.script(
+        Dataset::Earnings,
+        [
+            Scripted::Status(StatusCode::TOO_MANY_REQUESTS),
+            Scripted::Page,
+            Scripted::Status(StatusCode::INTERNAL_SERVER_ERROR),
+            Scripted::Status(StatusCode::BAD_GATEWAY),
+        ],
+    );
+
+    let earnings = proxy::fetch_earning_history(&state, &RANGE, &SyncProgress::default())
+        .await
+        .unwrap();
+
+    assert_eq!(earnings.len(), 24);
+    let (_, metrics) = get(&state, "/metrics").await;
+    let metrics = metrics.as_str().unwrap();
+    for line in [
+        r#"midgard_requests_total{dataset="earnings",outcome="ok"} 3"#,
+        r#"midgard_requests_total{dataset="earnings",outcome="429"} 1"#,
+        r#"midgard_requests_total{dataset="earnings",outcome="500"} 1"#,
+    ] {
+        assert!(metrics.contains(line), "missing {} in\n{}", line, metrics);
+    }
+    assert_eq!(
+        midgard.requested_from(Dataset::Earnings),
+        [
+            START,
+            START,
+            START + 3000,
+            START + 3000,
+            START + 3000,
+            START + 6000
+        ]
+    );
+}
+
+#[tokio::test]
+async fn gives_up_after_the_configured_retries() {
+    let midgard = MockMidgard::start().await;
+    let mut config = midgard_config(&midgard.url);
+    config.midgard.max_retries = 2;
+    let state = test_state(config);
+    midgard.script(
+        Dataset::Depths,
+        [Scripted::Status(StatusCode::SERVICE_UNAVAILABLE); 3],
+    );
+
+    let error = proxy::fetch_price_history(&state, &RANGE, &SyncProgress::default())
+        .await
+        .unwrap_err();
+
+    assert_eq!(error.kind, ErrorKind::Upstream);
+    assert!(error.message.contains("503"), "{}", error.message);
+    assert_eq!(midgard.requested_from(Dataset::Depths).len(), 3);
+}
+
+#[tokio::test]
+async fn client_errors_are_not_retried() {
+    let (midgard, state) = setup().await;
+    midgard.script(Dataset::Swaps, [Scripted::Status(StatusCode::BAD_REQUEST)]);
+
+    let error = proxy::fetch_swap_history(&state, &RANGE, &SyncProgress::default())
+        .await
+        .unwrap_err();
+
+    assert_eq!(error.kind, ErrorKind::Upstream);
+    assert_eq!(midgard.requested_from(Dataset::Swaps), [START]);
+}
+
+#[tokio::test]
+async fn an_empty_page_ends_the_sync() {
+    let (midgard, state) = setup().await;
+    midgard.script(Dataset::Depths, [Scripted::Page, Scripted::EmptyPage]);
+
+    let depths = proxy::fetch_price_history(&state, &RANGE, &SyncProgress::default())
+        .await
+        .unwrap();
+
+    assert_eq!(depths.len(), 10);
+    assert_eq!(
+        midgard.requested_from(Dataset::Depths),
+        [START, START + 3000]
+    );
+}
+
+#[tokio::test]
+async fn a_malformed_end_time_fails_instead_of_panicking() {
+    let (midgard, state) = setup().await;
+    midgard.script(Dataset::Earnings, [Scripted::Malformed("endTime")]);
+
+    let error = proxy::fetch_earning_history(&state, &RANGE, &SyncProgress::default())
+        .await
+        .unwrap_err();
+
+    assert_eq!(error.kind, ErrorKind::Upstream);
+    assert!(
+        error.message.contains("Invalid endTime"),
+        "{}",
+        error.message
+    );
+}
+
+#[tokio::test]
+async fn malformed_numbers_fail_the_sync_instead_of_storing_zero() {
+    let (midgard, state) = setup().await;
+    midgard.script(Dataset::Runepool, [Scripted::Malformed("units")]);
+
+    let error = proxy::get_prev_2_months_runepool_history(&state, &RANGE, &SyncProgress::default())
+        .await
+        .unwrap_err();
+
+    assert_eq!(error.kind, ErrorKind::Upstream);
+    assert!(error.message.contains("Invalid units"), "{}", error.message);
+    assert_eq!(
+        state.rune_pool.get_last_update_timestamp().await.unwrap(),
+        None
+    );
+}
+
+#[tokio::test]
+async fn a_cancelled_sync_stops_before_the_next_page() {
+    let (midgard, state) = setup().await;
+    let progress = SyncProgress::default();
+    progress.cancel();
+
+    let error = proxy::fetch_swap_history(&state, &RANGE, &progress)
+        .await
+        .unwrap_err();
+
+    assert_eq!(error.kind, ErrorKind::Conflict);
+    assert!(midgard.requested_from(Dataset::Swaps).is_empty());
+}
+
+#[tokio::test]
+async fn a_shutdown_ends_the_sync_without_failing_it() {
+    let (midgard, state) = setup().await;
+    state.shutdown.trigger();
+
+    let swaps = proxy::fetch_swap_history(&state, &RANGE, &SyncProgress::default())
+        .await
+        .unwrap();
+
+    assert!(swaps.is_empty());
+    assert!(midgard.requested_from(Dataset::Swaps).is_empty());
+}
+
+#[tokio::test]
+async fn syncs_skip_the_intervals_already_stored() {
+    let (midgard, state) = setup().await;
+    let stored: Vec<SwapHistory> = (0..5)
+        .map(|n| SwapHistory {
+            start_time: interval(n),
+            end_time: interval(n + 1),
+            ..Default::default()
+        })
+        .collect();
+    state.swap_history.save_batch(&stored).await.unwrap();
+
+    let progress = SyncProgress::default();
+    proxy::get_prev_2_months_swap_history(&state, &RANGE, &progress)
+        .await
+        .unwrap();
+    let report = progress.report();
+    assert_eq!(report.rows_skipped, 5);
+    assert_eq!(
+        report.rows_inserted as usize,
+        midgard.interval_count(Dataset::Swaps) - 5
+    );
+
+    // Everything is stored now, a second sync has nothing left to insert
+    let progress = SyncProgress::default();
+    proxy::get_prev_2_months_swap_history(&state, &RANGE, &progress)
+        .await
+        .unwrap();
+    assert_eq!(progress.report().rows_inserted, 0);
+}